@@ -0,0 +1,75 @@
+// 探索で何度も呼ばれる関数の実行時間を計測する
+//
+// 例: cargo bench --bench search
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use rand::{Rng, SeedableRng, rngs};
+
+use thunder_rust::chapter3::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+use thunder_rust::chapter3::MazeState00::{ActionBuffer, MazeConfig, MazeState};
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+// 計測に使う盤面生成シード
+const SEED: u64 = 0;
+// advanceを続けて呼ぶ回数
+const ADVANCE_N: usize = 10000;
+
+// ビーム幅5で1ゲーム(100ターン)をプレイする
+fn bench_beam_search(c: &mut Criterion) {
+    c.bench_function("beam_search_100_turns", |b| {
+        b.iter(|| {
+            let mut state = MazeState::new(CONFIG, Some(SEED));
+            while !state.is_done() {
+                let action = beam_search_action_with_time_threshold(&state, 5, 10);
+                state.advance(action).expect("action must be legal");
+            }
+            black_box(state.game_score)
+        })
+    });
+}
+
+// 決まった合法手の列でadvanceをADVANCE_N回呼ぶ。盤面の生成と複製は計測に含めない
+fn bench_advance(c: &mut Criterion) {
+    let config = MazeConfig::new(30, 30, ADVANCE_N);
+    let initial_state = MazeState::new(config, Some(SEED));
+
+    let mut rng = rngs::StdRng::seed_from_u64(SEED);
+    let mut state = initial_state.clone();
+    let mut actions = Vec::with_capacity(ADVANCE_N);
+    while !state.is_done() {
+        let legal_actions = state.legal_actions();
+        let action = legal_actions[rng.gen_range(0..legal_actions.len())];
+        state.advance(action).expect("action must be legal");
+        actions.push(action);
+    }
+
+    c.bench_function("advance_10k", |b| {
+        b.iter_batched(
+            || initial_state.clone(),
+            |mut state| {
+                for &action in &actions {
+                    state.advance(action).expect("action must be legal");
+                }
+                state
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+// 合法手の取得をVecを返す場合とバッファに書き込む場合で比べる
+fn bench_legal_actions(c: &mut Criterion) {
+    let state = MazeState::new(CONFIG, Some(SEED));
+    c.bench_function("legal_actions", |b| b.iter(|| black_box(&state).legal_actions()));
+
+    let mut buf = ActionBuffer::new();
+    c.bench_function("legal_actions_into", |b| {
+        b.iter(|| {
+            black_box(&state).legal_actions_into(&mut buf);
+            buf.len()
+        })
+    });
+}
+
+criterion_group!(benches, bench_beam_search, bench_advance, bench_legal_actions);
+criterion_main!(benches);
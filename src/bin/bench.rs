@@ -0,0 +1,145 @@
+// 全てのアルゴリズムを同じ盤面生成シードと1手あたりの制限時間でプレイさせ、平均スコアと実行時間を比べる
+//
+// 例: cargo run --release --bin bench -- --first-seed 0 --games 100 --time-ms 10
+// --csv FILE を指定すると、アルゴリズムごとのスコアの分布をCSVとしても書き出す
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use thunder_rust::chapter3::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+use thunder_rust::chapter3::ChokudaiSearchWithTime07::chokudai_search_action_wirh_time_threshold;
+use thunder_rust::chapter3::MazeState00::{MazeConfig, MazeState};
+use thunder_rust::chapter3::MctsAction::mcts_action_with_time_threshold;
+use thunder_rust::common::game::greedy_action;
+use thunder_rust::common::stats::{export_scores_csv, ScoreSummary};
+
+const USAGE: &str = "usage: bench [--first-seed N] [--games N] [--time-ms N] [--csv FILE]";
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// コマンドライン引数で指定する設定
+struct Options {
+    first_seed: u64,  // 盤面生成シードを first_seed..first_seed + games とする
+    games: u64,
+    time_ms: usize,   // 1手あたりの制限時間
+    csv: Option<String>,  // スコアの分布を書き出すCSVファイル
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            first_seed: 0,
+            games: 100,
+            time_ms: 10,
+            csv: None,
+        }
+    }
+}
+
+// 数値を受け取るオプションの値を読む
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", name))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--first-seed" => options.first_seed = parse_value("--first-seed", args.next())?,
+            "--games" => options.games = parse_value("--games", args.next())?,
+            "--time-ms" => options.time_ms = parse_value("--time-ms", args.next())?,
+            "--csv" => options.csv = Some(parse_value("--csv", args.next())?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            other => return Err(format!("unknown option: {}", other)),
+        }
+    }
+
+    if options.games == 0 {
+        return Err("--games must be positive".to_string());
+    }
+
+    Ok(options)
+}
+
+type AIFunction = fn(&MazeState, usize) -> usize;
+
+struct StringAIPair {
+    name: &'static str,
+    ai: AIFunction,  // 盤面と1手あたりの制限時間(ms)から行動を決める
+}
+
+// 全ての盤面をプレイし、スコアの分布と全体の実行時間(秒)を返す
+fn run(ai: &StringAIPair, options: &Options) -> (ScoreSummary, f64) {
+    let start_time = Instant::now();
+    let mut scores = Vec::with_capacity(options.games as usize);
+
+    for seed in options.first_seed..options.first_seed + options.games {
+        let mut state = MazeState::new(CONFIG, Some(seed));
+        while !state.is_done() {
+            let action = (ai.ai)(&state, options.time_ms);
+            state.advance(action).expect("action must be legal");
+        }
+        scores.push(state.game_score as i64);
+    }
+
+    (ScoreSummary::from_scores(&scores), start_time.elapsed().as_secs_f64())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let ais = [
+        StringAIPair {
+            name: "greedy",
+            ai: |state, _| greedy_action(state).unwrap(),
+        },
+        StringAIPair {
+            name: "beam(width 5)",
+            ai: |state, time_ms| beam_search_action_with_time_threshold(state, 5, time_ms),
+        },
+        StringAIPair {
+            name: "chokudai(width 1)",
+            ai: |state, time_ms| chokudai_search_action_wirh_time_threshold(state, 1, CONFIG.end_turn, time_ms),
+        },
+        StringAIPair {
+            name: "mcts",
+            ai: mcts_action_with_time_threshold,
+        },
+    ];
+
+    println!(
+        "{}x{}, {} turns, seeds {}..{}, {} ms/move",
+        CONFIG.h,
+        CONFIG.w,
+        CONFIG.end_turn,
+        options.first_seed,
+        options.first_seed + options.games,
+        options.time_ms
+    );
+    println!("{:<20}{:>12}{:>12}", "algorithm", "mean score", "time(s)");
+    let mut results = Vec::with_capacity(ais.len());
+    for ai in &ais {
+        let (summary, elapsed) = run(ai, &options);
+        println!("{:<20}{:>12.2}{:>12.2}", ai.name, summary.mean, elapsed);
+        results.push((ai.name.to_string(), summary));
+    }
+
+    if let Some(path) = &options.csv {
+        if let Err(error) = export_scores_csv(path, &results) {
+            eprintln!("cannot write {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
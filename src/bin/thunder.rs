@@ -0,0 +1,210 @@
+// アルゴリズム、盤面生成シード、盤面の大きさを実行時に指定してゲームを1回プレイする
+//
+// 例: cargo run --bin thunder -- --algo beam --seed 42 --width 10 --time-ms 10 --show
+// --record FILE でプレイした行動をリプレイファイルに書き出し、--replay FILE で同じゲームを再生する
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use thunder_rust::chapter3::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+use thunder_rust::chapter3::ChokudaiSearchWithTime07::chokudai_search_action_wirh_time_threshold;
+use thunder_rust::chapter3::MazeState00::{MazeConfig, MazeState};
+use thunder_rust::chapter3::MctsAction::mcts_action_with_time_threshold;
+use thunder_rust::common::game::greedy_action;
+use thunder_rust::common::replay::{read_replay, write_replay};
+
+const USAGE: &str = "usage: thunder [--algo greedy|beam|chokudai|mcts] [--seed N] [--width N] [--height N] [--end-turn N] [--time-ms N] [--show] [--record FILE] [--replay FILE]";
+
+// リプレイを再生するときの1ターンあたりの表示時間
+const REPLAY_FRAME_MS: u64 = 200;
+
+// 探索に使うアルゴリズム
+#[derive(Clone, Copy)]
+enum Algo {
+    Greedy,
+    Beam,
+    Chokudai,
+    Mcts,
+}
+
+// コマンドライン引数で指定する設定
+struct Options {
+    algo: Algo,
+    seed: u64,
+    width: usize,
+    height: Option<usize>,  // 指定しない場合はwidthと同じにする
+    end_turn: usize,
+    time_ms: usize,
+    show: bool,
+    record: Option<String>,  // プレイした行動を書き出すリプレイファイル
+    replay: Option<String>,  // 指定した場合はプレイせずにこのリプレイファイルを再生する
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            algo: Algo::Beam,
+            seed: 0,
+            width: 30,
+            height: None,
+            end_turn: 100,
+            time_ms: 10,
+            show: false,
+            record: None,
+            replay: None,
+        }
+    }
+}
+
+// 数値を受け取るオプションの値を読む
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", name))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--algo" => {
+                options.algo = match args.next().as_deref() {
+                    Some("greedy") => Algo::Greedy,
+                    Some("beam") => Algo::Beam,
+                    Some("chokudai") => Algo::Chokudai,
+                    Some("mcts") => Algo::Mcts,
+                    Some(other) => return Err(format!("unknown algorithm: {}", other)),
+                    None => return Err("--algo requires a value".to_string()),
+                }
+            }
+            "--seed" => options.seed = parse_value("--seed", args.next())?,
+            "--width" => options.width = parse_value("--width", args.next())?,
+            "--height" => options.height = Some(parse_value("--height", args.next())?),
+            "--end-turn" => options.end_turn = parse_value("--end-turn", args.next())?,
+            "--time-ms" => options.time_ms = parse_value("--time-ms", args.next())?,
+            "--show" => options.show = true,
+            "--record" => options.record = Some(args.next().ok_or("--record requires a value")?),
+            "--replay" => options.replay = Some(args.next().ok_or("--replay requires a value")?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            other => return Err(format!("unknown option: {}", other)),
+        }
+    }
+
+    if options.width == 0 || options.height == Some(0) {
+        return Err("board size must be positive".to_string());
+    }
+
+    Ok(options)
+}
+
+// 指定したアルゴリズムで行動を決定する
+fn select_action(options: &Options, state: &MazeState) -> usize {
+    match options.algo {
+        Algo::Greedy => greedy_action(state).unwrap(),
+        Algo::Beam => beam_search_action_with_time_threshold(state, 5, options.time_ms),
+        Algo::Chokudai => chokudai_search_action_wirh_time_threshold(state, 1, state.config.end_turn, options.time_ms),
+        Algo::Mcts => mcts_action_with_time_threshold(state, options.time_ms),
+    }
+}
+
+// リプレイファイルの盤面を生成し直し、記録した行動を1ターンずつ表示しながら再生する
+fn play_replay(path: &str) -> Result<(), String> {
+    let (mut state, actions) = read_replay(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+    println!("{}", state);
+    for (turn, &action) in actions.iter().enumerate() {
+        thread::sleep(Duration::from_millis(REPLAY_FRAME_MS));
+        state.advance(action).map_err(|error| format!("illegal action {} at turn {}: {}", action, turn, error))?;
+        println!("{}", state);
+    }
+    println!("Score:\t{}", state.game_score);
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Some(path) = &options.replay {
+        if let Err(message) = play_replay(path) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let config = MazeConfig::new(options.height.unwrap_or(options.width), options.width, options.end_turn);
+    let mut state = MazeState::new(config, Some(options.seed));
+    state.record_history = options.record.is_some();
+    if options.show {
+        println!("{}", state);
+    }
+
+    while !state.is_done() {
+        let action = select_action(&options, &state);
+        state.advance(action).expect("action must be legal");
+        if options.show {
+            println!("{}", state);
+        }
+    }
+
+    println!("Score:\t{}", state.game_score);
+
+    if let Some(path) = &options.record {
+        if let Err(error) = write_replay(path, &state) {
+            eprintln!("cannot write {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_algorithm_seed_and_board_size() {
+        let options = parse(&["--algo", "chokudai", "--seed", "42", "--width", "10", "--height", "5", "--end-turn", "20", "--show"]).unwrap();
+        assert!(matches!(options.algo, Algo::Chokudai));
+        assert_eq!((options.seed, options.width, options.height, options.end_turn), (42, 10, Some(5), 20));
+        assert!(options.show);
+
+        let options = parse(&[]).unwrap();
+        assert!(matches!(options.algo, Algo::Beam));
+        assert_eq!((options.seed, options.width, options.height, options.time_ms), (0, 30, None, 10));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse(&["--algo", "minimax"]).is_err());
+        assert!(parse(&["--algo"]).is_err());
+        assert!(parse(&["--seed", "x"]).is_err());
+        assert!(parse(&["--width", "0"]).is_err());
+        assert!(parse(&["--replay"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+
+    // 選んだ行動で小さい盤面を最後までプレイできる
+    #[test]
+    fn every_algorithm_plays_a_small_board() {
+        for algo in ["greedy", "beam", "chokudai", "mcts"] {
+            let options = parse(&["--algo", algo, "--width", "4", "--end-turn", "5", "--time-ms", "1"]).unwrap();
+            let mut state = MazeState::new(MazeConfig::new(4, 4, 5), Some(0));
+            while !state.is_done() {
+                state.advance(select_action(&options, &state)).unwrap();
+            }
+        }
+    }
+}
@@ -7,3 +7,8 @@ pub mod BeamSearch04;
 pub mod BeamSearchWithTime05;
 pub mod ChokudaiSearch06;
 pub mod ChokudaiSearchWithTime07;
+pub mod MctsAction;
+pub mod AStarAction;
+pub mod ThunderSearch;
+pub mod EpsilonGreedy;
+pub mod MultiAgentMazeState;
\ No newline at end of file
@@ -0,0 +1,171 @@
+#![allow(non_snake_case)]
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+use super::MazeState00::{MazeConfig, MazeState};
+use crate::common::game::run_episodes;
+use crate::common::time_keeper::TimeKeeper;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// A*の探索ノード
+// 1ターンごとにpoint_maxから得たポイントを引いた値をコストとみなすと、
+// g = 使ったターン数 * point_max - game_score は非負のコストの累計となる。
+// h = 残りターン数 * point_max - 残りで得られるポイントの上界 は残りのコストの下界なので許容的なヒューリスティックとなる。
+// 上界はscore_upper_boundで求めるので、ポイントが復活する盤面も扱える。
+// f = g + h が小さいものほど最終スコアの上界が大きい。
+struct AStarNode {
+    state: MazeState,
+    f_cost: i32,
+}
+
+impl AStarNode {
+    fn new(state: MazeState) -> Self {
+        let point_max = state.config.point_max.max(0);
+        let g_cost = state.turn as i32 * point_max - state.game_score;
+        let h_cost = (state.config.end_turn - state.turn) as i32 * point_max - (state.score_upper_bound() - state.game_score);
+        AStarNode {
+            state,
+            f_cost: g_cost + h_cost,
+        }
+    }
+}
+
+// BinaryHeapから f が小さい順に取り出せるように逆順で比較する
+// f が同じなら、より先のターンまで進んでいるものを優先する
+impl Ord for AStarNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f_cost.cmp(&self.f_cost)
+            .then_with(|| self.state.turn.cmp(&other.state.turn))
+    }
+}
+
+impl PartialOrd for AStarNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for AStarNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AStarNode {}
+
+// 制限時間(ms)を指定してA*で行動を決定する
+// 終了状態を取り出せたらそれが最適なのでその最初の行動を返し、
+// 時間切れの場合はそれまでに取り出したノードのうち最も先のターンまで進んだものの最初の行動を返す。
+// f の小さい順に取り出しているので、同じターンでは最終スコアの上界が大きいものが先に選ばれる。
+pub fn a_star_action(state: &MazeState, time_threshold: usize) -> usize {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let mut open_list = BinaryHeap::new();
+    open_list.push(AStarNode::new(state.clone()));
+
+    // これまでに取り出したノードのうち、最も先のターンまで進んだものの最初の行動
+    let mut deepest: Option<(usize, i32)> = None;
+
+    while let Some(node) = open_list.pop() {
+        if node.state.is_done() {
+            return node.state.first_action as usize;
+        }
+        if time_keeper.is_time_over() {
+            break;
+        }
+        if node.state.first_action != -1 && deepest.is_none_or(|(turn, _)| node.state.turn > turn) {
+            deepest = Some((node.state.turn, node.state.first_action));
+        }
+
+        for action in node.state.legal_actions() {
+            let mut next_state = node.state.clone();
+            next_state.advance(action).expect("action must be legal");
+            if node.state.turn == state.turn {
+                next_state.first_action = action as i32;
+            }
+            open_list.push(AStarNode::new(next_state));
+        }
+    }
+
+    match deepest {
+        Some((_, first_action)) => first_action as usize,
+        None => state.legal_actions()[0],
+    }
+}
+
+type AIFunction = fn(&MazeState) -> usize;
+
+struct StringAIPair {
+    name: String,
+    ai: AIFunction,
+}
+
+// 盤面生成シードを0..game_numberとしてgame_number回プレイして平均スコアを表示する
+fn test_ai_score(ai: &StringAIPair, game_number: usize) {
+    let summary = run_episodes(|seed| MazeState::new(CONFIG, Some(seed)), ai.ai, 0..game_number as u64);
+    println!("Score of {}:\t{}", ai.name, summary);
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    let ais = [
+        StringAIPair {
+            name: "beam_search_action_with_time_threshold".to_string(),
+            ai: |state| beam_search_action_with_time_threshold(state, 5, 10),
+        },
+        StringAIPair {
+            name: "a_star_action".to_string(),
+            ai: |state| a_star_action(state, 10),
+        },
+    ];
+    for ai in ais {
+        test_ai_score(&ai, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 全ての行動列を調べて、stateから得られる最終スコアの最大値を返す
+    fn best_final_score(state: &MazeState) -> i32 {
+        if state.is_done() {
+            return state.game_score;
+        }
+        state.legal_actions().into_iter()
+            .map(|action| {
+                let mut next_state = state.clone();
+                next_state.advance(action).unwrap();
+                best_final_score(&next_state)
+            })
+            .max()
+            .unwrap()
+    }
+
+    // 小さい盤面なら時間切れにならないので、A*で選んだ行動だけで最適なスコアに届く
+    fn assert_a_star_is_optimal(config: MazeConfig) {
+        for seed in 0..10 {
+            let mut state = MazeState::new(config, Some(seed));
+            let optimum = best_final_score(&state);
+            while !state.is_done() {
+                state.advance(a_star_action(&state, 1000)).unwrap();
+            }
+            assert_eq!(state.game_score, optimum, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn a_star_reaches_the_optimal_score() {
+        assert_a_star_is_optimal(MazeConfig::new(3, 3, 4));
+    }
+
+    // 10以上のポイントやポイントの復活があっても、上界をscore_upper_boundで求めるので最適なまま
+    #[test]
+    fn a_star_handles_large_points_and_regen() {
+        assert_a_star_is_optimal(MazeConfig { point_min: 5, point_max: 30, ..MazeConfig::new(3, 3, 4) });
+        assert_a_star_is_optimal(MazeConfig { regen_after: Some(2), ..MazeConfig::new(3, 3, 5) });
+    }
+}
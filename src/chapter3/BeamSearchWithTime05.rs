@@ -1,215 +1,421 @@
 #![allow(non_snake_case)]
 
-use rand::{Rng, SeedableRng, rngs, thread_rng};
-use std::collections::BinaryHeap;
-use std::time::Instant;
-
-// 時間を管理する構造体
-struct TimeKeeper {
-    start_time: Instant,
-    time_threshold: usize,
-}
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
-impl TimeKeeper {
-    // 時間制限をミリ秒単位で指定してインスタンスをつくる。
-    fn new(time_threshold: usize) -> Self {
-        TimeKeeper {
-            start_time: Instant::now(),
-            time_threshold,
-        }
-    }
+use super::MazeState00::{ActionBuffer, Evaluator, MazeConfig, MazeState, default_evaluator};
+use crate::common::bounded_beam::BoundedBeam;
+use crate::common::game::{greedy_action, run_episodes};
+use crate::common::logging::{search_debug, search_trace};
+use crate::common::node_arena::NodeArena;
+use crate::common::rng::make_rng;
+use crate::common::stats::{ScoreSummary, SearchStats};
+use crate::common::time_keeper::{Clock, TimeKeeper};
 
-    // インスタンス生成した時から指定した時間制限を超過したか判定する。
-    fn is_time_over(&self) -> bool {
-        let elapsed_time = self.start_time.elapsed().as_millis() as usize;
-        elapsed_time >= self.time_threshold
-    }
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// ビーム幅と深さを指定してビームサーチで行動を決定する
+pub fn beam_search_action_with_time_threshold(state: &MazeState, beam_width: usize, time_threshold: usize) -> usize {
+    beam_search_action_with_stats(state, beam_width, time_threshold).0
 }
 
-// // 座標を保持する
-#[derive(Debug, Clone, Copy)]
-struct Coord {
-    y: i32,
-    x: i32,
+// beam_search_action_with_time_thresholdと同じ探索を行い、探索にかかった計算量も返す
+pub fn beam_search_action_with_stats(state: &MazeState, beam_width: usize, time_threshold: usize) -> (usize, SearchStats) {
+    beam_search_action_with_evaluator(state, beam_width, &TimeKeeper::new(time_threshold), default_evaluator)
 }
 
-impl Coord {
-    fn new(y: i32, x: i32) -> Self {
-        Coord { y, x }
-    }
+// 盤面評価の関数と時間管理を指定してビームサーチを行い、行動と探索にかかった計算量を返す
+// time_keeperは呼び出し側でresetしておく。Instantを使えない環境では、独自のClockを使うTimeKeeperを渡す
+pub fn beam_search_action_with_evaluator(
+    state: &MazeState,
+    beam_width: usize,
+    time_keeper: &TimeKeeper<impl Clock>,
+    evaluator: Evaluator,
+) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let (best_state, _) = beam_search_best_state(state, beam_width, time_keeper, evaluator, false, &mut stats);
+    let action = match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    };
+    search_debug!(
+        "beam search: turn {} -> action {} (evaluated_score {}, depth {}, {} nodes)",
+        state.turn, action, best_state.evaluated_score, stats.depth_reached, stats.nodes_expanded
+    );
+    (action, stats)
 }
 
-// 迷路の高さと幅
-const H: usize = 30;
-const W: usize = 30;
-// ゲーム終了ターン
-const END_TURN: usize = 100;
-
-// 一人ゲームの例
-// 1ターンに上下左右四方向のいずれかに1マスずつ進む。
-// 床にあるポイントを踏むと自身のスコアとなり、床のポイントが消える。
-// END_TURNの時点のスコアを高くすることが目的
-#[derive(Debug, Clone)]
-struct MazeState {
-    character: Coord,
-    points: [[i32; W]; H],
-    turn: usize,
-    game_score: i32,
-    evaluated_score: i32,
-    first_action: i32,
+// 直前の行動と逆向きに戻る行動を展開しないビームサーチで行動を決定し、探索にかかった計算量も返す
+// 同じマスを往復するだけの盤面がビームを占めないので、同じ時間でより深く探索できる
+pub fn beam_search_action_no_reverse(state: &MazeState, beam_width: usize, time_threshold: usize) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let (best_state, _) = beam_search_best_state(state, beam_width, &TimeKeeper::new(time_threshold), default_evaluator, true, &mut stats);
+    let action = match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    };
+    (action, stats)
 }
 
-impl MazeState{
-    fn new(seed: Option<u64>) -> Self {
-        let mut rng_for_construct: rngs::StdRng = SeedableRng::seed_from_u64(thread_rng().gen());
-        if let Some(s) = seed {
-            rng_for_construct = SeedableRng::seed_from_u64(s)
-        }
-        let character = Coord::new(rng_for_construct.gen_range(0..H as i32), rng_for_construct.gen_range(0..W as i32));
+// ビームサーチを行い、最も深く探索できたビームの最良の盤面を返す
+// 時間切れにならずに探索を終えた場合はtrueも返す
+// no_reverseがtrueの場合は、各盤面の直前の行動と逆向きに戻る行動を展開しない
+fn beam_search_best_state(
+    state: &MazeState,
+    beam_width: usize,
+    time_keeper: &TimeKeeper<impl Clock>,
+    evaluator: Evaluator,
+    no_reverse: bool,
+    stats: &mut SearchStats,
+) -> (MazeState, bool) {
+    // ビームは評価の高い順に並べ、次のビームにはbeam_width個までしか残さない
+    let mut now_beam = vec![state.clone()];
+    let mut best_state = state.clone();
+    let mut completed = true;
 
-        let mut points = [[0; W]; H];   // 床のポイントを1~9で表現する
+    let mut legal_actions = ActionBuffer::new();
 
-        // h*wの迷路を生成する。
-        for y in 0..H {
-            for x in 0..W {
-                if y == character.y as usize && x == character.x as usize {
-                    continue;
+    let mut t = 0;
+    'search: loop {
+        let mut next_beam = BoundedBeam::new(beam_width);
+
+        for now_state in &now_beam {
+            if time_keeper.is_time_over() {
+                search_debug!("beam search: time over at depth {} after {:?}", t, time_keeper.elapsed());
+                completed = false;
+                break 'search;
+            }
+
+            if no_reverse {
+                now_state.legal_actions_no_reverse_into(now_state.last_action, &mut legal_actions);
+            } else {
+                now_state.legal_actions_into(&mut legal_actions);
+            }
+
+            for &action in &legal_actions {
+                let mut next_state = now_state.clone();
+                next_state.advance(action).expect("action must be legal");
+                next_state.evaluate_with(evaluator);
+                stats.nodes_expanded += 1;
+                search_trace!("beam search: depth {} expand action {} -> evaluated_score {}", t, action, next_state.evaluated_score);
+
+                if t == 0 {
+                    next_state.first_action = action as i32;
                 }
-                points[y][x] = rng_for_construct.gen_range(0..10);
-             }
+                next_beam.push(next_state);
+            }
+        }
+        debug_assert!(next_beam.len() <= beam_width, "next_beam must not exceed beam_width");
+
+        // 次のビームが空の場合は、それまでに見つけた最良の盤面を返す
+        if next_beam.is_empty() {
+            break;
         }
 
-        let turn = 0;  // 現在のターン
-        let game_score = 0;  // ゲーム上で実際に得たスコア
-        let evaluated_score = 0;  // 探索上で評価したスコア
-        let first_action = -1;  // 探索木のルートノードで最初に選択した行動
+        now_beam = next_beam.into_sorted_vec();
+        best_state = now_beam[0].clone();
+        search_debug!(
+            "beam search: depth {} kept {} states (best evaluated_score {}, worst {})",
+            t + 1, now_beam.len(), best_state.evaluated_score, now_beam[now_beam.len() - 1].evaluated_score
+        );
+        t += 1;
 
-        MazeState {
-            character,
-            points,
-            turn,
-            game_score,
-            evaluated_score,
-            first_action,
+        if best_state.is_done() {
+            break;
         }
     }
 
-    // [どのゲームでも実装する] : ゲームの終了判定
-    fn is_done(&mut self) -> bool {
-        self.turn == END_TURN
-    }
-    // [どのゲームでも実装する] : 探索用の盤面評価をする
-    fn evaluate_score(&mut self) {
-        self.evaluated_score = self.game_score;
-    }
-    // [どのゲームでも実装する] : 指定したactionでゲームを1ターン進める
-    fn advance(&mut self, action: usize) {
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
+    stats.depth_reached = stats.depth_reached.max(t);
+    stats.elapsed_ms = time_keeper.elapsed().as_millis();
+    (best_state, completed)
+}
 
-        self.character.x += dx[action] as i32;
-        self.character.y += dy[action] as i32;
+// 制限時間(ms)を指定し、ビーム幅を1, 2, 4, 8, ...と広げながらビームサーチを繰り返して行動を決定する
+// 時間内に最後まで探索できた結果のうち、最もスコアの高いものの行動を返す
+pub fn beam_stack_search_action(state: &MazeState, time_threshold: usize) -> usize {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let mut best_state: Option<MazeState> = None;
+    let mut beam_width = 1;
+    let mut last_nodes_expanded = 0;
 
-        let point = &mut self.points[self.character.y as usize][self.character.x as usize];
-        if *point > 0 {
-            self.game_score += *point;
-            *point = 0;
+    loop {
+        let mut stats = SearchStats::default();
+        let (candidate, completed) = beam_search_best_state(state, beam_width, &time_keeper, default_evaluator, false, &mut stats);
+        if !completed {
+            // 1回も最後まで探索できなかった場合は、途中までの結果を使う
+            if best_state.is_none() {
+                best_state = Some(candidate);
+            }
+            break;
+        }
+        if best_state.as_ref().is_none_or(|best| candidate.game_score > best.game_score) {
+            best_state = Some(candidate);
+        }
+        // ビーム幅を広げても展開するノードが増えなければ、以降の結果も変わらない
+        if stats.nodes_expanded == last_nodes_expanded {
+            break;
         }
+        last_nodes_expanded = stats.nodes_expanded;
+        beam_width *= 2;
+    }
 
-        self.turn += 1;
+    match best_state.map_or(-1, |best| best.first_action) {
+        -1 => state.legal_actions()[0],
+        first_action => first_action as usize,
     }
-    // [どのゲームでも実装する] : 現在の状況でプレイヤーが可能な行動を全て取得する
-    fn legal_actions(&self) -> Vec<usize> {
-        let mut actions = Vec::new();
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
+}
+
+// 移動先のポイントが高い順に子を展開するビームサーチで行動を決定し、探索にかかった計算量も返す
+// 次のビームはbeam_width個までしか持たない。ビームが埋まっていて、移動後のスコアが
+// ビームの最も低い評価に届かない子は展開しない。以降の子はさらにスコアが低いので、その親の展開を打ち切る
+// 評価はevaluate_scoreを使い、beam_search_action_with_statsと同じ行動を返す
+pub fn beam_search_action_with_move_ordering(state: &MazeState, beam_width: usize, time_threshold: usize) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let mut now_beam = vec![state.clone()];
+    let mut best_state = state.clone();
+    let mut legal_actions = ActionBuffer::new();
 
-        for action in 0..4 {
-            let ty = (self.character.y + dy[action]) as usize;
-            let tx = (self.character.x + dx[action]) as usize;
-            if ty < H && tx < W {
-                actions.push(action);
+    let mut t = 0;
+    'search: loop {
+        let mut next_beam = BoundedBeam::new(beam_width);
+
+        for now_state in &now_beam {
+            if time_keeper.is_time_over() {
+                break 'search;
+            }
+
+            now_state.legal_actions_into(&mut legal_actions);
+            // 同じポイントの場合は元の行動の順を保つ
+            legal_actions.sort_by_key(|&action| std::cmp::Reverse(now_state.target_point(action)));
+
+            for &action in &legal_actions {
+                let next_score = now_state.game_score + now_state.target_point(action);
+                if next_beam.is_full() && next_beam.worst().is_some_and(|worst: &MazeState| next_score < worst.evaluated_score) {
+                    break;
+                }
+
+                let mut next_state = now_state.clone();
+                next_state.advance(action).expect("action must be legal");
+                next_state.evaluate_score();
+                stats.nodes_expanded += 1;
+
+                if t == 0 {
+                    next_state.first_action = action as i32;
+                }
+                next_beam.push(next_state);
             }
         }
 
-        actions
+        // 次のビームが空の場合は、それまでに見つけた最良の盤面を使う
+        if next_beam.is_empty() {
+            break;
+        }
+
+        now_beam = next_beam.into_sorted_vec();
+        best_state = now_beam[0].clone();
+        t += 1;
+
+        if best_state.is_done() {
+            break;
+        }
     }
 
-    // [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
-    fn _to_string(&self) -> String {
-        let mut result = format!("turn:\t{}\nscore:\t{}\n", self.turn, self.game_score);
+    stats.depth_reached = t;
+    stats.elapsed_ms = time_keeper.elapsed().as_millis();
+    let action = match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    };
+    (action, stats)
+}
+
+// スコアの上限で枝刈りするビームサーチで行動を決定し、探索にかかった計算量も返す
+// 盤面から伸ばせるスコアはMazeState::score_upper_boundを超えない。
+// これまでに見つけた最も高いスコアに上限が届かない盤面は展開しない。
+// 最良の盤面にポイントが残っていなければ、それ以上探索しても変わらないので打ち切る。
+// 途中の盤面のスコアを最終スコアの下限として使えるのは、スコアが減らない場合だけなので、
+// ポイントが復活する盤面や罠のある盤面では枝刈りしない
+pub fn beam_search_action_with_bound(state: &MazeState, beam_width: usize, time_threshold: usize) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let use_bound = state.config.regen_after.is_none() && !state.has_traps();
+    let mut now_beam = vec![state.clone()];
+    let mut best_state = state.clone();
+    let mut incumbent = state.game_score;
+    let mut legal_actions = ActionBuffer::new();
+
+    let mut t = 0;
+    'search: loop {
+        let mut next_beam = BoundedBeam::new(beam_width);
+
+        for now_state in &now_beam {
+            if time_keeper.is_time_over() {
+                break 'search;
+            }
+            if use_bound && now_state.score_upper_bound() < incumbent {
+                continue;
+            }
+
+            now_state.legal_actions_into(&mut legal_actions);
+
+            for &action in &legal_actions {
+                let mut next_state = now_state.clone();
+                next_state.advance(action).expect("action must be legal");
+                next_state.evaluate_score();
+                stats.nodes_expanded += 1;
+                incumbent = incumbent.max(next_state.game_score);
 
-        for h in 0..H {
-            for w in 0..W {
-                if self.character.y as usize == h && self.character.x as usize == w {
-                    result.push('@');
-                } else if self.points[h][w] > 0 {
-                    result.push_str(&self.points[h][w].to_string());
-                } else {
-                    result.push('.');
+                if t == 0 {
+                    next_state.first_action = action as i32;
                 }
+                next_beam.push(next_state);
             }
-            result.push('\n');
         }
 
-        result
+        // 次のビームが空の場合は、それまでに見つけた最良の盤面を使う
+        if next_beam.is_empty() {
+            break;
+        }
+
+        now_beam = next_beam.into_sorted_vec();
+        best_state = now_beam[0].clone();
+        t += 1;
+
+        if best_state.is_done() || (use_bound && best_state.remaining_points() == 0) {
+            break;
+        }
     }
+
+    stats.depth_reached = t;
+    stats.elapsed_ms = time_keeper.elapsed().as_millis();
+    let action = match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    };
+    (action, stats)
 }
 
-// 探索時のソート用に評価を比較する
-impl Ord for MazeState {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.evaluated_score.cmp(&other.evaluated_score)
-    }
+// 2つの盤面のキャラクターの近さ。同じマスなら1で、マンハッタン距離が離れるほど0に近づく
+fn character_closeness(a: &MazeState, b: &MazeState) -> f64 {
+    let d = a.character.manhattan(&b.character);
+    1.0 / (1.0 + d as f64)
 }
 
-impl PartialOrd for MazeState {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+// 次のビームに残す盤面を、キャラクターの位置が散らばるように選ぶ
+// 既に選んだ盤面とのcharacter_closenessの合計にdiversity_lambdaを掛けた値を評価から引き、
+// その値が最も高い盤面から順にbeam_width個選ぶ。diversity_lambdaが0の場合は評価の高い順に選ぶ
+fn select_diverse_beam(mut candidates: Vec<MazeState>, beam_width: usize, diversity_lambda: f64) -> Vec<MazeState> {
+    // 同じ値の場合は評価の高い順を保つため、先に並べておく
+    candidates.sort_unstable_by(|a, b| b.cmp(a));
+    let mut selected: Vec<MazeState> = Vec::with_capacity(beam_width);
+    while selected.len() < beam_width && !candidates.is_empty() {
+        let mut best_index = 0;
+        let mut best_value = f64::NEG_INFINITY;
+        for (i, candidate) in candidates.iter().enumerate() {
+            let similarity: f64 = selected.iter().map(|s| character_closeness(candidate, s)).sum();
+            let value = candidate.evaluated_score as f64 - diversity_lambda * similarity;
+            if value > best_value {
+                best_index = i;
+                best_value = value;
+            }
+        }
+        // removeなら残りの盤面は評価の高い順のまま並ぶ
+        selected.push(candidates.remove(best_index));
     }
+    selected
 }
 
-impl PartialEq for MazeState {
-    fn eq(&self, other: &Self) -> bool {
-        self.evaluated_score == other.evaluated_score
-    }
+// キャラクターの位置が似た盤面ばかりがビームに残らないようにしたビームサーチで行動を決定する
+// 評価が同じ盤面が多い場合でも、離れた場所の盤面を残して探索する
+fn beam_search_action_with_diversity(state: &MazeState, beam_width: usize, time_threshold: usize, diversity_lambda: f64) -> usize {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    diverse_beam_search(state, beam_width, diversity_lambda, || time_keeper.is_time_over())
 }
 
-impl Eq for MazeState {}
+// is_time_overがtrueを返すまで、beam_search_action_with_diversityと同じ探索を行う
+// is_time_overは盤面を1つ展開するごとに呼ぶ
+fn diverse_beam_search(state: &MazeState, beam_width: usize, diversity_lambda: f64, is_time_over: impl Fn() -> bool) -> usize {
+    let mut now_beam = vec![state.clone()];
+    let mut best_state = state.clone();
+    let mut legal_actions = ActionBuffer::new();
 
-// ビーム幅と深さを指定してビームサーチで行動を決定する
-fn beam_search_action_with_time_threshold(state: &MazeState, beam_width: usize, time_threshold: usize) -> usize {
-    let mut now_beam = BinaryHeap::new();
+    let mut t = 0;
+    'search: loop {
+        let mut candidates = Vec::with_capacity(now_beam.len() * 4);
+
+        for now_state in &now_beam {
+            if is_time_over() {
+                break 'search;
+            }
+
+            now_state.legal_actions_into(&mut legal_actions);
+            for &action in &legal_actions {
+                let mut next_state = now_state.clone();
+                next_state.advance(action).expect("action must be legal");
+                next_state.evaluate_score();
+
+                if t == 0 {
+                    next_state.first_action = action as i32;
+                }
+                candidates.push(next_state);
+            }
+        }
+
+        // 次のビームが空の場合は、それまでに見つけた最良の盤面の行動を返す
+        if candidates.is_empty() {
+            break;
+        }
+
+        now_beam = select_diverse_beam(candidates, beam_width, diversity_lambda);
+        // 最良の盤面は多様性の補正を含めず、評価だけで選ぶ
+        best_state = now_beam.iter().max().unwrap().clone();
+        t += 1;
+
+        if best_state.is_done() {
+            break;
+        }
+    }
+
+    match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    }
+}
+
+// 同じ深さで同一局面を重複して展開しないビームサーチで行動を決定する
+// 盤面のZobristハッシュを深さごとに記録し、既に追加した局面と同じ子は次のビームに入れない
+fn beam_search_action_with_dedup(state: &MazeState, beam_width: usize, time_threshold: usize) -> usize {
+    let mut now_beam = vec![state.clone()];
     let mut best_state = state.clone();
 
-    now_beam.push(state.clone());
+    let mut legal_actions = ActionBuffer::new();
 
     let time_keeper = TimeKeeper::new(time_threshold);
 
     let mut t = 0;
     loop {
-        let mut next_beam = BinaryHeap::new();
+        let mut next_beam = BoundedBeam::new(beam_width);
+        let mut hash_check = HashSet::new();
 
-        // let mut first_action = 0;
-        for _ in 0..beam_width {
+        for now_state in &now_beam {
             if time_keeper.is_time_over() {
                 return match best_state.first_action {
                     -1 => state.legal_actions()[0],
                     _ => best_state.first_action as usize,
-
                 }
             }
 
-            if now_beam.is_empty() {
-                break;
-            }
-
-            let now_state = now_beam.pop().unwrap();
-            let legal_actions = now_state.legal_actions();
+            now_state.legal_actions_into(&mut legal_actions);
 
             for &action in &legal_actions {
                 let mut next_state = now_state.clone();
-                next_state.advance(action);
+                next_state.advance(action).expect("action must be legal");
+                if !hash_check.insert(next_state.zobrist_key()) {
+                    continue;
+                }
                 next_state.evaluate_score();
 
                 if t == 0 {
@@ -218,9 +424,15 @@ fn beam_search_action_with_time_threshold(state: &MazeState, beam_width: usize,
                 next_beam.push(next_state);
             }
         }
+        debug_assert!(next_beam.len() <= beam_width, "next_beam must not exceed beam_width");
 
-        now_beam = next_beam;
-        best_state = now_beam.peek().unwrap().clone();
+        // 次のビームが空の場合は、それまでに見つけた最良の盤面の行動を返す
+        if next_beam.is_empty() {
+            break;
+        }
+
+        now_beam = next_beam.into_sorted_vec();
+        best_state = now_beam[0].clone();
         t += 1;
 
         if best_state.is_done() {
@@ -228,34 +440,629 @@ fn beam_search_action_with_time_threshold(state: &MazeState, beam_width: usize,
         }
     }
 
-    best_state.first_action as usize
+    match best_state.first_action {
+        -1 => state.legal_actions()[0],
+        _ => best_state.first_action as usize,
+    }
+}
+
+// ビームサーチで次のビームの候補にする盤面と、アリーナ上の親の添字、親からの行動
+// 比較は盤面の比較だけで行い、beam_search_action_with_time_threshold と同じ順序で探索する
+struct ArenaCandidate {
+    state: MazeState,
+    parent: usize,
+    action: usize,
+}
+
+impl Ord for ArenaCandidate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.state.cmp(&other.state)
+    }
+}
+
+impl PartialOrd for ArenaCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ArenaCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+}
+
+impl Eq for ArenaCandidate {}
+
+// ビーム幅と制限時間を指定してビームサーチを行い、最も深く探索できたビームの最良の行動列を返す
+// ビームに残した盤面はアリーナに追加し、ビームにはその添字だけを持つ。行動列は最後に親をたどって復元する
+// 返り値の先頭は beam_search_action_with_time_threshold が返す行動と一致する
+fn beam_search_actions(state: &MazeState, beam_width: usize, time_threshold: usize) -> Vec<usize> {
+    let mut arena = NodeArena::new();
+    let root_index = arena.push_root(state.clone());
+    let mut best_index = root_index;
+    let mut now_beam = vec![best_index];
+
+    let mut legal_actions = ActionBuffer::new();
+
+    let time_keeper = TimeKeeper::new(time_threshold);
+
+    'search: loop {
+        let mut next_beam = BoundedBeam::new(beam_width);
+
+        for &now_index in &now_beam {
+            if time_keeper.is_time_over() {
+                break 'search;
+            }
+
+            let now_state = &arena.states[now_index];
+            now_state.legal_actions_into(&mut legal_actions);
+
+            for &action in &legal_actions {
+                let mut next_state = now_state.clone();
+                next_state.advance(action).expect("action must be legal");
+                next_state.evaluate_score();
+                // 評価が同じ盤面の順序をbeam_search_action_with_time_thresholdとそろえるため、first_actionも記録する
+                if now_index == root_index {
+                    next_state.first_action = action as i32;
+                }
+                next_beam.push(ArenaCandidate { state: next_state, parent: now_index, action });
+            }
+        }
+        debug_assert!(next_beam.len() <= beam_width, "next_beam must not exceed beam_width");
+
+        // 次のビームが空の場合は、それまでに見つけた最良の行動列を返す
+        if next_beam.is_empty() {
+            break;
+        }
+
+        now_beam = next_beam
+            .into_sorted_vec()
+            .into_iter()
+            .map(|candidate| arena.push(candidate.state, candidate.parent, candidate.action))
+            .collect();
+        best_index = now_beam[0];
+
+        if arena.states[best_index].is_done() {
+            break;
+        }
+    }
+
+    let actions = arena.path(best_index);
+    if actions.is_empty() {
+        return vec![state.legal_actions()[0]];
+    }
+    actions
+}
+
+type AIFunction = fn(&MazeState) -> usize;
+// 盤面、ビーム幅、制限時間(ms)から行動と探索にかかった計算量を返す探索
+type StatsAIFunction = fn(&MazeState, usize, usize) -> (usize, SearchStats);
+
+struct StringAIPair {
+    name: String,
+    ai: AIFunction,
+}
+
+// シードを指定してゲーム状況を表示しながらビームサーチでプレイさせる。
+#[allow(dead_code)]
+fn play_game(seed: u64, beam_width: usize, time_threshold: usize) {
+    let mut state = MazeState::new(CONFIG, Some(seed));
+    println!("{}", state);
+
+    while !state.is_done() {
+        state.advance(beam_search_action_with_time_threshold(&state, beam_width, time_threshold)).expect("action must be legal");
+        println!("{}", state);
+    }
 }
 
-// ゲームをgame_number回プレイして平均スコアを表示する
-fn test_ai_score(game_number: usize) {
+// ポイントがいくつかの塊に集まった盤面を生成する
+// cluster_n個の中心からマンハッタン距離radius以内のマスだけに1~9のポイントを置き、他のマスは空きマスにする
+// 塊から離れた場所では評価が同じ盤面が多くなる
+fn clustered_state(seed: u64, cluster_n: usize, radius: i32) -> MazeState {
+    let mut rng = make_rng(seed);
+    let (h, w) = (CONFIG.h as i32, CONFIG.w as i32);
+    let centers: Vec<(i32, i32)> = (0..cluster_n).map(|_| (rng.gen_range(0..h), rng.gen_range(0..w))).collect();
+    let character = (rng.gen_range(0..h), rng.gen_range(0..w));
+
+    let mut layout = String::new();
+    for y in 0..h {
+        for x in 0..w {
+            if (y, x) == character {
+                layout.push('@');
+            } else if centers.iter().any(|&(cy, cx)| (cy - y).abs() + (cx - x).abs() <= radius) {
+                layout.push(char::from_digit(rng.gen_range(1..10), 10).unwrap());
+            } else {
+                layout.push('.');
+            }
+        }
+        layout.push('\n');
+    }
+
+    let mut state: MazeState = layout.parse().expect("generated layout must be valid");
+    state.config.end_turn = CONFIG.end_turn;
+    state
+}
+
+// clustered_stateの盤面で、盤面生成シードを0..game_numberとしてgame_number回プレイしてスコアの分布を表示する
+fn test_ai_score_on_clustered(ai: &StringAIPair, game_number: usize) -> ScoreSummary {
+    let summary = run_episodes(|seed| clustered_state(seed, 4, 3), ai.ai, 0..game_number as u64);
+    println!("Score of {}(clustered):\t{}", ai.name, summary);
+    summary
+}
+
+// 盤面の設定を指定して、盤面生成シードを0..game_numberとしてgame_number回プレイしてスコアの分布を表示する
+// progressを指定した場合は、1回プレイするごとに(プレイし終えた回数, game_number)で呼び出す
+// run_episodesはシードの順に1ゲームずつ盤面をつくってプレイするので、次の盤面をつくる時点で1つ前のゲームを数える
+fn test_ai_score(ai: &StringAIPair, config: MazeConfig, game_number: usize, progress: Option<&dyn Fn(usize, usize)>) -> ScoreSummary {
+    let make_state = |seed: u64| {
+        if let Some(progress) = progress.filter(|_| seed > 0) {
+            progress(seed as usize, game_number);
+        }
+        MazeState::new(config, Some(seed))
+    };
+    let summary = run_episodes(make_state, ai.ai, 0..game_number as u64);
+    if let Some(progress) = progress.filter(|_| game_number > 0) {
+        progress(game_number, game_number);
+    }
+
+    println!("Score of {}({}x{}, {} turns):\t{}", ai.name, config.h, config.w, config.end_turn, summary);
+    summary
+}
+
+// test_ai_scoreのprogressに渡す、進み具合と残り時間の目安を標準エラー出力に表示する関数をつくる
+// 残り時間は、つくってからの経過時間を1回あたりの時間として見積もる
+fn eta_printer() -> impl Fn(usize, usize) {
+    let start = Instant::now();
+    move |completed, total| {
+        let remaining = start.elapsed() / completed as u32 * (total - completed) as u32;
+        eprint!("\r{}/{} games, about {}s left ", completed, total, remaining.as_secs());
+        if completed == total {
+            eprintln!();
+        }
+    }
+}
+
+// ビーム幅を指定してgame_number回プレイし、平均スコアと1手あたりの平均展開ノード数、探索の深さを表示する
+fn test_ai_score_with_stats(name: &str, search: StatsAIFunction, beam_width: usize, config: MazeConfig, game_number: usize) {
     let mut score_mean = 0.0;
+    let mut nodes_expanded = 0;
+    let mut depth_reached = 0;
+    let mut action_count = 0;
 
-    for _ in 0..game_number {
-        let mut state = MazeState::new(None);
+    for i in 0..game_number {
+        let mut state = MazeState::new(config, Some(i as u64));
 
-        // let mut c = 1;
         while !state.is_done() {
-            let action = beam_search_action_with_time_threshold(&state, 5, 10);
-            state.advance(action);
-            // println!("{}, {}, {}", c, action, state.game_score);
-            // c += 1;
+            let (action, stats) = search(&state, beam_width, 10);
+            state.advance(action).expect("action must be legal");
+            nodes_expanded += stats.nodes_expanded;
+            depth_reached += stats.depth_reached;
+            action_count += 1;
         }
 
-        let score = state.game_score;
-        // println!("score:\t{}", score);
-        score_mean += score as f64;
+        score_mean += state.game_score as f64;
     }
 
     score_mean /= game_number as f64;
-    println!("Score:\t{}", score_mean);
+    println!(
+        "Score of {} beam width {}({}x{}, {} turns):\t{}\tnodes_expanded/action:\t{}\tdepth_reached/action:\t{}",
+        name, beam_width, config.h, config.w, config.end_turn, score_mean,
+        nodes_expanded as f64 / action_count as f64, depth_reached as f64 / action_count as f64,
+    );
+}
+
+// ゲーム全体の持ち時間(ms)を指定してgame_number回プレイして平均スコアを表示する
+// 1つのTimeKeeperをゲームの間使い回し、ターンごとにresetして残りの持ち時間を割り振る
+fn test_ai_score_with_game_budget(config: MazeConfig, game_number: usize, total_ms: u64) {
+    let mut score_mean = 0.0;
+
+    for i in 0..game_number {
+        let mut state = MazeState::new(config, Some(i as u64));
+        let mut time_keeper = TimeKeeper::with_elapsed_budget(Duration::from_millis(total_ms), config.end_turn);
+
+        while !state.is_done() {
+            let (action, _) = beam_search_action_with_evaluator(&state, 5, &time_keeper, default_evaluator);
+            state.advance(action).expect("action must be legal");
+            time_keeper.reset();
+        }
+
+        score_mean += state.game_score as f64;
+    }
+
+    score_mean /= game_number as f64;
+    println!("Score with game budget {}ms({}x{}, {} turns):\t{}", total_ms, config.h, config.w, config.end_turn, score_mean);
+}
+
+// 1回の探索で得た行動列をまとめて実行しながらゲームをgame_number回プレイして平均スコアを表示する
+fn test_ai_score_with_actions(config: MazeConfig, game_number: usize) {
+    let mut score_mean = 0.0;
+
+    for i in 0..game_number {
+        let mut state = MazeState::new(config, Some(i as u64));
+
+        while !state.is_done() {
+            for action in beam_search_actions(&state, 5, 10) {
+                state.advance(action).expect("action must be legal");
+            }
+        }
+
+        score_mean += state.game_score as f64;
+    }
+
+    score_mean /= game_number as f64;
+    println!("Score with actions({}x{}, {} turns):\t{}", config.h, config.w, config.end_turn, score_mean);
 }
 
 #[allow(dead_code)]
 pub fn main() {
-    test_ai_score(100);
-}
\ No newline at end of file
+    // play_game(121321, 5, 10);
+    let ais = [
+        StringAIPair {
+            name: "beam_search_action_with_time_threshold".to_string(),
+            ai: |state| beam_search_action_with_time_threshold(state, 5, 10),
+        },
+        StringAIPair {
+            name: "beam_search_action_with_dedup".to_string(),
+            ai: |state| beam_search_action_with_dedup(state, 5, 10),
+        },
+        StringAIPair {
+            name: "beam_stack_search_action".to_string(),
+            ai: |state| beam_stack_search_action(state, 10),
+        },
+        StringAIPair {
+            name: "beam_search_action_with_horizon".to_string(),
+            ai: |state| beam_search_action_with_evaluator(state, 5, &TimeKeeper::new(10), |state| state.horizon_score(3)).0,
+        },
+        // 評価を常に0にすると、ビームに残る盤面はハッシュの大小で決まり、ランダムな行動よりもスコアが低くなる
+        StringAIPair {
+            name: "beam_search_action_with_zero_evaluator".to_string(),
+            ai: |state| beam_search_action_with_evaluator(state, 5, &TimeKeeper::new(10), |_| 0).0,
+        },
+    ];
+    // 盤面の大きさを変えて同じアルゴリズムを比較する。時間がかかるので進み具合を表示する
+    for config in [MazeConfig::new(10, 10, 30), CONFIG, MazeConfig { wall_ratio: 0.2, ..CONFIG }] {
+        for ai in &ais {
+            test_ai_score(ai, config, 100, Some(&eta_printer()));
+        }
+    }
+    test_ai_score_with_actions(CONFIG, 100);
+
+    // 罠のある盤面では、1手先しか見ない貪欲法は罠を避けるあまり罠に囲まれた場所から出られなくなる
+    // ビームサーチは先のポイントのために小さな罠を踏んで通り抜けるので、スコアに大きな差がつく
+    let trap_config = MazeConfig { trap_ratio: 0.2, ..CONFIG };
+    let greedy = StringAIPair {
+        name: "greedy_action".to_string(),
+        ai: |state| greedy_action(state).unwrap(),
+    };
+    test_ai_score(&greedy, trap_config, 100, None);
+    test_ai_score(&ais[0], trap_config, 100, None);
+
+    // ゲーム終了ターンが盤面より長い場合は、取ったマスのポイントを復活させて高いポイントの場所を巡回させる
+    let regen_config = MazeConfig { regen_after: Some(20), ..MazeConfig::new(10, 10, 300) };
+    test_ai_score(&greedy, regen_config, 100, None);
+    test_ai_score(&ais[0], regen_config, 100, None);
+
+    // 残っているポイントへの近さを評価に加えた場合と比較する
+    // 盤面全体のポイントを足すため、取ることよりもポイントの多い場所に近づくことを優先しがちで、平均スコアは上がらない
+    let potential_ais = [
+        StringAIPair {
+            name: "beam_search_action_with_time_threshold(width 3)".to_string(),
+            ai: |state| beam_search_action_with_time_threshold(state, 3, 10),
+        },
+        StringAIPair {
+            name: "beam_search_action_with_potential(width 3)".to_string(),
+            ai: |state| beam_search_action_with_evaluator(state, 3, &TimeKeeper::new(10), |state| state.game_score + state.manhattan_potential()).0,
+        },
+    ];
+    for ai in &potential_ais {
+        test_ai_score(ai, CONFIG, 100, None);
+    }
+
+    // ビーム幅を変えて展開ノード数とスコアを比較する
+    // ポイントが塊に集まった盤面では、評価が同じ盤面ばかりがビームに残り、塊にたどり着けないことが多い
+    // キャラクターの位置が散らばるように選ぶと、塊を見つけやすくなる
+    let diversity_ais = [
+        StringAIPair {
+            name: "beam_search_action_with_time_threshold".to_string(),
+            ai: |state| beam_search_action_with_time_threshold(state, 5, 10),
+        },
+        StringAIPair {
+            name: "beam_search_action_with_diversity(lambda 10)".to_string(),
+            ai: |state| beam_search_action_with_diversity(state, 5, 10, 10.0),
+        },
+    ];
+    for ai in &diversity_ais {
+        test_ai_score_on_clustered(ai, 100);
+    }
+
+    // 移動先のポイント順に展開すると、同じ行動を選びながら展開するノードを減らせる
+    for beam_width in [5, 10] {
+        test_ai_score_with_stats("beam_search_action_with_stats", beam_search_action_with_stats, beam_width, CONFIG, 100);
+        test_ai_score_with_stats("beam_search_action_with_move_ordering", beam_search_action_with_move_ordering, beam_width, CONFIG, 100);
+    }
+
+    // 直前に来たマスへ戻る行動を展開しないと、スコアを保ったまま展開するノードを4分の1ほど減らせる
+    for beam_width in [5, 10] {
+        test_ai_score_with_stats("beam_search_action_no_reverse", beam_search_action_no_reverse, beam_width, CONFIG, 100);
+    }
+
+    // 小さい盤面ではポイントを取り尽くせるので、スコアの上限で枝刈りして打ち切ると展開するノードを減らせる
+    // 大きい盤面では時間いっぱいまで探索するので、展開するノードはほとんど変わらない
+    let small_config = MazeConfig::new(5, 5, 40);
+    test_ai_score_with_stats("beam_search_action_with_stats", beam_search_action_with_stats, 5, small_config, 100);
+    test_ai_score_with_stats("beam_search_action_with_bound", beam_search_action_with_bound, 5, small_config, 100);
+
+    // 1ターン10msと同じ持ち時間をゲーム全体で管理する
+    test_ai_score_with_game_budget(CONFIG, 100, 10 * CONFIG.end_turn as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::BeamSearch04::beam_search_action;
+    use std::cell::{Cell, RefCell};
+
+    // 時間切れにならずに最後まで探索できる小さい盤面
+    const SMALL_CONFIG: MazeConfig = MazeConfig::new(5, 5, 10);
+    // 小さい盤面の探索が時間切れにならないだけの制限時間(ms)
+    const GENEROUS_MS: usize = 10000;
+
+    #[test]
+    fn beam_search_actions_starts_with_the_single_action() {
+        for seed in 0..20 {
+            let state = MazeState::new(SMALL_CONFIG, Some(seed));
+            let actions = beam_search_actions(&state, 5, GENEROUS_MS);
+            assert_eq!(actions[0], beam_search_action_with_time_threshold(&state, 5, GENEROUS_MS), "seed {}", seed);
+            assert_eq!(actions.len(), SMALL_CONFIG.end_turn);
+
+            let mut replayed = state.clone();
+            replayed.apply_actions(&actions).unwrap();
+            assert!(replayed.is_done());
+        }
+    }
+
+    // ゲーム終了ターンが短いとビームが時間切れの前に尽きる。その場合も合法な行動を返す
+    #[test]
+    fn beam_search_does_not_panic_when_the_beam_drains() {
+        for end_turn in 1..=3 {
+            for seed in 0..20 {
+                let config = MazeConfig { wall_ratio: 0.3, ..MazeConfig::new(3, 3, end_turn) };
+                let mut state = MazeState::new(config, Some(seed));
+                while !state.is_done() {
+                    for beam_width in [0, 1, 5] {
+                        let action = beam_search_action_with_time_threshold(&state, beam_width, GENEROUS_MS);
+                        assert!(state.legal_actions().contains(&action));
+                    }
+                    state.advance(beam_search_action_with_time_threshold(&state, 5, GENEROUS_MS)).unwrap();
+                }
+            }
+        }
+    }
+
+    // 時間切れにならない場合、ビーム幅を広げるほど展開するノードは増える
+    #[test]
+    fn nodes_expanded_grows_with_beam_width() {
+        for seed in 0..20 {
+            let state = MazeState::new(SMALL_CONFIG, Some(seed));
+            let nodes: Vec<u64> = [1, 2, 5, 10]
+                .iter()
+                .map(|&beam_width| beam_search_action_with_stats(&state, beam_width, GENEROUS_MS).1.nodes_expanded)
+                .collect();
+            assert!(nodes[0] > 0);
+            assert!(nodes.windows(2).all(|pair| pair[0] <= pair[1]), "seed {}: {:?}", seed, nodes);
+        }
+    }
+
+    // 評価が同じ盤面もターン、first_action、Zobristハッシュで順序が決まるので、同じ盤面からは毎回同じ行動列になる
+    #[test]
+    fn same_seed_and_width_give_identical_action_sequences() {
+        for seed in 0..10 {
+            let state = MazeState::new(SMALL_CONFIG, Some(seed));
+            assert_eq!(beam_search_actions(&state, 3, GENEROUS_MS), beam_search_actions(&state.clone(), 3, GENEROUS_MS));
+        }
+
+        // 評価が同じでも別の局面なら等しいとはみなさない
+        let state = MazeState::new(SMALL_CONFIG, Some(0));
+        let children: Vec<MazeState> = state.legal_actions().into_iter().map(|action| {
+            let mut child = state.clone();
+            child.advance(action).unwrap();
+            child.evaluated_score = 0;
+            child
+        }).collect();
+        for (i, a) in children.iter().enumerate() {
+            for b in &children[i + 1..] {
+                assert_ne!(a.cmp(b), std::cmp::Ordering::Equal);
+            }
+        }
+    }
+
+    // 1ターン分のスコアが減ったことがあるかどうかと、最終スコアを返す
+    fn play_counting_losses(mut state: MazeState, mut select_action: impl FnMut(&MazeState) -> usize) -> (bool, i32) {
+        let mut lost = false;
+        while !state.is_done() {
+            let before = state.game_score;
+            state.advance(select_action(&state)).unwrap();
+            lost |= state.game_score < before;
+        }
+        (lost, state.game_score)
+    }
+
+    // 貪欲法は1手先で点が減る罠を踏まないので、罠に囲まれると低い点の領域から出られない
+    // ビームサーチは罠で点を失ってでも、その先の高い点を取りに行く
+    #[test]
+    fn beam_search_crosses_traps_that_fence_in_greedy() {
+        let state: MazeState = "1@a9999".parse().unwrap();
+        let (greedy_lost, greedy_score) = play_counting_losses(state.clone(), |s| greedy_action(s).unwrap());
+        let (beam_lost, beam_score) = play_counting_losses(state, |s| beam_search_action_with_time_threshold(s, 5, GENEROUS_MS));
+        assert_eq!((greedy_lost, greedy_score), (false, 1));
+        assert_eq!((beam_lost, beam_score), (true, 36));
+
+        let config = MazeConfig { trap_ratio: 0.4, ..SMALL_CONFIG };
+        let (mut greedy_total, mut beam_total) = (0, 0);
+        for seed in 0..30 {
+            let state = MazeState::new(config, Some(seed));
+            greedy_total += play_counting_losses(state.clone(), |s| greedy_action(s).unwrap()).1;
+            beam_total += play_counting_losses(state, |s| beam_search_action_with_time_threshold(s, 5, GENEROUS_MS)).1;
+        }
+        assert!(beam_total > greedy_total, "beam {} greedy {}", beam_total, greedy_total);
+    }
+
+    // 次のビームをbeam_width個に抑えても、全ての子を残してから上位を取り出す元の実装と同じスコアになる
+    #[test]
+    fn bounded_beam_scores_like_the_unbounded_implementation() {
+        for seed in 0..20 {
+            for beam_width in [1, 3, 10] {
+                let mut bounded = MazeState::new(SMALL_CONFIG, Some(seed));
+                let mut unbounded = bounded.clone();
+                while !bounded.is_done() {
+                    bounded.advance(beam_search_action_with_time_threshold(&bounded, beam_width, GENEROUS_MS)).unwrap();
+                    unbounded.advance(beam_search_action(&unbounded, beam_width, SMALL_CONFIG.end_turn)).unwrap();
+                }
+                assert_eq!(bounded.game_score, unbounded.game_score, "seed {} width {}", seed, beam_width);
+            }
+        }
+    }
+
+    fn play_with_evaluator(mut state: MazeState, evaluator: Evaluator) -> i32 {
+        while !state.is_done() {
+            let (action, _) = beam_search_action_with_evaluator(&state, 5, &TimeKeeper::new(GENEROUS_MS), evaluator);
+            state.advance(action).unwrap();
+        }
+        state.game_score
+    }
+
+    // default_evaluatorを渡すと、評価関数を受け取る前の実装と同じ点になる
+    #[test]
+    fn default_evaluator_reproduces_the_evaluate_score_beam() {
+        for seed in 0..20 {
+            let state = MazeState::new(SMALL_CONFIG, Some(seed));
+            let mut expected = state.clone();
+            while !expected.is_done() {
+                expected.advance(beam_search_action(&expected, 5, SMALL_CONFIG.end_turn)).unwrap();
+            }
+            assert_eq!(play_with_evaluator(state, default_evaluator), expected.game_score, "seed {}", seed);
+        }
+    }
+
+    // 常に0を返す評価関数では盤面を区別できないので、点が大きく下がる
+    #[test]
+    fn zero_evaluator_scores_below_the_default() {
+        let (mut default_total, mut zero_total) = (0, 0);
+        for seed in 0..20 {
+            let state = MazeState::new(SMALL_CONFIG, Some(seed));
+            default_total += play_with_evaluator(state.clone(), default_evaluator);
+            zero_total += play_with_evaluator(state, |_| 0);
+        }
+        assert!(zero_total < default_total, "zero {} default {}", zero_total, default_total);
+    }
+
+    // ポイントの少ない盤面では、上限が届かない盤面を展開せず、取り尽くした時点で打ち切るので、展開するノードが減る
+    #[test]
+    fn bound_prunes_a_sparse_board() {
+        let state: MazeState = "@....\n..9..\n.....\n...5.\n.....".parse().unwrap();
+        let (bounded_action, bounded_stats) = beam_search_action_with_bound(&state, 5, GENEROUS_MS);
+        let (plain_action, plain_stats) = beam_search_action_with_stats(&state, 5, GENEROUS_MS);
+        assert!(
+            bounded_stats.nodes_expanded < plain_stats.nodes_expanded,
+            "bounded {} plain {}", bounded_stats.nodes_expanded, plain_stats.nodes_expanded
+        );
+
+        let mut bounded = state.clone();
+        bounded.advance(bounded_action).unwrap();
+        let mut plain = state;
+        plain.advance(plain_action).unwrap();
+        while !bounded.is_done() {
+            bounded.advance(beam_search_action_with_bound(&bounded, 5, GENEROUS_MS).0).unwrap();
+            plain.advance(beam_search_action_with_stats(&plain, 5, GENEROUS_MS).0).unwrap();
+        }
+        assert_eq!(bounded.game_score, 14);
+        assert_eq!(plain.game_score, 14);
+    }
+
+    // 罠のある盤面では途中のスコアが最終スコアの下限にならないので、枝刈りせずに通常のビームサーチと同じ探索をする
+    #[test]
+    fn bound_is_not_used_on_boards_with_traps() {
+        let config = MazeConfig { trap_ratio: 0.3, ..SMALL_CONFIG };
+        for seed in 0..20 {
+            let state = MazeState::new(config, Some(seed));
+            assert!(state.has_traps());
+            let (bounded_action, bounded_stats) = beam_search_action_with_bound(&state, 5, GENEROUS_MS);
+            let (plain_action, plain_stats) = beam_search_action_with_stats(&state, 5, GENEROUS_MS);
+            assert_eq!(bounded_action, plain_action, "seed {}", seed);
+            assert_eq!(bounded_stats.nodes_expanded, plain_stats.nodes_expanded, "seed {}", seed);
+        }
+    }
+
+    // progressは1回プレイするごとに、(1, n)から(n, n)まで順に呼ばれる
+    #[test]
+    fn progress_is_called_once_per_game() {
+        let ai = StringAIPair {
+            name: "greedy".to_string(),
+            ai: |state| greedy_action(state).unwrap(),
+        };
+        let calls = RefCell::new(Vec::new());
+        let record = |done: usize, total: usize| calls.borrow_mut().push((done, total));
+        test_ai_score(&ai, SMALL_CONFIG, 7, Some(&record));
+        assert_eq!(calls.into_inner(), (1..=7).map(|done| (done, 7)).collect::<Vec<_>>());
+    }
+
+    // 2手分展開した子を、評価が同じ盤面が多い候補として使う
+    fn two_ply_candidates(state: &MazeState) -> Vec<MazeState> {
+        let mut candidates = vec![state.clone()];
+        for _ in 0..2 {
+            candidates = candidates
+                .iter()
+                .flat_map(|now_state| {
+                    now_state.legal_actions().into_iter().map(move |action| {
+                        let mut next_state = now_state.clone();
+                        next_state.advance(action).unwrap();
+                        next_state.evaluate_score();
+                        next_state
+                    })
+                })
+                .collect();
+        }
+        candidates
+    }
+
+    // diversity_lambdaが0の場合は、評価の高い順に並べて先頭からbeam_width個取るのと同じ盤面を選ぶ
+    #[test]
+    fn zero_lambda_selects_the_plain_beam() {
+        for seed in 0..10 {
+            let candidates = two_ply_candidates(&clustered_state(seed, 4, 3));
+            for beam_width in [1, 3, 5, 100] {
+                let mut plain = candidates.clone();
+                plain.sort_unstable_by(|a, b| b.cmp(a));
+                plain.truncate(beam_width);
+                assert_eq!(select_diverse_beam(candidates.clone(), beam_width, 0.0), plain, "seed {} width {}", seed, beam_width);
+            }
+        }
+    }
+
+    // 1手あたり展開する盤面の数を固定して、clustered_stateの盤面をプレイしたスコアの合計
+    fn clustered_score_with_node_budget(beam_width: usize, node_budget: usize, diversity_lambda: f64) -> i32 {
+        (0..5)
+            .map(|seed| {
+                let mut state = clustered_state(seed, 4, 3);
+                while !state.is_done() {
+                    let nodes = Cell::new(0);
+                    let is_time_over = || {
+                        nodes.set(nodes.get() + 1);
+                        nodes.get() > node_budget
+                    };
+                    state.advance(diverse_beam_search(&state, beam_width, diversity_lambda, is_time_over)).unwrap();
+                }
+                state.game_score
+            })
+            .sum()
+    }
+
+    // 評価が同じ盤面の多いclustered_stateでは、同じビーム幅と展開数でも多様性を持たせた方がスコアが高い
+    #[test]
+    fn diversity_beats_the_plain_beam_on_clustered_boards() {
+        let plain = clustered_score_with_node_budget(10, 200, 0.0);
+        let diverse = clustered_score_with_node_budget(10, 200, 10.0);
+        assert!(diverse > plain, "diverse {} plain {}", diverse, plain);
+    }
+}
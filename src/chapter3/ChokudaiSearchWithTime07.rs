@@ -1,271 +1,477 @@
 #![allow(non_snake_case)]
 
-use rand::{Rng, SeedableRng, rngs, thread_rng};
 use std::collections::BinaryHeap;
 use std::time::Instant;
 
-// 時間を管理する構造体
-struct TimeKeeper {
-    start_time: Instant,
-    time_threshold: usize,
-}
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-impl TimeKeeper {
-    // 時間制限をミリ秒単位で指定してインスタンスをつくる。
-    fn new(time_threshold: usize) -> Self {
-        TimeKeeper {
-            start_time: Instant::now(),
-            time_threshold,
-        }
-    }
+use super::MazeState00::{ActionBuffer, Evaluator, MazeConfig, MazeState, default_evaluator};
+use crate::common::game::run_episodes;
+use crate::common::logging::{search_debug, search_trace};
+use crate::common::time_keeper::{Clock, TimeKeeper};
+use crate::common::stats::SearchStats;
 
-    // インスタンス生成した時から指定した時間制限を超過したか判定する。
-    fn is_time_over(&self) -> bool {
-        let elapsed_time = self.start_time.elapsed().as_millis() as usize;
-        elapsed_time >= self.time_threshold
-    }
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// ビーム1本あたりのビームの幅と深さ、本数を指定してchokudaiサーチで行動を決定する
+pub fn chokudai_search_action_wirh_time_threshold(state: &MazeState, beam_width: usize, beam_depth: usize, time_threshold: usize) -> usize {
+    chokudai_search_action_with_stats(state, beam_width, beam_depth, time_threshold).0
 }
 
-// // 座標を保持する
-#[derive(Debug, Clone, Copy)]
-struct Coord {
-    y: i32,
-    x: i32,
+// chokudai_search_action_wirh_time_thresholdと同じ探索を行い、探索にかかった計算量も返す
+pub fn chokudai_search_action_with_stats(state: &MazeState, beam_width: usize, beam_depth: usize, time_threshold: usize) -> (usize, SearchStats) {
+    chokudai_search_action_with_evaluator(state, beam_width, beam_depth, time_threshold, default_evaluator)
 }
 
-impl Coord {
-    fn new(y: i32, x: i32) -> Self {
-        Coord { y, x }
-    }
+// 盤面評価の関数を指定してchokudaiサーチを行い、行動と探索にかかった計算量を返す
+// 制限時間は盤面を1つ展開するごとに確認し、ビームを流している途中でも時間切れになった時点で打ち切る
+pub fn chokudai_search_action_with_evaluator(
+    state: &MazeState,
+    beam_width: usize,
+    beam_depth: usize,
+    time_threshold: usize,
+    evaluator: Evaluator,
+) -> (usize, SearchStats) {
+    chokudai_search_action_with_time_keeper(state, beam_width, beam_depth, &TimeKeeper::new(time_threshold), evaluator)
+}
+
+// 盤面評価の関数と時間管理を指定してchokudaiサーチを行い、行動と探索にかかった計算量を返す
+// time_keeperは呼び出し側でresetしておく。Instantを使えない環境では、独自のClockを使うTimeKeeperを渡す
+pub fn chokudai_search_action_with_time_keeper(
+    state: &MazeState,
+    beam_width: usize,
+    beam_depth: usize,
+    time_keeper: &TimeKeeper<impl Clock>,
+    evaluator: Evaluator,
+) -> (usize, SearchStats) {
+    let (action, mut stats) = chokudai_search(state, beam_width, beam_depth, usize::MAX, evaluator, || time_keeper.is_time_over());
+    stats.elapsed_ms = time_keeper.elapsed().as_millis();
+    (action, stats)
 }
 
-// 迷路の高さと幅
-const H: usize = 30;
-const W: usize = 30;
-// ゲーム終了ターン
-const END_TURN: usize = 100;
-
-// 一人ゲームの例
-// 1ターンに上下左右四方向のいずれかに1マスずつ進む。
-// 床にあるポイントを踏むと自身のスコアとなり、床のポイントが消える。
-// END_TURNの時点のスコアを高くすることが目的
-#[derive(Debug, Clone)]
-struct MazeState {
-    character: Coord,
-    points: [[i32; W]; H],
-    turn: usize,
-    game_score: i32,
-    evaluated_score: i32,
-    first_action: i32,
+// ビームを流す本数を指定してchokudaiサーチで行動を決定する
+// 時間で打ち切らないので、同じ盤面からは実行環境や負荷によらず同じ行動を返す
+pub fn chokudai_search_action_with_beam_number(state: &MazeState, beam_width: usize, beam_depth: usize, beam_number: usize) -> usize {
+    chokudai_search(state, beam_width, beam_depth, beam_number, default_evaluator, || false).0
 }
 
-impl MazeState{
-    fn new(seed: Option<u64>) -> Self {
-        let mut rng_for_construct: rngs::StdRng = SeedableRng::seed_from_u64(thread_rng().gen());
-        if let Some(s) = seed {
-            rng_for_construct = SeedableRng::seed_from_u64(s)
+// ビームをbeam_number本流すか、is_time_overがtrueを返すまでchokudaiサーチを行い、行動と探索にかかった計算量を返す
+// is_time_overは盤面を1つ展開するごとに呼ぶ
+fn chokudai_search(
+    state: &MazeState,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    evaluator: Evaluator,
+    is_time_over: impl Fn() -> bool,
+) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let beam = chokudai_beams(state, beam_width, beam_depth, beam_number, evaluator, is_time_over, &mut stats);
+
+    // beam[0]にはfirst_actionを持たないルートしか入らないので、beam[1]以降から選ぶ
+    for t in (1..=beam_depth).rev() {
+        let now_beam = &beam[t];
+        if let Some(best_state) = now_beam.peek() {
+            stats.depth_reached = t;
+            search_debug!(
+                "chokudai search: turn {} -> action {} (evaluated_score {}, depth {}, {} nodes)",
+                state.turn, best_state.first_action, best_state.evaluated_score, t, stats.nodes_expanded
+            );
+            return (best_state.first_action as usize, stats);
         }
-        let character = Coord::new(rng_for_construct.gen_range(0..H as i32), rng_for_construct.gen_range(0..W as i32));
+    }
 
-        let mut points = [[0; W]; H];   // 床のポイントを1~9で表現する
+    // 1手も展開できなかった場合
+    (state.legal_actions()[0], stats)
+}
 
-        // h*wの迷路を生成する。
-        for y in 0..H {
-            for x in 0..W {
-                if y == character.y as usize && x == character.x as usize {
-                    continue;
+// chokudai_searchでビームを流し、探索を終えた時点の深さごとのビームを返す
+// beam[0]にはルートだけが入り、beam[1]以降の盤面はルートで選んだ行動をfirst_actionに持つ
+fn chokudai_beams(
+    state: &MazeState,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    evaluator: Evaluator,
+    is_time_over: impl Fn() -> bool,
+    stats: &mut SearchStats,
+) -> Vec<BinaryHeap<MazeState>> {
+
+    let mut beam: Vec<BinaryHeap<MazeState>> = vec![BinaryHeap::new(); beam_depth + 1];
+    // let mut beam: Vec<BinaryHeap<MazeState>> = Vec::with_capacity(beam_depth + 1);
+    // beam.extend((0..=beam_depth).map(|_| BinaryHeap::new()));
+
+    beam[0].push(state.clone());
+    let mut legal_actions = ActionBuffer::new();
+
+    'search: for _ in 0..beam_number {
+        for t in 0..beam_depth {
+            // beam[t]から取り出しながらbeam[t + 1]に追加するので、beam[t]だけ一時的に借りる
+            let mut now_beam = std::mem::take(&mut beam[t]);
+
+            for _ in 0..beam_width {
+                // 時間切れの場合は、それまでに展開した盤面から行動を選ぶ
+                if is_time_over() {
+                    search_debug!("chokudai search: time over at depth {} after {} nodes", t, stats.nodes_expanded);
+                    beam[t] = now_beam;
+                    break 'search;
                 }
-                points[y][x] = rng_for_construct.gen_range(0..10);
-             }
-        }
 
-        let turn = 0;  // 現在のターン
-        let game_score = 0;  // ゲーム上で実際に得たスコア
-        let evaluated_score = 0;  // 探索上で評価したスコア
-        let first_action = -1;  // 探索木のルートノードで最初に選択した行動
-
-        MazeState {
-            character,
-            points,
-            turn,
-            game_score,
-            evaluated_score,
-            first_action,
-        }
-    }
+                if now_beam.is_empty() {
+                    break;
+                }
 
-    // [どのゲームでも実装する] : ゲームの終了判定
-    fn is_done(&mut self) -> bool {
-        self.turn == END_TURN
-    }
-    // [どのゲームでも実装する] : 探索用の盤面評価をする
-    fn evaluate_score(&mut self) {
-        self.evaluated_score = self.game_score;
-    }
-    // [どのゲームでも実装する] : 指定したactionでゲームを1ターン進める
-    fn advance(&mut self, action: usize) {
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
-
-        self.character.x += dx[action] as i32;
-        self.character.y += dy[action] as i32;
-
-        let point = &mut self.points[self.character.y as usize][self.character.x as usize];
-        if *point > 0 {
-            self.game_score += *point;
-            *point = 0;
-        }
+                if now_beam.peek().unwrap().is_done() {
+                    break;
+                }
+                let now_state = now_beam.pop().unwrap();
 
-        self.turn += 1;
-    }
-    // [どのゲームでも実装する] : 現在の状況でプレイヤーが可能な行動を全て取得する
-    fn legal_actions(&self) -> Vec<usize> {
-        let mut actions = Vec::new();
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
-
-        for action in 0..4 {
-            let ty = (self.character.y + dy[action]) as usize;
-            let tx = (self.character.x + dx[action]) as usize;
-            if ty < H && tx < W {
-                actions.push(action);
-            }
-        }
+                now_state.legal_actions_into(&mut legal_actions);
 
-        actions
-    }
+                for &action in &legal_actions {
+                    let mut next_state = now_state.clone();
+                    next_state.advance(action).expect("action must be legal");
+                    next_state.evaluate_with(evaluator);
+                    stats.nodes_expanded += 1;
+                    search_trace!("chokudai search: depth {} expand action {} -> evaluated_score {}", t, action, next_state.evaluated_score);
 
-    // [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
-    fn _to_string(&self) -> String {
-        let mut result = format!("turn:\t{}\nscore:\t{}\n", self.turn, self.game_score);
-
-        for h in 0..H {
-            for w in 0..W {
-                if self.character.y as usize == h && self.character.x as usize == w {
-                    result.push('@');
-                } else if self.points[h][w] > 0 {
-                    result.push_str(&self.points[h][w].to_string());
-                } else {
-                    result.push('.');
+                    if t == 0 {
+                        next_state.first_action = action as i32;
+                    }
+                    // ルート以外の盤面は、必ずルートで選んだ行動を引き継いでいる
+                    debug_assert!(next_state.first_action != -1, "first_action must be set below the root");
+
+                    beam[t + 1].push(next_state);
                 }
             }
-            result.push('\n');
-        }
 
-        result
+            beam[t] = now_beam;
+        }
     }
+
+    beam
 }
 
-// 探索時のソート用に評価を比較する
-impl Ord for MazeState {
+// chokudaiサーチ中の盤面と、ルートからその盤面に至るまでの行動列
+// 比較は盤面の比較だけで行い、chokudai_search_action_wirh_time_threshold と同じ順序で探索する
+#[derive(Clone)]
+struct PathNode {
+    state: MazeState,
+    actions: Vec<usize>,
+}
+
+impl Ord for PathNode {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.evaluated_score.cmp(&other.evaluated_score)
+        self.state.cmp(&other.state)
     }
 }
 
-impl PartialOrd for MazeState {
+impl PartialOrd for PathNode {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl PartialEq for MazeState {
+impl PartialEq for PathNode {
     fn eq(&self, other: &Self) -> bool {
-        self.evaluated_score == other.evaluated_score
+        self.state == other.state
     }
 }
 
-impl Eq for MazeState {}
+impl Eq for PathNode {}
 
-// ビーム1本あたりのビームの幅と深さ、本数を指定してchokudaiサーチで行動を決定する
-fn chokudai_search_action_wirh_time_threshold(state: &MazeState, beam_width: usize, beam_depth: usize, time_threshold: usize) -> usize {
+// chokudaiサーチを行い、最も深く探索できたビームの最良の行動列を返す
+// 同じ盤面を展開した時点で打ち切った場合、返り値の先頭は chokudai_search_action_wirh_time_threshold が返す行動と一致する
+pub fn chokudai_search_actions(state: &MazeState, beam_width: usize, beam_depth: usize, time_threshold: usize) -> Vec<usize> {
     let time_keeper = TimeKeeper::new(time_threshold);
+    chokudai_search_path(state, beam_width, beam_depth, usize::MAX, || time_keeper.is_time_over())
+}
 
-    let mut beam: Vec<BinaryHeap<MazeState>> = vec![BinaryHeap::new(); beam_depth + 1];
-    // let mut beam: Vec<BinaryHeap<MazeState>> = Vec::with_capacity(beam_depth + 1);
-    // beam.extend((0..=beam_depth).map(|_| BinaryHeap::new()));
-
-    beam[0].push(state.clone());
-
-    loop {
+// ビームをbeam_number本流すか、is_time_overがtrueを返すまでchokudaiサーチを行い、最良の行動列を返す
+// 返り値の先頭は、同じbeam_numberのchokudai_search_action_with_beam_numberが返す行動と一致する
+fn chokudai_search_path(
+    state: &MazeState,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    is_time_over: impl Fn() -> bool,
+) -> Vec<usize> {
+    let mut beam: Vec<BinaryHeap<PathNode>> = vec![BinaryHeap::new(); beam_depth + 1];
+
+    beam[0].push(PathNode { state: state.clone(), actions: Vec::new() });
+    let mut legal_actions = ActionBuffer::new();
+
+    'search: for _ in 0..beam_number {
         for t in 0..beam_depth {
-            let mut now_beam = beam[t].clone();
-            let mut next_beam = beam[t + 1].clone();
+            let mut now_beam = std::mem::take(&mut beam[t]);
 
             for _ in 0..beam_width {
+                if is_time_over() {
+                    beam[t] = now_beam;
+                    break 'search;
+                }
+
                 if now_beam.is_empty() {
                     break;
                 }
 
-                if now_beam.peek().unwrap().clone().is_done() {
+                if now_beam.peek().unwrap().state.is_done() {
                     break;
                 }
-                let now_state = now_beam.pop().unwrap();
+                let now_node = now_beam.pop().unwrap();
 
-                let legal_actions = now_state.legal_actions();
+                now_node.state.legal_actions_into(&mut legal_actions);
 
                 for &action in &legal_actions {
-                    let mut next_state = now_state.clone();
-                    next_state.advance(action);
-                    next_state.evaluate_score();
-
+                    let mut next_node = now_node.clone();
+                    next_node.state.advance(action).expect("action must be legal");
+                    next_node.state.evaluate_score();
                     if t == 0 {
-                        next_state.first_action = action as i32;
+                        next_node.state.first_action = action as i32;
                     }
+                    next_node.actions.push(action);
 
-                    next_beam.push(next_state);
+                    beam[t + 1].push(next_node);
                 }
             }
 
             beam[t] = now_beam;
-            beam[t + 1] = next_beam;
-        }
-        if time_keeper.is_time_over() {
-            break;
         }
     }
 
-    for t in (0..=beam_depth).rev() {
-        let now_beam = &beam[t];
-        if let Some(best_state) = now_beam.peek() {
-            return best_state.first_action as usize;
+    // beam[0]には行動列が空のルートしか入らないので、beam[1]以降から選ぶ
+    for t in (1..=beam_depth).rev() {
+        if let Some(best_node) = beam[t].peek() {
+            return best_node.actions.clone();
         }
     }
 
-    0 // ここには来ないはず
+    // 1手も展開できなかった場合
+    vec![state.legal_actions()[0]]
 }
 
-// ゲームをgame_number回プレイして平均スコアを表示する
-fn test_ai_score(game_number: usize) {
-    let mut score_mean = 0.0;
+// シードを指定してゲーム状況を表示しながらchokudaiサーチでプレイさせる。
+#[allow(dead_code)]
+fn play_game(seed: u64, beam_width: usize, time_threshold: usize) {
+    let mut state = MazeState::new(CONFIG, Some(seed));
+    println!("{}", state);
 
-    for _ in 0..game_number {
-        let mut state = MazeState::new(None);
+    while !state.is_done() {
+        state.advance(chokudai_search_action_wirh_time_threshold(&state, beam_width, CONFIG.end_turn, time_threshold)).expect("action must be legal");
+        println!("{}", state);
+    }
+}
 
-        // let mut c = 1;
-        while !state.is_done() {
-            let action = chokudai_search_action_wirh_time_threshold(&state, 1, END_TURN, 10);
-            state.advance(action);
-            // println!("{}, {}, {}", c, action, state.game_score);
-            // c += 1;
-        }
+// 盤面生成シードを0..game_numberとしてgame_number回プレイし、スコアの分布と1手あたりの展開ノード数を表示する
+fn test_ai_score(game_number: u64) {
+    let mut nodes_expanded = 0;
+    let mut action_count = 0;
+    let summary = run_episodes(
+        |seed| MazeState::new(CONFIG, Some(seed)),
+        |state: &MazeState| {
+            let (action, stats) = chokudai_search_action_with_stats(state, 1, CONFIG.end_turn, 10);
+            nodes_expanded += stats.nodes_expanded;
+            action_count += 1;
+            action
+        },
+        0..game_number,
+    );
+
+    println!("Score:\t{}", summary);
+    println!("nodes_expanded/action:\t{}", nodes_expanded as f64 / action_count as f64);
+}
 
-        let score = state.game_score;
-        // println!("score:\t{}", score);
-        score_mean += score as f64;
-    }
+// 1回の探索で得た行動列を全て実行しながらgame_number回プレイし、スコアの分布を表示する
+// 行動列を使い切ったら探索し直す。行動列は最後のターンを超えないので、次のゲームに持ち越されることはない
+fn test_ai_score_with_actions(game_number: u64) {
+    let mut planned_actions: Vec<usize> = Vec::new();
+    let summary = run_episodes(
+        |seed| MazeState::new(CONFIG, Some(seed)),
+        |state: &MazeState| {
+            if planned_actions.is_empty() {
+                planned_actions = chokudai_search_actions(state, 1, CONFIG.end_turn, 10);
+                planned_actions.reverse();
+            }
+            planned_actions.pop().expect("chokudai_search_actions returns at least one action")
+        },
+        0..game_number,
+    );
 
-    score_mean /= game_number as f64;
-    println!("Score:\t{}", score_mean);
+    println!("Score with actions:\t{}", summary);
 }
 
+// test_ai_score_parallelで1手ごとに流すビームの本数
+#[cfg(feature = "parallel")]
+const PARALLEL_BEAM_NUMBER: usize = 30;
+
+// 盤面生成シードを0..game_numberとしてgame_number回のプレイを並列に行い、平均スコアを表示して返す
+// 探索は時間ではなくビームの本数で打ち切り、スコアは整数のまま合計するので、スレッド数や負荷に関係なく同じ結果になる
+#[cfg(feature = "parallel")]
+fn test_ai_score_parallel(config: MazeConfig, game_number: usize) -> f64 {
+    let score_sum: i64 = (0..game_number as u64)
+        .into_par_iter()
+        .map(|seed| {
+            let mut state = MazeState::new(config, Some(seed));
+            while !state.is_done() {
+                let action = chokudai_search_action_with_beam_number(&state, 1, config.end_turn, PARALLEL_BEAM_NUMBER);
+                state.advance(action).expect("action must be legal");
+            }
+            state.game_score as i64
+        })
+        .sum();
+
+    let score_mean = score_sum as f64 / game_number as f64;
+    println!("Score:\t{}", score_mean);
+    score_mean
+}
 
 #[allow(dead_code)]
 pub fn main() {
+    // play_game(121321, 1, 10);
     let start_time = Instant::now();
     test_ai_score(10);
 
     // 経過時間を秒で表示する
     let elapsed_time = start_time.elapsed().as_secs_f64();
     println!("elapsed_time:\t{}", elapsed_time);
-}
\ No newline at end of file
+
+    // 1回の探索で最後のターンまでの行動列を決めるので、探索の回数が大きく減る
+    let start_time = Instant::now();
+    test_ai_score_with_actions(10);
+    let elapsed_time = start_time.elapsed().as_secs_f64();
+    println!("elapsed_time(with actions):\t{}", elapsed_time);
+
+    #[cfg(feature = "parallel")]
+    {
+        let start_time = Instant::now();
+        test_ai_score_parallel(CONFIG, 10);
+        let elapsed_time = start_time.elapsed().as_secs_f64();
+        println!("elapsed_time(parallel):\t{}", elapsed_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::ChokudaiSearch06::chokudai_search_action;
+    use crate::chapter3::MazeState00::Movement;
+    use crate::common::time_keeper::MockClock;
+    use std::time::Duration;
+
+    // 時間切れにならずに最後まで探索できる小さい盤面
+    const SMALL_CONFIG: MazeConfig = MazeConfig::new(5, 5, 10);
+
+    // ビームをその場で取り出す実装は、深さごとにビームを複製する元の実装と同じ行動を返す
+    #[test]
+    fn in_place_beams_match_the_cloning_implementation() {
+        for seed in 0..20 {
+            let mut state = MazeState::new(SMALL_CONFIG, Some(seed));
+            while !state.is_done() {
+                let rest = SMALL_CONFIG.end_turn - state.turn;
+                for (beam_width, beam_number) in [(1, 1), (2, 3), (3, 10)] {
+                    assert_eq!(
+                        chokudai_search_action_with_beam_number(&state, beam_width, rest, beam_number),
+                        chokudai_search_action(&state, beam_width, rest, beam_number),
+                        "seed {} turn {}", seed, state.turn
+                    );
+                }
+                state.advance(chokudai_search_action_with_beam_number(&state, 2, rest, 3)).unwrap();
+            }
+        }
+    }
+
+    // 並列に実行したときの平均スコアが、スレッド数によらず逐次に実行した場合と一致することを確かめる
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_score_is_independent_of_thread_count() {
+        let config = MazeConfig::new(5, 5, 10);
+        let game_number = 16;
+        let mut sequential_sum = 0;
+        for seed in 0..game_number as u64 {
+            let mut state = MazeState::new(config, Some(seed));
+            while !state.is_done() {
+                let action = chokudai_search_action_with_beam_number(&state, 1, config.end_turn, PARALLEL_BEAM_NUMBER);
+                state.advance(action).unwrap();
+            }
+            sequential_sum += state.game_score;
+        }
+        let sequential_mean = sequential_sum as f64 / game_number as f64;
+
+        for threads in [1, 2, 4] {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            assert_eq!(pool.install(|| test_ai_score_parallel(config, game_number)), sequential_mean, "{} threads", threads);
+        }
+    }
+
+    // 何本ビームを流しても、beam[1]以降の盤面は全てルートで選んだ合法手をfirst_actionに持つ
+    #[test]
+    fn every_state_below_the_root_has_a_first_action() {
+        let config = MazeConfig { wall_ratio: 0.3, movement: Movement::EightWay, ..SMALL_CONFIG };
+        for seed in 0..20 {
+            let state = MazeState::new(config, Some(seed));
+            let legal_actions = state.legal_actions();
+            for (beam_width, beam_number) in [(1, 1), (2, 5), (3, 20)] {
+                let mut stats = SearchStats::default();
+                let beam = chokudai_beams(&state, beam_width, config.end_turn, beam_number, default_evaluator, || false, &mut stats);
+                assert!(beam[0].iter().all(|root| root.first_action == -1));
+                for now_beam in &beam[1..] {
+                    for now_state in now_beam {
+                        assert!(legal_actions.contains(&(now_state.first_action as usize)), "seed {}: {}", seed, now_state.first_action);
+                    }
+                }
+            }
+        }
+    }
+
+    // 1手も展開しない場合は-1ではなく合法手を返す
+    #[test]
+    fn unexpanded_search_returns_a_legal_action() {
+        let state = MazeState::new(SMALL_CONFIG, Some(0));
+        for (beam_width, beam_depth) in [(0, SMALL_CONFIG.end_turn), (3, 0)] {
+            let action = chokudai_search_action_with_beam_number(&state, beam_width, beam_depth, 3);
+            assert!(state.legal_actions().contains(&action));
+        }
+    }
+
+    #[test]
+    fn chokudai_search_actions_starts_with_the_single_action() {
+        for seed in 0..20 {
+            let mut state = MazeState::new(SMALL_CONFIG, Some(seed));
+            while !state.is_done() {
+                let rest = SMALL_CONFIG.end_turn - state.turn;
+                for (beam_width, beam_number) in [(1, 1), (2, 3), (3, 10)] {
+                    let actions = chokudai_search_path(&state, beam_width, rest, beam_number, || false);
+                    assert!(!actions.is_empty() && actions.len() <= rest);
+                    assert_eq!(
+                        actions[0],
+                        chokudai_search_action_with_beam_number(&state, beam_width, rest, beam_number),
+                        "seed {} turn {}", seed, state.turn
+                    );
+                }
+                state.advance(chokudai_search_action_with_beam_number(&state, 2, rest, 3)).unwrap();
+            }
+        }
+    }
+
+    // 1本のビームを流し切るより短い制限時間でも、時間切れになった時点で打ち切って行動を返す
+    // 時刻を読むたびに1ms進む時計を使うので、何個展開したところで時間切れになるかは実行環境によらない
+    #[test]
+    fn tight_budget_returns_mid_sweep() {
+        let state = MazeState::new(CONFIG, Some(0));
+        let search = || {
+            let clock = MockClock::ticking(1);
+            let time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+            chokudai_search_action_with_time_keeper(&state, 1000, CONFIG.end_turn, &time_keeper, default_evaluator)
+        };
+        let (action, stats) = search();
+        assert!(state.legal_actions().contains(&action));
+        assert!(stats.nodes_expanded > 0);
+        // 1本目のビームを最後の深さまで流す前に打ち切っている
+        assert!(stats.depth_reached < CONFIG.end_turn);
+        // 同じ時計なら同じところで打ち切るので、同じ行動と計算量になる
+        let (again_action, again_stats) = search();
+        assert_eq!((again_action, again_stats.nodes_expanded, again_stats.depth_reached), (action, stats.nodes_expanded, stats.depth_reached));
+    }
+}
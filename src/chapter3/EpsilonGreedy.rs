@@ -0,0 +1,123 @@
+#![allow(non_snake_case)]
+
+use rand::{Rng, SeedableRng, rngs};
+
+use super::MazeState00::{MazeConfig, MazeState};
+use crate::common::game::greedy_action;
+use crate::common::stats::ScoreSummary;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+// 探索に使う乱数生成器のシード値
+const SEARCH_SEED: u64 = 42;
+
+// 確率epsilonで合法手から一様ランダムに行動を選び、それ以外は貪欲法で行動を決定する
+pub fn epsilon_greedy_action(state: &MazeState, epsilon: f64, rng: &mut impl Rng) -> usize {
+    let legal_actions = state.legal_actions();
+    if rng.gen_bool(epsilon) {
+        return legal_actions[rng.gen_range(0..legal_actions.len())];
+    }
+    greedy_action(state).unwrap()
+}
+
+// 各合法手で1手進めたスコアをtemperatureで割ったsoftmax分布から行動を選ぶ
+// temperatureが0に近いほど貪欲法に、大きいほど一様ランダムに近づく。temperatureが0以下の場合は貪欲法と同じ行動を返す
+pub fn softmax_action(state: &MazeState, temperature: f64, rng: &mut impl Rng) -> usize {
+    let legal_actions = state.legal_actions();
+    let scores: Vec<f64> = legal_actions
+        .iter()
+        .map(|&action| {
+            let mut next_state = state.clone();
+            next_state.advance(action).expect("action must be legal");
+            next_state.game_score as f64
+        })
+        .collect();
+    let max_score = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if temperature <= 0.0 {
+        return legal_actions[scores.iter().position(|&score| score == max_score).unwrap()];
+    }
+
+    // 最大値を引いてからexpを取り、温度が小さくてもオーバーフローしないようにする
+    let weights: Vec<f64> = scores.iter().map(|score| ((score - max_score) / temperature).exp()).collect();
+    let mut r = rng.gen_range(0.0..weights.iter().sum::<f64>());
+    for (action, weight) in legal_actions.iter().zip(&weights) {
+        if r < *weight {
+            return *action;
+        }
+        r -= weight;
+    }
+    *legal_actions.last().unwrap()
+}
+
+// 盤面生成シードを0..game_numberとしてgame_number回プレイしてスコアの分布を表示する
+// 探索に使う乱数生成器は呼び出し側で用意する
+fn test_ai_score(
+    name: &str,
+    game_number: usize,
+    rng: &mut rngs::StdRng,
+    mut select_action: impl FnMut(&MazeState, &mut rngs::StdRng) -> usize,
+) -> ScoreSummary {
+    let mut scores = Vec::with_capacity(game_number);
+
+    for i in 0..game_number {
+        let mut state = MazeState::new(CONFIG, Some(i as u64));
+
+        while !state.is_done() {
+            let action = select_action(&state, rng);
+            state.advance(action).expect("action must be legal");
+        }
+
+        scores.push(state.game_score);
+    }
+
+    let summary = ScoreSummary::from_scores(&scores);
+    println!("Score of {}:\t{}", name, summary);
+    summary
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    for epsilon in [0.0, 0.1, 0.3] {
+        // epsilonごとに同じシードの乱数生成器を使い、実行順に関係なく同じ結果になるようにする
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        let name = format!("epsilon_greedy_action(epsilon = {})", epsilon);
+        test_ai_score(&name, 100, &mut rng, |state, rng| epsilon_greedy_action(state, epsilon, rng));
+    }
+    for temperature in [0.0, 1.0, 100.0] {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        let name = format!("softmax_action(temperature = {})", temperature);
+        test_ai_score(&name, 100, &mut rng, |state, rng| softmax_action(state, temperature, rng));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 右に進むと5、左に進むと1、下に進むと3を取れる盤面
+    const BOARD: &str = "1@5\n.3.";
+
+    #[test]
+    fn zero_temperature_matches_greedy() {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        for seed in 0..20 {
+            let state = MazeState::new(CONFIG, Some(seed));
+            assert_eq!(softmax_action(&state, 0.0, &mut rng), greedy_action(&state).unwrap(), "seed {}", seed);
+        }
+    }
+
+    // 温度が低いと最もスコアの高い行動だけを選び、高いとどの行動もほぼ同じ確率で選ぶ
+    #[test]
+    fn softmax_at_both_temperature_extremes() {
+        let state: MazeState = BOARD.parse().unwrap();
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        assert!((0..1000).all(|_| softmax_action(&state, 1e-3, &mut rng) == 0));
+
+        let mut counts = [0; 4];
+        for _ in 0..3000 {
+            counts[softmax_action(&state, 1e6, &mut rng)] += 1;
+        }
+        assert_eq!(counts[3], 0);
+        assert!(counts[..3].iter().all(|&count| (900..1100).contains(&count)), "{:?}", counts);
+    }
+}
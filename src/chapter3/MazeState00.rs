@@ -1,144 +1,1860 @@
 #![allow(non_snake_case)]
 
-use rand::{Rng, SeedableRng};
+use arrayvec::ArrayVec;
+use once_cell::sync::Lazy;
+use rand::{Rng, SeedableRng, rngs};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::common::game::{Game, GameResult};
+use crate::common::rng::{make_rng, resolve_seed};
 
 // 座標を保持する
-#[derive(Debug, Clone, Copy)]
-struct Coord {
-    y: i32,
-    x: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Coord {
+    pub y: i32,
+    pub x: i32,
 }
 
 impl Coord {
-    fn new(y: i32, x: i32) -> Self {
+    pub fn new(y: i32, x: i32) -> Self {
         Coord { y, x }
     }
+
+    // otherとのマンハッタン距離を返す
+    pub fn manhattan(&self, other: &Coord) -> i32 {
+        (self.y - other.y).abs() + (self.x - other.x).abs()
+    }
+
+    // 右、左、下、上の順に隣接する4マスを返す。盤面外のマスも含むので、必要に応じてin_boundsで除く
+    pub fn neighbors4(&self) -> impl Iterator<Item = Coord> {
+        let coord = *self;
+        (0..4).map(move |action| coord.step(action))
+    }
+
+    // 高さh、幅wの盤面の内側にあるか判定する
+    pub fn in_bounds(&self, h: usize, w: usize) -> bool {
+        self.y >= 0 && self.y < h as i32 && self.x >= 0 && self.x < w as i32
+    }
+
+    // 行動番号actionの方向に1マス進んだ座標を返す。行動番号はdy8、dx8の並びに従う
+    pub fn step(&self, action: usize) -> Coord {
+        Coord::new(self.y + dy8[action], self.x + dx8[action])
+    }
+}
+
+// キャラクターが1ターンに移動できる方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Movement {
+    FourWay,   // 上下左右
+    EightWay,  // 上下左右と斜め
+}
+
+impl Movement {
+    // 行動の数。行動番号 0..4 はどちらの場合も同じ方向を表す
+    pub const fn action_n(self) -> usize {
+        match self {
+            Movement::FourWay => 4,
+            Movement::EightWay => 8,
+        }
+    }
+}
+
+// 右、左、下、上、右下、左下、右上、左上の順の移動量。四方向の場合は先頭の4つを使う
+#[allow(non_upper_case_globals)]
+const dy8: [i32; 8] = [0, 0, 1, -1, 1, 1, -1, -1];
+#[allow(non_upper_case_globals)]
+const dx8: [i32; 8] = [1, -1, 0, 0, 1, -1, 1, -1];
+// 各行動と逆向きに進む行動
+const REVERSE_ACTION: [usize; 8] = [1, 0, 3, 2, 7, 6, 5, 4];
+
+// 合法手を書き込むバッファ。行動は最大8つなのでヒープを使わずに持てる
+pub type ActionBuffer = ArrayVec<usize, 8>;
+
+// 迷路の高さと幅、ゲーム終了ターンを実行時に指定する
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MazeConfig {
+    pub h: usize,         // 迷路の高さ
+    pub w: usize,         // 迷路の幅
+    pub end_turn: usize,  // ゲーム終了ターン
+    pub wall_ratio: f64,  // 壁にするマスの割合
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trap_ratio: f64,  // 罠にするマスの割合
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub regen_after: Option<usize>,  // 取ったマスのポイントが元に戻るまでのターン数。Noneの場合は戻らない
+    pub movement: Movement,  // 移動できる方向
+    #[cfg_attr(feature = "serde", serde(default = "default_point_min"))]
+    pub point_min: i32,  // 床に置くポイントの最小値
+    #[cfg_attr(feature = "serde", serde(default = "default_point_max"))]
+    pub point_max: i32,  // 床に置くポイントの最大値
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub score_start_cell: bool,  // trueの場合はキャラクターの初期位置にもポイントを置き、ターン0で取ったものとしてスコアに加える
+}
+
+impl MazeConfig {
+    // 壁と罠のない四方向移動の迷路の設定をつくる。壁を置く場合は wall_ratio を、罠を置く場合は trap_ratio を、
+    // ポイントを復活させる場合は regen_after を、斜めに移動する場合は movement を指定する。
+    // 床のポイントは0~9とし、変える場合は point_min と point_max を指定する。point_min は0以上とし、負のポイントは trap_ratio で置く。
+    // キャラクターの初期位置にはポイントを置かない。置いてスコアに数える場合は score_start_cell を指定する。
+    pub const fn new(h: usize, w: usize, end_turn: usize) -> Self {
+        MazeConfig {
+            h,
+            w,
+            end_turn,
+            wall_ratio: 0.0,
+            trap_ratio: 0.0,
+            regen_after: None,
+            movement: Movement::FourWay,
+            point_min: DEFAULT_POINT_MIN,
+            point_max: DEFAULT_POINT_MAX,
+            score_start_cell: false,
+        }
+    }
+}
+
+// 床に置くポイントの標準の範囲
+const DEFAULT_POINT_MIN: i32 = 0;
+const DEFAULT_POINT_MAX: i32 = 9;
+
+// point_min と point_max を持たないJSONを読み込む場合は標準の範囲とする
+#[cfg(feature = "serde")]
+fn default_point_min() -> i32 {
+    DEFAULT_POINT_MIN
+}
+
+#[cfg(feature = "serde")]
+fn default_point_max() -> i32 {
+    DEFAULT_POINT_MAX
+}
+
+// Zobristハッシュに使う乱数表
+#[derive(Debug)]
+pub struct ZobristTable {
+    points: Vec<u64>,     // マスにポイントが残っている場合に使う値
+    character: Vec<u64>,  // キャラクターがマスにいる場合に使う値
+    turn: Vec<u64>,       // ターンごとに使う値
+}
+
+impl ZobristTable {
+    // 盤面の大きさごとに決まったシードで乱数表をつくる
+    fn new(config: &MazeConfig) -> Self {
+        let mut rng: rngs::StdRng = SeedableRng::seed_from_u64(ZOBRIST_SEED);
+        let cell_n = config.h * config.w;
+        ZobristTable {
+            points: (0..cell_n).map(|_| rng.gen()).collect(),
+            character: (0..cell_n).map(|_| rng.gen()).collect(),
+            turn: (0..=config.end_turn).map(|_| rng.gen()).collect(),
+        }
+    }
+
+    // 盤面の大きさが同じなら同じ乱数表を使い回す。初めて使う大きさの場合だけ生成する。
+    fn get(config: &MazeConfig) -> Arc<ZobristTable> {
+        static TABLES: Lazy<Mutex<ZobristTableCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+        let mut tables = TABLES.lock().unwrap();
+        tables
+            .entry((config.h, config.w, config.end_turn))
+            .or_insert_with(|| Arc::new(ZobristTable::new(config)))
+            .clone()
+    }
 }
 
-// 迷路の高さと幅
-const H: usize = 3;
-const W: usize = 4;
-// ゲーム終了ターン
-const END_TURN: usize = 4;
+const ZOBRIST_SEED: u64 = 20240103;
+// (高さ, 幅, ゲーム終了ターン)ごとの乱数表
+type ZobristTableCache = HashMap<(usize, usize, usize), Arc<ZobristTable>>;
+
+// evaluate_score_with_horizonで割り引いたポイントを整数で扱うための倍率
+const HORIZON_SCALE: i32 = 1 << 8;
+const HORIZON_SHIFT: i32 = 2;  // 割引率 1/2^(d+HORIZON_SHIFT) の指数に加える値
+
+// 罠のマスで減るポイントの最大値
+const TRAP_POINT_MAX: i32 = 5;
+// 盤面の文字列で表せる罠のポイントの最小値。'a'~'i'の9文字で表す
+const TRAP_POINT_MIN: i32 = -9;
+
+// 罠のマスを表示する文字。-1~-9を'a'~'i'で表す
+fn trap_char(point: i32) -> char {
+    debug_assert!((TRAP_POINT_MIN..=-1).contains(&point), "trap point {} cannot be rendered", point);
+    (b'a' + (-point - 1) as u8) as char
+}
+
+// to_colored_stringで使うANSIエスケープシーケンス
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_YELLOW: &str = "\x1b[33m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_GRAY: &str = "\x1b[90m";
+const ANSI_MAGENTA: &str = "\x1b[35m";
+const ANSI_BRIGHT_CYAN: &str = "\x1b[96m";
+
+// 合法でない行動をadvanceに渡したときのエラー
+// 探索が誤った行動を選んだ場合に、どう合法でなかったのかを呼び出し側で調べられるようにする
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalAction {
+    GameOver,                        // ゲームが終了している(どこにも移動できない場合を含む)
+    UnknownAction(usize),            // 移動できる方向の行動番号ではない
+    OutOfBounds { y: i32, x: i32 },  // 移動先が盤面の外
+    Wall(Coord),                     // 移動先が壁
+}
+
+impl fmt::Display for IllegalAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IllegalAction::GameOver => write!(f, "the game is already over"),
+            IllegalAction::UnknownAction(action) => write!(f, "action {} is not a direction of this board", action),
+            IllegalAction::OutOfBounds { y, x } => write!(f, "({}, {}) is out of the board", y, x),
+            IllegalAction::Wall(coord) => write!(f, "({}, {}) is a wall", coord.y, coord.x),
+        }
+    }
+}
+
+impl std::error::Error for IllegalAction {}
+
+// advance_undoableで進めた1ターンを元に戻すための記録
+#[derive(Debug, Clone, Copy)]
+pub struct UndoToken {
+    character: Coord,      // 移動前の座標
+    point: i32,            // 移動先で取ったポイント
+    regen_turn: usize,     // 移動先のマスのポイントが復活するターン
+    evaluated_score: i32,  // 進める前の探索上の評価
+    hash: u64,             // 進める前のZobristハッシュ
+    last_action: Option<usize>,  // 進める前に最後に選んでいた行動
+}
+
+// 盤面の文字列を読み込めなかったときのエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,                                          // 盤面の行がない
+    InvalidHeader(String),                          // turn: や score: の値が数値でない
+    RaggedRow { row: usize },                       // 行によって幅が違う
+    InvalidChar { row: usize, col: usize, ch: char },  // '@', '#', '.', 数字以外の文字がある
+    MissingCharacter,                               // '@' がない
+    MultipleCharacters,                             // '@' が複数ある
+    TurnExceedsEndTurn,                             // turn: がゲーム終了ターンを超えている
+}
 
 // 一人ゲームの例
-// 1ターンに上下左右四方向のいずれかに1マスずつ進む。
+// 1ターンに上下左右四方向のいずれかに1マスずつ進む。(EightWayの場合は斜めにも進める)
+// 壁のあるマスには進めない。
 // 床にあるポイントを踏むと自身のスコアとなり、床のポイントが消える。
 // END_TURNの時点のスコアを高くすることが目的
-struct MazeState {
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "MazeStateFields"))]
+pub struct MazeState {
+    pub config: MazeConfig,
+    pub character: Coord,
+    pub points: Vec<i32>,  // y * w + x 番目のマスのポイント
+    pub walls: Vec<bool>,  // y * w + x 番目のマスが壁かどうか
+    pub turn: usize,
+    pub game_score: i32,
+    pub evaluated_score: i32,
+    pub first_action: i32,
+    pub last_action: Option<usize>,  // 最後にadvanceで選んだ行動。まだ動いていない場合はNone
+    pub record_history: bool,  // trueの場合はadvanceで選んだ行動をhistoryに記録する
+    pub history: Vec<usize>,   // これまでに選んだ行動
+    pub original_points: Vec<i32>,  // 復活させるときに戻すポイント。regen_afterがNoneの場合は空
+    pub regen_turn: Vec<usize>,     // 取ったマスのポイントが復活するターン。regen_afterがNoneの場合は空
+    seed: u64,  // 盤面を生成したシード。文字列から読み込んだ盤面では0
+    #[cfg_attr(feature = "serde", serde(skip))]
+    zobrist: Arc<ZobristTable>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hash: u64,  // advanceで差分更新するZobristハッシュ
+}
+
+// JSONから復元するときに読み込む項目。Zobristハッシュは読み込んだ後に計算し直す
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct MazeStateFields {
+    config: MazeConfig,
     character: Coord,
-    points: [[i32; W]; H],
+    points: Vec<i32>,
+    walls: Vec<bool>,
     turn: usize,
     game_score: i32,
+    evaluated_score: i32,
+    first_action: i32,
+    #[serde(default)]
+    last_action: Option<usize>,
+    #[serde(default)]
+    record_history: bool,
+    #[serde(default)]
+    history: Vec<usize>,
+    #[serde(default)]
+    original_points: Vec<i32>,
+    #[serde(default)]
+    regen_turn: Vec<usize>,
+    #[serde(default)]
+    seed: u64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<MazeStateFields> for MazeState {
+    type Error = String;
+
+    // 盤面の大きさと各項目が食い違っていないか確認してから復元する
+    fn try_from(fields: MazeStateFields) -> Result<Self, Self::Error> {
+        let config = fields.config;
+        if fields.points.len() != config.h * config.w {
+            return Err("points does not match the board size".to_string());
+        }
+        if fields.walls.len() != config.h * config.w {
+            return Err("walls does not match the board size".to_string());
+        }
+        if !(0..config.h as i32).contains(&fields.character.y) || !(0..config.w as i32).contains(&fields.character.x) {
+            return Err("character is out of the board".to_string());
+        }
+        if fields.turn > config.end_turn {
+            return Err("turn exceeds end_turn".to_string());
+        }
+        let regen_len = if config.regen_after.is_some() { config.h * config.w } else { 0 };
+        if fields.original_points.len() != regen_len || fields.regen_turn.len() != regen_len {
+            return Err("regen cells do not match regen_after".to_string());
+        }
+
+        let mut state = MazeState {
+            config,
+            character: fields.character,
+            points: fields.points,
+            walls: fields.walls,
+            turn: fields.turn,
+            game_score: fields.game_score,
+            evaluated_score: fields.evaluated_score,
+            first_action: fields.first_action,
+            last_action: fields.last_action,
+            record_history: fields.record_history,
+            history: fields.history,
+            original_points: fields.original_points,
+            regen_turn: fields.regen_turn,
+            seed: fields.seed,
+            zobrist: ZobristTable::get(&config),
+            hash: 0,
+        };
+        state.hash = state.compute_zobrist_key();
+        Ok(state)
+    }
 }
 
 impl MazeState {
-    fn new(seed: u64) -> Self {
-        let mut rng_for_construct = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
-        let character = Coord::new(rng_for_construct.gen_range(0..H as i32), rng_for_construct.gen_range(0..W as i32));
+    // シードを指定しない場合は毎回異なる迷路を生成する
+    // 盤面はconfigとシードだけで決まり、同じ設定とシードで生成した盤面はポイント、壁、キャラクターの位置が等しくなる
+    // seedがNoneの場合は乱数でシードを選び、選んだシードはseed()で取得できる
+    pub fn new(config: MazeConfig, seed: Option<u64>) -> Self {
+        assert!(config.point_min >= 0, "point_min must not be negative; use trap_ratio for negative cells");
+        assert!(config.point_min <= config.point_max, "point_min must not exceed point_max");
+        let seed = resolve_seed(seed);
+        let mut rng_for_construct = make_rng(seed);
+        let character = Coord::new(rng_for_construct.gen_range(0..config.h as i32), rng_for_construct.gen_range(0..config.w as i32));
 
-        let mut points = [[0; W]; H];  // 床のポイントを1~9で表現する
+        let mut points = vec![0; config.h * config.w];  // 床のポイントをpoint_min~point_maxで表現する
 
         // h*wの迷路を生成する。
-        for y in 0..H {
-            for x in 0..W {
+        let start = character.y as usize * config.w + character.x as usize;
+        for (i, point) in points.iter_mut().enumerate() {
+            if i == start && !config.score_start_cell {
+                continue;
+            }
+            *point = rng_for_construct.gen_range(config.point_min..=config.point_max);
+        }
+
+        // キャラクターの初期位置以外のマスを wall_ratio の割合で壁にする。壁のマスにはポイントを置かない。
+        let mut walls = vec![false; config.h * config.w];
+        for y in 0..config.h {
+            for x in 0..config.w {
                 if y == character.y as usize && x == character.x as usize {
                     continue;
                 }
-                points[y][x] = rng_for_construct.gen_range(0..10);
+                if rng_for_construct.gen_bool(config.wall_ratio) {
+                    walls[y * config.w + x] = true;
+                    points[y * config.w + x] = 0;
+                }
             }
         }
 
+        // 壁以外のマスを trap_ratio の割合で罠にする。罠のマスは-1~-5のポイントで、踏むとスコアが減る。
+        for (i, point) in points.iter_mut().enumerate() {
+            if i == start || walls[i] {
+                continue;
+            }
+            if rng_for_construct.gen_bool(config.trap_ratio) {
+                *point = rng_for_construct.gen_range(-TRAP_POINT_MAX..=-1);
+            }
+        }
+
+        // ポイントを復活させる場合は、生成したときのポイントを覚えておく
+        let (original_points, mut regen_turn) = match config.regen_after {
+            Some(_) => (points.clone(), vec![0; config.h * config.w]),
+            None => (Vec::new(), Vec::new()),
+        };
+
         let turn = 0;  // 現在のターン
-        let game_score = 0;  // ゲーム上で実際に得たスコア
+        let mut game_score = 0;  // ゲーム上で実際に得たスコア
+        let evaluated_score = 0;  // 探索上で評価したスコア
 
-        MazeState {
+        // 初期位置のポイントを数える場合は、ターン0でそのマスを踏んだものとして取る
+        if config.score_start_cell {
+            game_score = points[start];
+            points[start] = 0;
+            if let Some(regen_after) = config.regen_after {
+                regen_turn[start] = turn + regen_after;
+            }
+        }
+        let first_action = -1;  // 探索木のルートノードで最初に選択した行動
+
+        let mut state = MazeState {
+            config,
             character,
             points,
+            walls,
             turn,
             game_score,
+            evaluated_score,
+            first_action,
+            last_action: None,
+            record_history: false,
+            history: Vec::new(),
+            original_points,
+            regen_turn,
+            seed,
+            zobrist: ZobristTable::get(&config),
+            hash: 0,
+        };
+        state.hash = state.compute_zobrist_key();
+        state
+    }
+
+    // ポイントも壁もない盤面をつくり、キャラクターを(0, 0)に置く
+    // set_pointでポイントを置いて、シードに頼らずに決まった局面を組み立てるときに使う。seed()は0になる
+    pub fn empty(config: MazeConfig) -> Self {
+        let cell_n = config.h * config.w;
+        let regen_len = if config.regen_after.is_some() { cell_n } else { 0 };
+        let mut state = MazeState {
+            config,
+            character: Coord::new(0, 0),
+            points: vec![0; cell_n],
+            walls: vec![false; cell_n],
+            turn: 0,
+            game_score: 0,
+            evaluated_score: 0,
+            first_action: -1,
+            last_action: None,
+            record_history: false,
+            history: Vec::new(),
+            original_points: vec![0; regen_len],
+            regen_turn: vec![0; regen_len],
+            seed: 0,
+            zobrist: ZobristTable::get(&config),
+            hash: 0,
+        };
+        state.hash = state.compute_zobrist_key();
+        state
+    }
+
+    // (y, x)のマスのポイントを返す。盤面外を指定した場合はpanicする
+    pub fn get_point(&self, y: usize, x: usize) -> i32 {
+        assert!(y < self.config.h && x < self.config.w, "({}, {}) is out of the {}x{} board", y, x, self.config.h, self.config.w);
+        self.at(y, x)
+    }
+
+    // (y, x)のマスのポイントをvalueにする。盤面外や、罠として表せない-10以下の値を指定した場合はpanicする
+    // ポイントが復活する盤面では、復活するときのポイントもvalueにする
+    pub fn set_point(&mut self, y: usize, x: usize, value: i32) {
+        assert!(y < self.config.h && x < self.config.w, "({}, {}) is out of the {}x{} board", y, x, self.config.h, self.config.w);
+        assert!(value >= TRAP_POINT_MIN, "point {} is below the lowest trap {}", value, TRAP_POINT_MIN);
+        let cell = y * self.config.w + x;
+        if (self.points[cell] != 0) != (value != 0) {
+            self.hash ^= self.zobrist.points[cell];
+        }
+        self.points[cell] = value;
+        if self.config.regen_after.is_some() {
+            self.original_points[cell] = value;
         }
     }
 
     // [どのゲームでも実装する] : ゲームの終了判定
-    fn is_done(&self) -> bool {
-        self.turn == END_TURN
+    // 壁に囲まれてどこにも移動できない場合もゲーム終了とする
+    pub fn is_done(&self) -> bool {
+        self.turn == self.config.end_turn || self.is_stuck()
+    }
+
+    // [どのゲームでも実装する] : 探索用の盤面評価をする
+    pub fn evaluate_score(&mut self) {
+        self.evaluated_score = self.game_score;
+    }
+
+    // 盤面に残っているポイントの合計を返す。罠のマイナスのポイントは数えない
+    // ポイントが復活しない場合、これ以上スコアを伸ばせる量の上限になる
+    pub fn remaining_points(&self) -> i32 {
+        self.points.iter().filter(|&&point| point > 0).sum()
+    }
+
+    // 盤面に罠が残っているか判定する
+    // 罠がなければ、移動してもgame_scoreが減ることはない
+    pub fn has_traps(&self) -> bool {
+        self.points.iter().any(|&point| point < 0)
+    }
+
+    // 最終スコアの上限を楽観的に見積もる。探索の枝刈りやA*のヒューリスティックに使う
+    // 壁や移動経路を無視して、残りターン数の歩数以内で届くマスのポイントを、1ターンに1マスずつ高い順に取れるものとする。
+    // 斜めに移動できる場合はチェビシェフ距離、そうでない場合はマンハッタン距離で数える。
+    // ポイントが復活する盤面では、届く範囲で最も高いポイントを毎ターン取れるものとする
+    pub fn score_upper_bound(&self) -> i32 {
+        let rest_turns = self.config.end_turn - self.turn;
+        let source = if self.config.regen_after.is_some() { &self.original_points } else { &self.points };
+        let mut reachable = Vec::new();
+        for y in 0..self.config.h as i32 {
+            for x in 0..self.config.w as i32 {
+                let cell = Coord::new(y, x);
+                let distance = match self.config.movement {
+                    Movement::FourWay => self.character.manhattan(&cell),
+                    Movement::EightWay => (y - self.character.y).abs().max((x - self.character.x).abs()),
+                };
+                let point = source[self.cell_index(cell)];
+                if distance as usize <= rest_turns && point > 0 {
+                    reachable.push(point);
+                }
+            }
+        }
+
+        if self.config.regen_after.is_some() {
+            return self.game_score + rest_turns as i32 * reachable.into_iter().max().unwrap_or(0);
+        }
+        reachable.sort_unstable_by(|a, b| b.cmp(a));
+        self.game_score + reachable.into_iter().take(rest_turns).sum::<i32>()
+    }
+
+    // スコアを0~1に正規化して返す。MCTSやThunderサーチで価値として使う
+    // 上限は、残っているポイントを全て取った場合のスコアと、毎ターンpoint_maxを取った場合のスコアの小さい方とする。
+    // ポイントが復活せず罠もない盤面では、上限はゲームを通して変わらない。ポイントのない盤面では0を返す
+    pub fn normalized_score(&self) -> f64 {
+        let max_score = (self.game_score + self.remaining_points()).min(self.config.end_turn as i32 * self.config.point_max);
+        if max_score <= 0 {
+            return 0.0;
+        }
+        (self.game_score.max(0) as f64 / max_score as f64).min(1.0)
+    }
+
+    // 探索用の盤面評価をevaluatorで行う
+    pub fn evaluate_with(&mut self, evaluator: Evaluator) {
+        self.evaluated_score = evaluator(self);
+    }
+
+    // 探索用の盤面評価に、キャラクターからradius歩以内で取れるポイントを距離に応じて割り引いて加える
+    // 距離dのマスのポイントは 1/2^(d+2) 倍する。割り引いた値を整数で扱うため、評価はHORIZON_SCALE倍になる
+    pub fn evaluate_score_with_horizon(&mut self, radius: usize) {
+        self.evaluated_score = self.horizon_score(radius);
+    }
+
+    // evaluate_score_with_horizonで使う評価を返す
+    pub fn horizon_score(&self, radius: usize) -> i32 {
+        let r = radius as i32;
+        let mut horizon = 0;
+        for dy in -r..=r {
+            let rest = r - dy.abs();
+            for dx in -rest..=rest {
+                let d = dy.abs() + dx.abs();
+                let ty = self.character.y + dy;
+                let tx = self.character.x + dx;
+                if d == 0 || ty < 0 || ty >= self.config.h as i32 || tx < 0 || tx >= self.config.w as i32 {
+                    continue;
+                }
+                horizon += (self.at(ty as usize, tx as usize) * HORIZON_SCALE).checked_shr((d + HORIZON_SHIFT) as u32).unwrap_or(0);
+            }
+        }
+        self.game_score * HORIZON_SCALE + horizon
+    }
+
+    // 残っているポイントのマスそれぞれについて、ポイント / (1 + キャラクターからのマンハッタン距離) を合計する
+    // 近いうちに取れそうなポイントの量を表す
+    pub fn manhattan_potential(&self) -> i32 {
+        let mut potential = 0;
+        for y in 0..self.config.h {
+            for x in 0..self.config.w {
+                let point = self.at(y, x);
+                if point <= 0 {
+                    continue;
+                }
+                let d = self.character.manhattan(&Coord::new(y as i32, x as i32));
+                potential += point / (1 + d);
+            }
+        }
+        potential
+    }
+
+    // 探索用の盤面評価に、manhattan_potentialを加える
+    pub fn evaluate_score_with_potential(&mut self) {
+        self.evaluated_score = self.game_score + self.manhattan_potential();
     }
 
     // [どのゲームでも実装する] : 指定したactionでゲームを1ターン進める
-    fn advance(&mut self, action: usize) {
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
+    // 壁や盤面外に進む行動、ゲーム終了後の行動はエラーを返し、盤面は変更しない
+    pub fn advance(&mut self, action: usize) -> Result<(), IllegalAction> {
+        if let Some(error) = self.illegal_reason(action) {
+            return Err(error);
+        }
 
-        self.character.x += dx[action] as i32;
-        self.character.y += dy[action] as i32;
+        self.hash ^= self.zobrist.character[self.cell_index(self.character)];
+        self.character = self.character.step(action);
+        let cell = self.cell_index(self.character);
+        self.hash ^= self.zobrist.character[cell];
 
-        let point = &mut self.points[self.character.y as usize][self.character.x as usize];
-        if *point > 0 {
+        let point = &mut self.points[cell];
+        if *point != 0 {
             self.game_score += *point;
             *point = 0;
+            self.hash ^= self.zobrist.points[cell];
+            if let Some(regen_after) = self.config.regen_after {
+                self.regen_turn[cell] = self.turn + regen_after;
+            }
         }
 
+        self.hash ^= self.zobrist.turn[self.turn];
         self.turn += 1;
+        self.hash ^= self.zobrist.turn[self.turn];
+
+        // 復活するターンになったマスのポイントを元に戻す
+        if self.config.regen_after.is_some() {
+            for i in 0..self.points.len() {
+                if self.regen_turn[i] == self.turn && self.points[i] == 0 {
+                    self.points[i] = self.original_points[i];
+                    self.hash ^= self.zobrist.points[i];
+                }
+            }
+        }
+
+        self.last_action = Some(action);
+        if self.record_history {
+            self.history.push(action);
+        }
+
+        Ok(())
+    }
+
+    // advanceと同じようにゲームを1ターン進め、undoで元に戻すための記録を返す
+    // 盤面を複製せずに1手先を調べたいときに使う
+    pub fn advance_undoable(&mut self, action: usize) -> Result<UndoToken, IllegalAction> {
+        let mut token = UndoToken {
+            character: self.character,
+            point: 0,
+            regen_turn: 0,
+            evaluated_score: self.evaluated_score,
+            hash: self.hash,
+            last_action: self.last_action,
+        };
+        let game_score = self.game_score;
+        if self.config.regen_after.is_some() && action < self.config.movement.action_n() && self.can_move(action) {
+            let next = self.character.step(action);
+            token.regen_turn = self.regen_turn[self.cell_index(next)];
+        }
+        self.advance(action)?;
+        token.point = self.game_score - game_score;  // 取ったポイントはスコアの増分と等しい
+        Ok(token)
+    }
+
+    // advance_undoableで進めた1ターンを元に戻す
+    // 直前にadvance_undoableで受け取った記録から順に渡す必要がある
+    pub fn undo(&mut self, token: UndoToken) {
+        // このターンに復活したマスを取られた状態に戻す
+        if self.config.regen_after.is_some() {
+            for i in 0..self.points.len() {
+                if self.regen_turn[i] == self.turn {
+                    self.points[i] = 0;
+                }
+            }
+        }
+        self.turn -= 1;
+        self.game_score -= token.point;
+        let cell = self.cell_index(self.character);
+        self.points[cell] = token.point;
+        if self.config.regen_after.is_some() {
+            self.regen_turn[cell] = token.regen_turn;
+        }
+        self.character = token.character;
+        self.evaluated_score = token.evaluated_score;
+        self.hash = token.hash;
+        self.last_action = token.last_action;
+        if self.record_history {
+            self.history.pop();
+        }
+    }
+
+    // 盤面を生成したシードを返す。同じconfigとこのシードでnewを呼ぶと同じ盤面を再現できる
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // dstをこの盤面と同じ内容に書き換える
+    // dstのVecの領域をそのまま使い回すので、探索中に作業用の盤面を使い回せば盤面を複製するたびに確保し直さずに済む
+    pub fn clone_into(&self, dst: &mut MazeState) {
+        dst.config = self.config;
+        dst.character = self.character;
+        dst.points.clone_from(&self.points);
+        dst.walls.clone_from(&self.walls);
+        dst.turn = self.turn;
+        dst.game_score = self.game_score;
+        dst.evaluated_score = self.evaluated_score;
+        dst.first_action = self.first_action;
+        dst.last_action = self.last_action;
+        dst.record_history = self.record_history;
+        dst.history.clone_from(&self.history);
+        dst.original_points.clone_from(&self.original_points);
+        dst.regen_turn.clone_from(&self.regen_turn);
+        dst.seed = self.seed;
+        dst.zobrist.clone_from(&self.zobrist);
+        dst.hash = self.hash;
+    }
+
+    // 同じ設定とシードで迷路を生成し直し、actionsの行動を順に適用した盤面を返す
+    // 返す盤面は行動を記録した状態になっている
+    pub fn replay(config: MazeConfig, seed: u64, actions: &[usize]) -> Result<Self, IllegalAction> {
+        let mut state = MazeState::new(config, Some(seed));
+        state.record_history = true;
+        state.apply_actions(actions).map_err(|(_, error)| error)?;
+        Ok(state)
+    }
+
+    // actionsの行動を順に適用する
+    // 合法でない行動があった場合は、その添字とエラーを返す。盤面はその直前の行動まで適用した状態のまま変更しない
+    pub fn apply_actions(&mut self, actions: &[usize]) -> Result<(), (usize, IllegalAction)> {
+        for (i, &action) in actions.iter().enumerate() {
+            self.advance(action).map_err(|error| (i, error))?;
+        }
+        Ok(())
+    }
+
+    // この盤面から、select_actionで選んだ行動でゲームが終わるまで進めた各ターンの盤面を順に返す
+    // 元の盤面は変更しない
+    pub fn run<F: FnMut(&MazeState) -> usize>(&self, select_action: F) -> GameRun<F> {
+        GameRun { state: self.clone(), select_action }
+    }
+
+    // action_fnで選んだ行動でゲームが終わるまで進め、結果を返す
+    // 壁に囲まれて途中で終わった場合を除き、turnsはゲーム終了ターンになる
+    pub fn run_to_completion(&mut self, mut action_fn: impl FnMut(&MazeState) -> usize) -> GameResult {
+        while !self.is_done() {
+            let action = action_fn(self);
+            self.advance(action).expect("action must be legal");
+        }
+        GameResult::Finished { score: self.game_score, turns: self.turn }
     }
 
     // [どのゲームでも実装する] : 現在の状況でプレイヤーが可能な行動を全て取得する
-    fn legal_actions(&self) -> Vec<usize> {
-        let mut actions = Vec::new();
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
+    pub fn legal_actions(&self) -> Vec<usize> {
+        let mut actions = ActionBuffer::new();
+        self.legal_actions_into(&mut actions);
+        actions.to_vec()
+    }
 
-        for action in 0..4 {
-            let ty = self.character.y + dy[action];
-            let tx = self.character.x + dx[action];
-            if ty >= 0 && ty < H as i32 && tx >= 0 && tx < W as i32 {
-                actions.push(action);
+    // legal_actionsと同じ行動をbufに書き込む。bufの中身は書き込む前に消す
+    // 探索のようにノードごとに合法手を調べる場合は、バッファを使い回してメモリ確保を避ける
+    pub fn legal_actions_into(&self, buf: &mut ActionBuffer) {
+        buf.clear();
+        for action in 0..self.config.movement.action_n() {
+            if self.can_move(action) {
+                buf.push(action);
             }
         }
+    }
+
+    // legal_actionsから、last_actionと逆向きに戻る行動を除いたものを返す
+    // 右に進んだ直後に左に戻るような、同じマスを往復するだけの行動を探索しないために使う
+    pub fn legal_actions_no_reverse(&self, last_action: usize) -> Vec<usize> {
+        let mut actions = ActionBuffer::new();
+        self.legal_actions_no_reverse_into(Some(last_action), &mut actions);
+        actions.to_vec()
+    }
+
+    // legal_actions_no_reverseと同じ行動をbufに書き込む。last_actionがNoneの場合はlegal_actionsと同じ
+    // 行き止まりで戻る以外に動けない場合は、戻る行動を残す
+    pub fn legal_actions_no_reverse_into(&self, last_action: Option<usize>, buf: &mut ActionBuffer) {
+        self.legal_actions_into(buf);
+        if let Some(last_action) = last_action {
+            if buf.len() > 1 {
+                buf.retain(|action| *action != REVERSE_ACTION[last_action]);
+            }
+        }
+    }
 
-        actions
+    // 合法なactionで移動した先のマスのポイントを返す
+    // 移動するとgame_scoreはちょうどこの値だけ増える
+    pub fn target_point(&self, action: usize) -> i32 {
+        let target = self.character.step(action);
+        self.at(target.y as usize, target.x as usize)
     }
 
-    // [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
-    fn to_string(&self) -> String {
-        let mut result = format!("turn:\t{}\nscore:\t{}\n", self.turn, self.game_score);
+    // 指定したactionの移動先が盤面内かつ壁でないか判定する
+    fn can_move(&self, action: usize) -> bool {
+        let target = self.character.step(action);
+        target.in_bounds(self.config.h, self.config.w) && !self.walls[self.cell_index(target)]
+    }
 
-        for h in 0..H {
-            for w in 0..W {
-                if self.character.y as usize == h && self.character.x as usize == w {
-                    result.push('@');
-                } else if self.points[h][w] > 0 {
-                    result.push_str(&self.points[h][w].to_string());
+    // actionが合法でない理由を返す。合法な場合はNone
+    fn illegal_reason(&self, action: usize) -> Option<IllegalAction> {
+        if self.is_done() {
+            return Some(IllegalAction::GameOver);
+        }
+        if action >= self.config.movement.action_n() {
+            return Some(IllegalAction::UnknownAction(action));
+        }
+        let target = self.character.step(action);
+        if !target.in_bounds(self.config.h, self.config.w) {
+            Some(IllegalAction::OutOfBounds { y: target.y, x: target.x })
+        } else if self.walls[self.cell_index(target)] {
+            Some(IllegalAction::Wall(target))
+        } else {
+            None
+        }
+    }
+
+    // どの方向にも移動できないか判定する
+    fn is_stuck(&self) -> bool {
+        (0..self.config.movement.action_n()).all(|action| !self.can_move(action))
+    }
+
+    // 指定したマスのポイントを取得する
+    #[inline]
+    fn at(&self, y: usize, x: usize) -> i32 {
+        self.points[y * self.config.w + x]
+    }
+
+    // 指定したマスを表示する文字列。'@' がキャラクター、'#' が壁、数字がポイント、'a'~'i' が罠、'.' が空きマス
+    fn cell_glyph(&self, y: usize, x: usize) -> String {
+        let point = self.at(y, x);
+        if self.character.y as usize == y && self.character.x as usize == x {
+            "@".to_string()
+        } else if self.walls[y * self.config.w + x] {
+            "#".to_string()
+        } else if point > 0 {
+            point.to_string()
+        } else if point < 0 {
+            trap_char(point).to_string()
+        } else {
+            ".".to_string()
+        }
+    }
+
+    // 盤面を表示するときの1マスの幅。最も大きいポイントの桁数とする
+    fn cell_width(&self) -> usize {
+        self.points.iter().map(|&point| point.max(0).to_string().len()).max().unwrap_or(1)
+    }
+
+    // 座標をマスの通し番号に変換する
+    fn cell_index(&self, coord: Coord) -> usize {
+        coord.y as usize * self.config.w + coord.x as usize
+    }
+
+    // advanceで差分更新しているZobristハッシュを返す
+    // キャラクターの位置、ターン、ポイントの残っているマスが等しければ同じ値になる
+    pub fn zobrist_key(&self) -> u64 {
+        self.hash
+    }
+
+    // Zobristハッシュを盤面全体から計算し直す
+    pub fn compute_zobrist_key(&self) -> u64 {
+        let mut key = self.zobrist.character[self.cell_index(self.character)] ^ self.zobrist.turn[self.turn];
+        for y in 0..self.config.h {
+            for x in 0..self.config.w {
+                if self.at(y, x) != 0 {
+                    key ^= self.zobrist.points[y * self.config.w + x];
+                }
+            }
+        }
+        key
+    }
+
+    // 盤面を時計回りに90度回転した盤面を返す。高さと幅は入れ替わる
+    // 行動の記録も回転後の向きに置き換える。seed()は元の盤面のままなので、回転した盤面はnewで再現できない
+    pub fn rotate90(&self) -> MazeState {
+        let h = self.config.h as i32;
+        self.transformed(self.config.w, self.config.h, |coord| Coord::new(coord.x, h - 1 - coord.y))
+    }
+
+    // 盤面を左右反転した盤面を返す
+    pub fn mirror_horizontal(&self) -> MazeState {
+        let w = self.config.w as i32;
+        self.transformed(self.config.h, self.config.w, |coord| Coord::new(coord.y, w - 1 - coord.x))
+    }
+
+    // 回転と反転で移り合う8通りの盤面のうち、Zobristハッシュが最小の値を返す
+    // 対称な盤面は同じ値になるので、置換表や重複除去で同一視できる
+    pub fn canonical_hash(&self) -> u64 {
+        let mut state = self.clone();
+        let mut hash = u64::MAX;
+        for _ in 0..4 {
+            hash = hash.min(state.zobrist_key()).min(state.mirror_horizontal().zobrist_key());
+            state = state.rotate90();
+        }
+        hash
+    }
+
+    // マスの座標をmapで移した、高さh、幅wの盤面をつくる
+    // 行動の向きも同じmapで移すため、mapは回転と反転を組み合わせたものに限る
+    fn transformed(&self, h: usize, w: usize, map: impl Fn(Coord) -> Coord) -> MazeState {
+        let config = MazeConfig { h, w, ..self.config };
+        let mut state = self.clone();
+        state.config = config;
+        state.character = map(self.character);
+        for y in 0..self.config.h {
+            for x in 0..self.config.w {
+                let from = self.cell_index(Coord::new(y as i32, x as i32));
+                let to = state.cell_index(map(Coord::new(y as i32, x as i32)));
+                state.points[to] = self.points[from];
+                state.walls[to] = self.walls[from];
+                if !self.regen_turn.is_empty() {
+                    state.original_points[to] = self.original_points[from];
+                    state.regen_turn[to] = self.regen_turn[from];
+                }
+            }
+        }
+
+        // 原点から1マス進んだ座標を移した差分で、行動の向きを求める
+        let origin = map(Coord::new(0, 0));
+        let map_action = |action: usize| {
+            let moved = map(Coord::new(0, 0).step(action));
+            (0..8)
+                .find(|&to| Coord::new(moved.y - origin.y, moved.x - origin.x) == Coord::new(dy8[to], dx8[to]))
+                .expect("map must be a rotation or a reflection")
+        };
+        state.last_action = self.last_action.map(map_action);
+        state.history = self.history.iter().map(|&action| map_action(action)).collect();
+        if self.first_action >= 0 {
+            state.first_action = map_action(self.first_action as usize) as i32;
+        }
+
+        state.zobrist = ZobristTable::get(&config);
+        state.hash = state.compute_zobrist_key();
+        state
+    }
+
+    // Displayと同じ盤面を、ANSIエスケープシーケンスで色を付けた文字列にする
+    // ポイント7~9は赤、4~6は黄、1~3は薄く、空きマスは灰色、キャラクターは明るいシアンで表示する
+    pub fn to_colored_string(&self) -> String {
+        let mut colored = format!("turn:\t{}\nscore:\t{}\n", self.turn, self.game_score);
+        let cell_width = self.cell_width();
+
+        for h in 0..self.config.h {
+            for w in 0..self.config.w {
+                let point = self.at(h, w);
+                let glyph = self.cell_glyph(h, w);
+                let color = if glyph == "@" {
+                    ANSI_BRIGHT_CYAN
+                } else if glyph == "#" {
+                    ANSI_RESET
+                } else if point >= 7 {
+                    ANSI_RED
+                } else if point >= 4 {
+                    ANSI_YELLOW
+                } else if point >= 1 {
+                    ANSI_DIM
+                } else if point < 0 {
+                    ANSI_MAGENTA
                 } else {
-                    result.push('.');
+                    ANSI_GRAY
+                };
+                if w > 0 && cell_width > 1 {
+                    colored.push(' ');
                 }
+                colored.push_str(color);
+                colored.push_str(&format!("{:>cell_width$}", glyph));
+                colored.push_str(ANSI_RESET);
             }
-            result.push('\n');
+            colored.push('\n');
         }
 
-        result
+        colored
+    }
+
+    // 盤面をJSON文字列にする
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("MazeState is always serializable")
+    }
+
+    // to_jsonで保存した盤面を復元する
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<MazeState, serde_json::Error> {
+        serde_json::from_str(s)
     }
 }
 
+// [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
+impl fmt::Display for MazeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "turn:\t{}\nscore:\t{}\n", self.turn, self.game_score)?;
+
+        // 2桁以上のポイントがある場合は、マスの幅をそろえて空白で区切る
+        let cell_width = self.cell_width();
+        for h in 0..self.config.h {
+            for w in 0..self.config.w {
+                if w > 0 && cell_width > 1 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{:>cell_width$}", self.cell_glyph(h, w))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Displayと同じ形式の文字列から盤面を読み込む
+// '@' がキャラクター、'#' が壁、数字がポイント、'a'~'i' が-1~-9の罠、'.' が空きマスを表す。
+// 空白を含む行がある場合は、Displayが2桁以上のポイントを表示するときと同じく、空白で区切った1つ1つをマスとして読む。
+// そうでない場合は1文字を1マスとする。(1列だけの盤面で全てのマスが2桁以上の場合は区別できないので、1文字ずつ読む)
+// 迷路の高さと幅は行数と列数から決め、ゲーム終了ターンは全マスの数とする。
+// 先頭に "turn:" と "score:" の行があれば、ターンとスコアとして読み込む。
+impl FromStr for MazeState {
+    type Err = ParseError;
+
+    fn from_str(layout: &str) -> Result<Self, Self::Err> {
+        let mut turn = 0;
+        let mut game_score = 0;
+        let mut rows = Vec::new();
+
+        for line in layout.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("turn:") {
+                turn = value.trim().parse().map_err(|_| ParseError::InvalidHeader(line.to_string()))?;
+            } else if let Some(value) = line.strip_prefix("score:") {
+                game_score = value.trim().parse().map_err(|_| ParseError::InvalidHeader(line.to_string()))?;
+            } else {
+                rows.push(line);
+            }
+        }
+
+        if rows.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let padded = rows.iter().any(|row| row.contains(' '));
+        let cells: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| {
+                if padded {
+                    row.split_whitespace().collect()
+                } else {
+                    row.char_indices().map(|(i, ch)| &row[i..i + ch.len_utf8()]).collect()
+                }
+            })
+            .collect();
+        let h = cells.len();
+        let w = cells[0].len();
+
+        let mut character = None;
+        let mut points = vec![0; h * w];
+        let mut walls = vec![false; h * w];
+        for (y, row) in cells.iter().enumerate() {
+            if row.len() != w {
+                return Err(ParseError::RaggedRow { row: y });
+            }
+            for (x, &cell) in row.iter().enumerate() {
+                let ch = cell.chars().next().unwrap();
+                match cell {
+                    "@" => {
+                        if character.is_some() {
+                            return Err(ParseError::MultipleCharacters);
+                        }
+                        character = Some(Coord::new(y as i32, x as i32));
+                    }
+                    "#" => walls[y * w + x] = true,
+                    "." => {}
+                    _ if cell.bytes().all(|b| b.is_ascii_digit()) => {
+                        points[y * w + x] = cell.parse().map_err(|_| ParseError::InvalidChar { row: y, col: x, ch })?;
+                    }
+                    _ if cell.len() == 1 && ('a'..='i').contains(&ch) => points[y * w + x] = -(ch as i32 - 'a' as i32 + 1),
+                    _ => return Err(ParseError::InvalidChar { row: y, col: x, ch }),
+                }
+            }
+        }
+        let character = character.ok_or(ParseError::MissingCharacter)?;
+
+        let config = MazeConfig::new(h, w, h * w);
+        if turn > config.end_turn {
+            return Err(ParseError::TurnExceedsEndTurn);
+        }
+
+        let mut state = MazeState {
+            config,
+            character,
+            points,
+            walls,
+            turn,
+            game_score,
+            evaluated_score: 0,
+            first_action: -1,
+            last_action: None,
+            record_history: false,
+            history: Vec::new(),
+            original_points: Vec::new(),
+            regen_turn: Vec::new(),
+            seed: 0,
+            zobrist: ZobristTable::get(&config),
+            hash: 0,
+        };
+        state.hash = state.compute_zobrist_key();
+        Ok(state)
+    }
+}
+
+// clone_fromはclone_intoで複製先の領域を使い回す
+impl Clone for MazeState {
+    fn clone(&self) -> Self {
+        MazeState {
+            config: self.config,
+            character: self.character,
+            points: self.points.clone(),
+            walls: self.walls.clone(),
+            turn: self.turn,
+            game_score: self.game_score,
+            evaluated_score: self.evaluated_score,
+            first_action: self.first_action,
+            last_action: self.last_action,
+            record_history: self.record_history,
+            history: self.history.clone(),
+            original_points: self.original_points.clone(),
+            regen_turn: self.regen_turn.clone(),
+            seed: self.seed,
+            zobrist: self.zobrist.clone(),
+            hash: self.hash,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        source.clone_into(self);
+    }
+}
+
+// 共通インターフェースから探索できるようにする
+impl Game for MazeState {
+    type Action = usize;
+
+    fn is_done(&self) -> bool {
+        MazeState::is_done(self)
+    }
+    fn legal_actions(&self) -> Vec<usize> {
+        MazeState::legal_actions(self)
+    }
+    fn advance(&mut self, action: usize) {
+        MazeState::advance(self, action).expect("action must be legal")
+    }
+    fn evaluate(&self) -> i64 {
+        self.game_score as i64
+    }
+}
+
+// 探索時のソート用に評価を比較する
+// 評価が同じ場合は、ターンが進んでいる方、first_actionが大きい方、Zobristハッシュが大きい方の順に優先し、
+// BinaryHeapから取り出す順番が挿入順に左右されないようにする
+impl Ord for MazeState {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.evaluated_score
+            .cmp(&other.evaluated_score)
+            .then_with(|| self.turn.cmp(&other.turn))
+            .then_with(|| self.first_action.cmp(&other.first_action))
+            .then_with(|| self.hash.cmp(&other.hash))
+    }
+}
+
+impl PartialOrd for MazeState {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MazeState {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for MazeState {}
+
+// MazeState::runで返す、1ターン進めるごとの盤面を返すイテレータ
+pub struct GameRun<F> {
+    state: MazeState,
+    select_action: F,
+}
+
+impl<F: FnMut(&MazeState) -> usize> Iterator for GameRun<F> {
+    type Item = MazeState;
+
+    fn next(&mut self) -> Option<MazeState> {
+        if self.state.is_done() {
+            return None;
+        }
+        let action = (self.select_action)(&self.state);
+        self.state.advance(action).expect("action must be legal");
+        Some(self.state.clone())
+    }
+}
+
+// 探索で盤面を評価する関数。値が大きいほど良い盤面とする
+// 状態を持たないクロージャも渡せる
+pub type Evaluator = fn(&MazeState) -> i32;
+
+// evaluate_scoreと同じく、ゲーム上で実際に得たスコアで評価する
+pub fn default_evaluator(state: &MazeState) -> i32 {
+    state.game_score
+}
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(3, 4, 4);
+
 // ランダムに行動を決定する
-fn random_action(state: &MazeState) -> usize {
+pub fn random_action(state: &MazeState) -> usize {
     let legal_actions = state.legal_actions();
     let mut rng_for_action = rand::thread_rng();
     legal_actions[rng_for_action.gen_range(0..legal_actions.len())]
 }
 
+// seed_rangeの各シードの盤面をaction_fnでプレイし、キャラクターが各マスに移動した回数を数える
+// 返す値は[y][x]の回数で、壁のない盤面では合計が end_turn * ゲーム数 になる
+pub fn visit_heatmap(config: MazeConfig, seed_range: Range<u64>, mut action_fn: impl FnMut(&MazeState) -> usize) -> Vec<Vec<u32>> {
+    let mut heatmap = vec![vec![0; config.w]; config.h];
+    for seed in seed_range {
+        let state = MazeState::new(config, Some(seed));
+        for frame in state.run(&mut action_fn) {
+            heatmap[frame.character.y as usize][frame.character.x as usize] += 1;
+        }
+    }
+    heatmap
+}
+
+// visit_heatmapの回数を、桁をそろえた格子として文字列にする
+pub fn render_heatmap(heatmap: &[Vec<u32>]) -> String {
+    let max_count = heatmap.iter().flatten().copied().max().unwrap_or(0);
+    let width = max_count.to_string().len();
+    let mut rendered = String::new();
+    for row in heatmap {
+        let cells: Vec<String> = row.iter().map(|count| format!("{:>width$}", count)).collect();
+        rendered.push_str(&cells.join(" "));
+        rendered.push('\n');
+    }
+    rendered
+}
+
 // シードを指定してゲーム状況を表示しながらAIにプレイさせる。
 fn play_game(seed: u64) {
-    let mut state = MazeState::new(seed);
-    println!("{}", state.to_string());
+    let mut state = MazeState::new(CONFIG, Some(seed));
+    state.record_history = true;
+    println!("{}", state);
 
-    while !state.is_done() {
-        state.advance(random_action(&state));
-        println!("{}", state.to_string());
+    for frame in state.run(random_action) {
+        println!("{}", frame);
+        state = frame;
     }
+
+    // 記録した行動から同じゲームを再現できることを確認する
+    let replayed = MazeState::replay(CONFIG, seed, &state.history).expect("recorded actions must be legal");
+    println!("history:\t{:?}", state.history);
+    println!("replayed score:\t{}", replayed.game_score);
 }
 
 #[allow(dead_code)]
 pub fn main() {
     play_game(121321);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::game::greedy_action;
+
+    #[test]
+    fn walled_in_character_has_no_legal_actions() {
+        let mut state: MazeState = "###\n#@#\n###".parse().unwrap();
+        assert!(state.legal_actions().is_empty());
+        assert!(state.is_done());
+        assert_eq!(state.advance(0), Err(IllegalAction::GameOver));
+        assert_eq!(state.run_to_completion(|_| unreachable!()), GameResult::Finished { score: 0, turns: 0 });
+    }
+
+    // シードを固定した乱数で合法手を選ぶ
+    fn seeded_random_action(state: &MazeState, rng: &mut impl Rng) -> usize {
+        let legal_actions = state.legal_actions();
+        legal_actions[rng.gen_range(0..legal_actions.len())]
+    }
+
+    #[test]
+    fn incremental_zobrist_key_matches_recomputed_key() {
+        let configs = [
+            MazeConfig::new(6, 7, 30),
+            MazeConfig { wall_ratio: 0.2, trap_ratio: 0.2, ..MazeConfig::new(6, 7, 30) },
+            MazeConfig { regen_after: Some(3), movement: Movement::EightWay, ..MazeConfig::new(6, 7, 30) },
+        ];
+        let mut rng = make_rng(0);
+        for config in configs {
+            for seed in 0..20 {
+                let mut state = MazeState::new(config, Some(seed));
+                assert_eq!(state.zobrist_key(), state.compute_zobrist_key());
+                while !state.is_done() {
+                    state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+                    assert_eq!(state.zobrist_key(), state.compute_zobrist_key(), "turn {} of seed {}", state.turn, seed);
+                }
+            }
+        }
+    }
+
+    // '@' がキャラクター、'.' が空きマス、数字がポイントを表す表示が変わっていないことを確かめる
+    #[test]
+    fn display_snapshot() {
+        let mut state = MazeState::new(CONFIG, Some(0));
+        assert_eq!(state.to_string(), "turn:\t0\nscore:\t0\n8.21\n2675\n81@.\n");
+        state.advance(0).unwrap();
+        assert_eq!(state.to_string(), "turn:\t1\nscore:\t0\n8.21\n2675\n81.@\n");
+    }
+
+    #[test]
+    fn off_board_action_is_an_error() {
+        let mut state: MazeState = "@1\n23".parse().unwrap();
+        assert!(state.advance(1).is_err());
+        assert!(state.advance(3).is_err());
+        assert_eq!((state.turn, state.game_score, state.character), (0, 0, Coord::new(0, 0)));
+        assert!(state.advance(0).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip_plays_the_same_game() {
+        let config = MazeConfig { wall_ratio: 0.1, trap_ratio: 0.1, regen_after: Some(5), ..MazeConfig::new(6, 6, 20) };
+        let state = MazeState::new(config, Some(42));
+        let restored = MazeState::from_json(&state.to_json()).unwrap();
+        assert_eq!(restored.to_string(), state.to_string());
+        assert_eq!(restored.zobrist_key(), state.zobrist_key());
+
+        // 同じ貪欲な行動列を両方に適用する
+        let play = |mut state: MazeState, actions: &[usize]| {
+            state.apply_actions(actions).unwrap();
+            state.game_score
+        };
+        let actions: Vec<usize> = state.run(|state| crate::common::game::greedy_action(state).unwrap()).map(|frame| frame.last_action.unwrap()).collect();
+        assert_eq!(play(restored, &actions), play(state, &actions));
+    }
+
+    // 読み込んだ盤面が元の盤面と同じマスとキャラクターの位置、ターン、スコアを持つことを確かめる
+    fn assert_round_trip(state: &MazeState) {
+        let parsed: MazeState = state.to_string().parse().unwrap();
+        assert_eq!(parsed.to_string(), state.to_string());
+        assert_eq!(parsed.points, state.points);
+        assert_eq!(parsed.walls, state.walls);
+        assert_eq!(parsed.character, state.character);
+        assert_eq!((parsed.turn, parsed.game_score), (state.turn, state.game_score));
+    }
+
+    #[test]
+    fn display_and_from_str_round_trip() {
+        let config = MazeConfig { wall_ratio: 0.2, trap_ratio: 0.2, ..MazeConfig::new(5, 7, 10) };
+        for seed in 0..20 {
+            let mut state = MazeState::new(config, Some(seed));
+            assert_round_trip(&state);
+            while !state.is_done() {
+                state.advance(state.legal_actions()[0]).unwrap();
+            }
+            assert_round_trip(&state);
+        }
+    }
+
+    #[test]
+    fn from_str_reports_invalid_layouts() {
+        assert_eq!("".parse::<MazeState>().unwrap_err(), ParseError::Empty);
+        assert_eq!("@1\n2".parse::<MazeState>().unwrap_err(), ParseError::RaggedRow { row: 1 });
+        assert_eq!("@x".parse::<MazeState>().unwrap_err(), ParseError::InvalidChar { row: 0, col: 1, ch: 'x' });
+        assert_eq!("12".parse::<MazeState>().unwrap_err(), ParseError::MissingCharacter);
+        assert_eq!("@@".parse::<MazeState>().unwrap_err(), ParseError::MultipleCharacters);
+        assert_eq!("turn:\t5\n@1".parse::<MazeState>().unwrap_err(), ParseError::TurnExceedsEndTurn);
+    }
+
+    // 角のマスでは四方向で2つ、八方向で3つ、中央のマスでは四方向で4つ、八方向で8つの行動がある
+    #[test]
+    fn legal_action_counts_by_movement() {
+        for (movement, corner_n, center_n) in [(Movement::FourWay, 2, 4), (Movement::EightWay, 3, 8)] {
+            let mut state = MazeState::empty(MazeConfig { movement, ..MazeConfig::new(3, 3, 4) });
+            assert_eq!(state.legal_actions().len(), corner_n);
+            state.character = Coord::new(1, 1);
+            assert_eq!(state.legal_actions().len(), center_n);
+        }
+    }
+
+    // Debugの表示は全ての項目を含むので、表示が等しければ盤面は完全に等しい
+    fn assert_identical(a: &MazeState, b: &MazeState) {
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+
+    #[test]
+    fn undo_restores_the_exact_state() {
+        let configs = [
+            MazeConfig::new(5, 5, 20),
+            MazeConfig { trap_ratio: 0.3, regen_after: Some(2), movement: Movement::EightWay, ..MazeConfig::new(5, 5, 20) },
+        ];
+        let mut rng = make_rng(1);
+        for config in configs {
+            for seed in 0..10 {
+                let mut state = MazeState::new(config, Some(seed));
+                state.record_history = true;
+                while !state.is_done() {
+                    let before = state.clone();
+                    for action in state.legal_actions() {
+                        let token = state.advance_undoable(action).unwrap();
+                        state.undo(token);
+                        assert_identical(&state, &before);
+                    }
+                    state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+                }
+            }
+        }
+    }
+
+    // 高さと幅が異なる盤面でも、y * w + x 番目のマスが(y, x)に表示される
+    #[test]
+    fn non_square_display_snapshot() {
+        let state = MazeState::new(MazeConfig { wall_ratio: 0.2, trap_ratio: 0.1, ..MazeConfig::new(3, 6, 4) }, Some(7));
+        assert_eq!(state.to_string(), "turn:\t0\nscore:\t0\n13d1b#\n@#7#7#\n1756#3\n");
+        assert_eq!(state.get_point(0, 2), -4);
+        assert_eq!(state.get_point(2, 5), 3);
+        assert!(state.walls[state.config.w + 1]);
+    }
+
+    #[test]
+    fn replaying_the_history_reproduces_the_score() {
+        let config = MazeConfig { trap_ratio: 0.2, regen_after: Some(4), ..MazeConfig::new(5, 6, 30) };
+        let mut rng = make_rng(1);
+        for seed in 0..10 {
+            let mut state = MazeState::new(config, Some(seed));
+            state.record_history = true;
+            while !state.is_done() {
+                state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+            }
+            let replayed = MazeState::replay(config, seed, &state.history.clone()).unwrap();
+            assert_eq!(replayed.game_score, state.game_score);
+            assert_identical(&replayed, &state);
+        }
+
+        // 記録しない場合はhistoryが空のまま
+        let mut state = MazeState::new(config, Some(0));
+        state.advance(state.legal_actions()[0]).unwrap();
+        assert!(state.history.is_empty());
+    }
+
+    // 四方向移動では取ったマスに奇数ターン後にしか戻れないので、斜めに動いて3ターン後に戻る
+    #[test]
+    fn consumed_cell_is_collectable_again_after_regen_after_turns() {
+        let config = MazeConfig { regen_after: Some(3), movement: Movement::EightWay, ..MazeConfig::new(2, 2, 10) };
+        let mut state = MazeState::empty(config);
+        state.set_point(0, 1, 5);
+
+        state.advance(0).unwrap();  // ターン0で(0, 1)のポイントを取る
+        assert_eq!((state.game_score, state.get_point(0, 1)), (5, 0));
+        state.advance(2).unwrap();  // (1, 1)
+        state.advance(1).unwrap();  // (1, 0)
+        assert_eq!((state.turn, state.get_point(0, 1)), (3, 5));
+        state.advance(6).unwrap();  // ターン3で斜めに(0, 1)へ戻る
+        assert_eq!((state.game_score, state.get_point(0, 1)), (10, 0));
+    }
+
+    #[test]
+    fn game_run_yields_one_frame_per_turn() {
+        let config = MazeConfig::new(5, 5, 12);
+        for seed in 0..10 {
+            let state = MazeState::new(config, Some(seed));
+            let frames: Vec<MazeState> = state.run(|s| greedy_action(s).unwrap()).collect();
+            assert_eq!(frames.len(), config.end_turn);
+            for (i, frame) in frames.iter().enumerate() {
+                assert_eq!(frame.turn, i + 1);
+            }
+            assert_eq!(state.turn, 0);
+
+            let mut played = state.clone();
+            played.run_to_completion(|s| greedy_action(s).unwrap());
+            assert_identical(frames.last().unwrap(), &played);
+        }
+    }
+
+    #[test]
+    fn heatmap_counts_one_visit_per_turn() {
+        let config = MazeConfig::new(5, 6, 12);
+        let game_number = 20;
+        let heatmap = visit_heatmap(config, 0..game_number, |s| greedy_action(s).unwrap());
+        assert_eq!((heatmap.len(), heatmap[0].len()), (config.h, config.w));
+        let total: u32 = heatmap.iter().flatten().sum();
+        assert_eq!(total as usize, config.end_turn * game_number as usize);
+
+        assert_eq!(render_heatmap(&[vec![1, 12], vec![0, 3]]), " 1 12\n 0  3\n");
+    }
+
+    #[test]
+    fn colored_string_wraps_the_character_in_escapes() {
+        let state: MazeState = "8@2\n.5a".parse().unwrap();
+        let colored = state.to_colored_string();
+        assert!(colored.contains("\x1b[96m@\x1b[0m"));
+        assert!(colored.contains("\x1b[31m8\x1b[0m"));
+        assert!(colored.contains("\x1b[33m5\x1b[0m"));
+        assert!(colored.contains("\x1b[2m2\x1b[0m"));
+        assert!(colored.contains("\x1b[90m.\x1b[0m"));
+        assert!(colored.contains("\x1b[35ma\x1b[0m"));
+        // Displayには色を付けない
+        assert!(!state.to_string().contains('\x1b'));
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_board() {
+        let config = MazeConfig { wall_ratio: 0.2, trap_ratio: 0.1, ..MazeConfig::new(8, 9, 20) };
+        for seed in 0..20 {
+            let a = MazeState::new(config, Some(seed));
+            let b = MazeState::new(config, Some(seed));
+            assert_eq!(a.points, b.points);
+            assert_eq!((a.walls.clone(), a.character), (b.walls.clone(), b.character));
+            assert_eq!(a.seed(), seed);
+        }
+
+        // シードを指定しない場合も、選んだシードから同じ盤面を作り直せる
+        let random = MazeState::new(config, None);
+        assert_identical(&MazeState::new(config, Some(random.seed())), &random);
+    }
+
+    #[test]
+    fn run_to_completion_matches_a_manual_run() {
+        let config = MazeConfig::new(5, 5, 12);
+        for seed in 0..10 {
+            let mut manual = MazeState::new(config, Some(seed));
+            while !manual.is_done() {
+                manual.advance(greedy_action(&manual).unwrap()).unwrap();
+            }
+
+            let mut state = MazeState::new(config, Some(seed));
+            let result = state.run_to_completion(|s| greedy_action(s).unwrap());
+            assert_eq!(result, GameResult::Finished { score: manual.game_score, turns: config.end_turn });
+            assert_identical(&state, &manual);
+        }
+    }
+
+    #[test]
+    fn uniform_point_range_fills_every_floor_cell() {
+        let config = MazeConfig { point_min: 5, point_max: 5, ..MazeConfig::new(4, 5, 10) };
+        for seed in 0..10 {
+            let state = MazeState::new(config, Some(seed));
+            let start = state.cell_index(state.character);
+            for (i, &point) in state.points.iter().enumerate() {
+                assert_eq!(point, if i == start { 0 } else { 5 }, "seed {} cell {}", seed, i);
+            }
+        }
+    }
+
+    // 2桁以上のポイントは空白で区切って表示し、その形式のまま読み込める
+    #[test]
+    fn multi_digit_points_round_trip() {
+        let config = MazeConfig { point_min: 5, point_max: 20, wall_ratio: 0.1, trap_ratio: 0.2, ..MazeConfig::new(4, 5, 10) };
+        for seed in 0..20 {
+            let mut state = MazeState::new(config, Some(seed));
+            assert!(state.to_string().contains(' '));
+            assert_round_trip(&state);
+            while !state.is_done() {
+                state.advance(state.legal_actions()[0]).unwrap();
+                assert_round_trip(&state);
+            }
+        }
+
+        let state: MazeState = "12  @\n 9  a".parse().unwrap();
+        assert_eq!((state.config.h, state.config.w), (2, 2));
+        assert_eq!(state.points, vec![12, 0, 9, -1]);
+        assert_eq!(state.to_string(), "turn:\t0\nscore:\t0\n12  @\n 9  a\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "point_min must not be negative")]
+    fn negative_point_min_is_rejected() {
+        MazeState::new(MazeConfig { point_min: -3, ..MazeConfig::new(3, 3, 4) }, Some(0));
+    }
+
+    #[test]
+    fn apply_actions_plays_a_valid_sequence_like_advance() {
+        let config = MazeConfig::new(5, 5, 100);
+        let mut expected = MazeState::new(config, Some(3));
+        let mut rng = make_rng(1);
+        let mut actions = Vec::new();
+        while !expected.is_done() {
+            let action = seeded_random_action(&expected, &mut rng);
+            expected.advance(action).unwrap();
+            actions.push(action);
+        }
+        assert_eq!(actions.len(), 100);
+
+        let mut state = MazeState::new(config, Some(3));
+        assert_eq!(state.apply_actions(&actions), Ok(()));
+        assert_identical(&state, &expected);
+    }
+
+    #[test]
+    fn apply_actions_reports_the_index_of_an_off_board_action() {
+        let config = MazeConfig::new(5, 5, 100);
+        let mut state = MazeState::new(config, Some(3));
+        let mut rng = make_rng(1);
+        let mut actions = Vec::new();
+        for _ in 0..50 {
+            let action = seeded_random_action(&state, &mut rng);
+            state.advance(action).unwrap();
+            actions.push(action);
+        }
+        // 上に進み続けると、今の行の数だけ進んだ次の行動で盤面の外に出る
+        let y = state.character.y;
+        let x = state.character.x;
+        actions.extend([3; 6]);
+        let before = state.clone();
+        let mut replayed = MazeState::new(config, Some(3));
+        let error_index = 50 + y as usize;
+        assert_eq!(
+            replayed.apply_actions(&actions),
+            Err((error_index, IllegalAction::OutOfBounds { y: -1, x }))
+        );
+        // 外に出る直前の行動までは適用されている
+        let mut expected = before;
+        expected.apply_actions(&actions[50..error_index]).unwrap();
+        assert_identical(&replayed, &expected);
+    }
+
+    #[test]
+    fn remaining_points_is_zero_after_collecting_every_cell() {
+        let mut state: MazeState = "@123\n....".parse().unwrap();
+        assert_eq!(state.remaining_points(), 6);
+        for _ in 0..3 {
+            state.advance(0).unwrap();
+        }
+        assert_eq!(state.remaining_points(), 0);
+        assert_eq!(state.game_score, 6);
+    }
+
+    // 全ての行動列を調べて、stateから得られる最終スコアの最大値を返す
+    fn best_final_score(state: &MazeState) -> i32 {
+        if state.is_done() {
+            return state.game_score;
+        }
+        state.legal_actions().into_iter()
+            .map(|action| {
+                let mut next_state = state.clone();
+                next_state.advance(action).unwrap();
+                best_final_score(&next_state)
+            })
+            .max()
+            .unwrap()
+    }
+
+    // ランダムな行動列で進めた途中のどの盤面でも、上限はそこから得られる最適な最終スコア以上になる
+    #[test]
+    fn score_upper_bound_is_at_least_the_achievable_score() {
+        let configs = [
+            MazeConfig::new(3, 4, 6),
+            MazeConfig { wall_ratio: 0.2, trap_ratio: 0.3, ..MazeConfig::new(3, 4, 6) },
+            MazeConfig { point_min: 5, point_max: 30, movement: Movement::EightWay, ..MazeConfig::new(3, 3, 5) },
+            MazeConfig { regen_after: Some(2), trap_ratio: 0.2, ..MazeConfig::new(3, 3, 6) },
+        ];
+        let mut rng = make_rng(0);
+        for config in configs {
+            for seed in 0..10 {
+                let mut state = MazeState::new(config, Some(seed));
+                loop {
+                    let optimum = best_final_score(&state);
+                    assert!(state.score_upper_bound() >= optimum, "{:?} seed {} turn {}", config, seed, state.turn);
+                    if state.is_done() {
+                        assert_eq!(state.score_upper_bound(), state.game_score);
+                        break;
+                    }
+                    state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+                }
+            }
+        }
+    }
+
+    // 3x4の盤面の角と中央で、隣接マス、盤面内の判定、移動、距離を確かめる
+    #[test]
+    fn coord_helpers_at_a_corner_and_the_center() {
+        let (h, w) = (3, 4);
+        let corner = Coord::new(0, 0);
+        let inside: Vec<Coord> = corner.neighbors4().filter(|c| c.in_bounds(h, w)).collect();
+        assert_eq!(inside, vec![Coord::new(0, 1), Coord::new(1, 0)]);
+        assert_eq!(corner.neighbors4().count(), 4);
+        assert!(!corner.step(1).in_bounds(h, w));
+        assert!(!corner.step(3).in_bounds(h, w));
+
+        let center = Coord::new(1, 1);
+        let neighbors: Vec<Coord> = center.neighbors4().collect();
+        assert_eq!(neighbors, vec![Coord::new(1, 2), Coord::new(1, 0), Coord::new(2, 1), Coord::new(0, 1)]);
+        assert!(neighbors.iter().all(|c| c.in_bounds(h, w) && c.manhattan(&center) == 1));
+        assert_eq!(center.step(4), Coord::new(2, 2));
+        assert_eq!(center.step(7), Coord::new(0, 0));
+
+        assert_eq!(corner.manhattan(&Coord::new(2, 3)), 5);
+        assert_eq!(Coord::new(2, 3).manhattan(&corner), 5);
+        assert!(!Coord::new(3, 0).in_bounds(h, w) && !Coord::new(0, 4).in_bounds(h, w));
+        assert!(Coord::new(2, 3).in_bounds(h, w));
+    }
+
+    #[test]
+    fn normalized_score_is_zero_on_an_empty_board_and_one_after_collecting_everything() {
+        let state = MazeState::empty(MazeConfig::new(3, 3, 5));
+        assert_eq!(state.normalized_score(), 0.0);
+
+        let mut state: MazeState = "@123\n....".parse().unwrap();
+        assert_eq!(state.normalized_score(), 0.0);
+        state.advance(0).unwrap();
+        assert!((state.normalized_score() - 1.0 / 6.0).abs() < 1e-9);
+        for _ in 0..2 {
+            state.advance(0).unwrap();
+        }
+        assert_eq!(state.remaining_points(), 0);
+        assert!((state.normalized_score() - 1.0).abs() < 1e-9);
+    }
+
+    // 中央から右に進んだ直後は左に戻る行動を除く。行き止まりでは戻る行動を残す
+    #[test]
+    fn no_reverse_excludes_the_way_back() {
+        let mut state = MazeState::empty(MazeConfig::new(3, 3, 5));
+        state.character = Coord::new(1, 0);
+        state.advance(0).unwrap();
+        assert_eq!(state.last_action, Some(0));
+        assert_eq!(state.legal_actions(), vec![0, 1, 2, 3]);
+        assert_eq!(state.legal_actions_no_reverse(0), vec![0, 2, 3]);
+
+        let mut buf = ActionBuffer::new();
+        state.legal_actions_no_reverse_into(state.last_action, &mut buf);
+        assert_eq!(buf.as_slice(), &[0, 2, 3]);
+        state.legal_actions_no_reverse_into(None, &mut buf);
+        assert_eq!(buf.as_slice(), &[0, 1, 2, 3]);
+
+        let dead_end: MazeState = "#@.".parse().unwrap();
+        assert_eq!(dead_end.legal_actions_no_reverse(1), vec![0]);
+    }
+
+    // clone_intoはcloneと同じ盤面になり、コピー先のバッファを使い回す
+    #[test]
+    fn clone_into_equals_clone_without_reallocating() {
+        let config = MazeConfig { regen_after: Some(3), wall_ratio: 0.1, ..MazeConfig::new(6, 7, 30) };
+        let mut rng = make_rng(0);
+        let mut dst = MazeState::new(config, Some(100));
+        dst.record_history = true;
+        for seed in 0..20 {
+            let mut state = MazeState::new(config, Some(seed));
+            state.record_history = true;
+            for _ in 0..seed as usize % 10 {
+                state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+            }
+            let (points, walls, original_points) = (dst.points.as_ptr(), dst.walls.as_ptr(), dst.original_points.as_ptr());
+            state.clone_into(&mut dst);
+            assert_identical(&dst, &state.clone());
+            assert_eq!(dst.zobrist_key(), state.zobrist_key());
+            assert_eq!((dst.points.as_ptr(), dst.walls.as_ptr(), dst.original_points.as_ptr()), (points, walls, original_points));
+        }
+    }
+
+    // 回転や鏡映で重なる盤面は同じcanonical_hashになり、4回回転すると元の盤面に戻る
+    #[test]
+    fn rotated_and_mirrored_boards_share_canonical_hash() {
+        let config = MazeConfig { wall_ratio: 0.2, regen_after: Some(3), ..MazeConfig::new(4, 6, 20) };
+        let mut rng = make_rng(0);
+        for seed in 0..20 {
+            let mut state = MazeState::new(config, Some(seed));
+            state.record_history = true;
+            for _ in 0..seed % 5 {
+                if state.is_done() {
+                    break;
+                }
+                state.advance(seeded_random_action(&state, &mut rng)).unwrap();
+            }
+            let rotated = state.rotate90();
+            assert_eq!((rotated.config.h, rotated.config.w), (6, 4));
+            assert_eq!(rotated.canonical_hash(), state.canonical_hash(), "seed {}", seed);
+            assert_eq!(state.mirror_horizontal().rotate90().canonical_hash(), state.canonical_hash());
+
+            let back = rotated.rotate90().rotate90().rotate90();
+            assert_identical(&back, &state);
+            assert_identical(&state.mirror_horizontal().mirror_horizontal(), &state);
+        }
+
+        let a = MazeState::new(config, Some(0));
+        let b = MazeState::new(config, Some(1));
+        assert_ne!(a.canonical_hash(), b.canonical_hash());
+    }
+
+    // 開始マスを数える場合は、ターン0でそのマスのポイントを取った状態から始める。数えない場合は開始マスにポイントを置かない
+    #[test]
+    fn start_cell_scoring_under_both_settings() {
+        for regen_after in [None, Some(2)] {
+            let config = MazeConfig { point_min: 9, point_max: 9, regen_after, ..MazeConfig::new(4, 4, 10) };
+            for seed in 0..10 {
+                let state = MazeState::new(config, Some(seed));
+                assert_eq!(state.game_score, 0);
+                assert_eq!(state.get_point(state.character.y as usize, state.character.x as usize), 0);
+
+                let scored = MazeState::new(MazeConfig { score_start_cell: true, ..config }, Some(seed));
+                assert_eq!(scored.game_score, 9);
+                assert_eq!(scored.get_point(scored.character.y as usize, scored.character.x as usize), 0);
+                assert_eq!(scored.zobrist_key(), scored.compute_zobrist_key());
+                // ポイントが復活する盤面では、ターン0で踏んだものとしてregen_after後に戻る
+                if regen_after.is_some() {
+                    let cell = scored.character.y as usize * config.w + scored.character.x as usize;
+                    assert_eq!((scored.original_points[cell], scored.regen_turn[cell]), (9, 2));
+                }
+            }
+        }
+    }
+
+    // 合法でない行動は、その理由ごとに異なるIllegalActionになり、盤面は変わらない
+    #[test]
+    fn each_illegal_action_variant() {
+        let mut state: MazeState = "@#\n1.".parse().unwrap();
+        let before = state.clone();
+        assert_eq!(state.advance(0), Err(IllegalAction::Wall(Coord::new(0, 1))));
+        assert_eq!(state.advance(1), Err(IllegalAction::OutOfBounds { y: 0, x: -1 }));
+        assert_eq!(state.advance(3), Err(IllegalAction::OutOfBounds { y: -1, x: 0 }));
+        assert_eq!(state.advance(4), Err(IllegalAction::UnknownAction(4)));
+        assert_identical(&state, &before);
+
+        let mut finished = MazeState::empty(MazeConfig::new(2, 2, 1));
+        finished.advance(0).unwrap();
+        assert_eq!(finished.advance(1), Err(IllegalAction::GameOver));
+
+        assert_eq!(IllegalAction::Wall(Coord::new(0, 1)).to_string(), "(0, 1) is a wall");
+        assert_eq!(IllegalAction::UnknownAction(4).to_string(), "action 4 is not a direction of this board");
+        assert_eq!(IllegalAction::OutOfBounds { y: -1, x: 0 }.to_string(), "(-1, 0) is out of the board");
+        assert_eq!(IllegalAction::GameOver.to_string(), "the game is already over");
+    }
+
+    // 2マスの盤面の右に9を置くと、貪欲法は右に進んで9を取る
+    #[test]
+    fn greedy_walks_to_the_nine_on_a_two_cell_board() {
+        let mut state = MazeState::empty(MazeConfig::new(1, 2, 1));
+        state.set_point(0, 1, 9);
+        assert_eq!((state.get_point(0, 0), state.get_point(0, 1)), (0, 9));
+        assert_eq!(state.seed(), 0);
+        assert_eq!(greedy_action(&state), Some(0));
+        assert_eq!(state.run_to_completion(|s| greedy_action(s).unwrap()), GameResult::Finished { score: 9, turns: 1 });
+        assert_eq!(state.get_point(0, 1), 0);
+    }
+
+    // set_pointで同じマスを書き換えても、差分更新したZobristハッシュは計算し直したものと一致する
+    #[test]
+    fn set_point_keeps_the_zobrist_key_in_sync() {
+        let mut state = MazeState::empty(MazeConfig { regen_after: Some(2), ..MazeConfig::new(4, 4, 10) });
+        for (y, x, value) in [(1, 1, 9), (1, 1, 0), (1, 1, 9), (2, 3, -3), (2, 3, 5)] {
+            state.set_point(y, x, value);
+            assert_eq!(state.zobrist_key(), state.compute_zobrist_key());
+            assert_eq!(state.original_points[y * 4 + x], value);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "(2, 0) is out of the 2x2 board")]
+    fn get_point_off_the_board_panics() {
+        let _ = MazeState::empty(MazeConfig::new(2, 2, 1)).get_point(2, 0);
+    }
+}
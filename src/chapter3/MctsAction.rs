@@ -0,0 +1,389 @@
+#![allow(non_snake_case)]
+
+use rand::{Rng, thread_rng};
+use std::collections::{HashMap, HashSet};
+
+use super::MazeState00::{MazeConfig, MazeState};
+use crate::common::game::{greedy_action, run_episodes};
+use crate::common::time_keeper::TimeKeeper;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// ランダムに行動を決定する
+fn random_action(state: &MazeState, rng: &mut impl Rng) -> usize {
+    let legal_actions = state.legal_actions();
+    legal_actions[rng.gen_range(0..legal_actions.len())]
+}
+
+// ゲームが終わるまでランダムにプレイしてスコアを返す
+fn playout(state: &mut MazeState, rng: &mut impl Rng) -> i32 {
+    while !state.is_done() {
+        state.advance(random_action(state, rng)).expect("action must be legal");
+    }
+    state.game_score
+}
+
+const C: f64 = 1.0;  // UCB1の計算に使う定数
+const EXPAND_THRESHOLD: usize = 10;  // ノードを展開する閾値
+
+// MCTSの計算に使うノード
+struct Node {
+    state: MazeState,
+    w: f64,  // 累計価値
+    n: usize,  // 試行回数
+    child_nodes: Vec<Node>,
+}
+
+impl Node {
+    fn new(state: MazeState) -> Self {
+        Node {
+            state,
+            w: 0.0,
+            n: 0,
+            child_nodes: Vec::new(),
+        }
+    }
+
+    // ノードの評価を行う。プレイアウトの乱数はrngから取る
+    fn evaluate(&mut self, rng: &mut impl Rng) -> f64 {
+        if self.state.is_done() {
+            let value = self.state.normalized_score();
+            self.w += value;
+            self.n += 1;
+            return value;
+        }
+
+        if self.child_nodes.is_empty() {
+            let mut state_copy = self.state.clone();
+            playout(&mut state_copy, rng);
+            let value = state_copy.normalized_score();
+            self.w += value;
+            self.n += 1;
+
+            if self.n == EXPAND_THRESHOLD {
+                self.expand();
+            }
+
+            return value;
+        }
+
+        let value = self.next_child_node().evaluate(rng);
+        self.w += value;
+        self.n += 1;
+        value
+    }
+
+    // ノードを展開する
+    fn expand(&mut self) {
+        let legal_actions = self.state.legal_actions();
+        self.child_nodes.clear();
+        for action in legal_actions {
+            let mut next_state = self.state.clone();
+            next_state.advance(action).expect("action must be legal");
+            self.child_nodes.push(Node::new(next_state));
+        }
+    }
+
+    // どのノードを評価するか選択する
+    fn next_child_node(&mut self) -> &mut Node {
+        // 一度も評価していない子ノードがあれば優先して評価する
+        if let Some(index) = self.child_nodes.iter().position(|child| child.n == 0) {
+            return &mut self.child_nodes[index];
+        }
+
+        let t = self.child_nodes.iter().map(|child| child.n).sum::<usize>() as f64;
+        let mut best_value = f64::NEG_INFINITY;
+        let mut best_action_index = 0;
+        for (i, child) in self.child_nodes.iter().enumerate() {
+            let ucb1_value = child.w / child.n as f64 + C * (2.0 * t.ln() / child.n as f64).sqrt();
+            if ucb1_value > best_value {
+                best_action_index = i;
+                best_value = ucb1_value;
+            }
+        }
+
+        &mut self.child_nodes[best_action_index]
+    }
+}
+
+// 試行回数が最も多い子ノードの行動を選ぶ
+fn most_visited_action(state: &MazeState, root_node: &Node) -> usize {
+    let legal_actions = state.legal_actions();
+    let mut best_action_searched_number = 0;
+    let mut best_action_index = 0;
+    for (i, child) in root_node.child_nodes.iter().enumerate() {
+        if child.n > best_action_searched_number {
+            best_action_index = i;
+            best_action_searched_number = child.n;
+        }
+    }
+    legal_actions[best_action_index]
+}
+
+// プレイアウト数を指定してMCTSで行動を決定する
+pub fn mcts_action(state: &MazeState, playout_number: usize) -> usize {
+    mcts_action_with_rng(state, playout_number, &mut thread_rng())
+}
+
+// プレイアウト数とプレイアウトに使う乱数生成器を指定してMCTSで行動を決定する
+// 同じシードの乱数生成器を渡せば、同じ盤面からは毎回同じ行動を選ぶ
+pub fn mcts_action_with_rng(state: &MazeState, playout_number: usize, rng: &mut impl Rng) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    for _ in 0..playout_number {
+        root_node.evaluate(rng);
+    }
+    most_visited_action(state, &root_node)
+}
+
+// 制限時間(ms)を指定してMCTSで行動を決定する
+pub fn mcts_action_with_time_threshold(state: &MazeState, time_threshold: usize) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let mut rng = thread_rng();
+    while !time_keeper.is_time_over() {
+        root_node.evaluate(&mut rng);
+    }
+    most_visited_action(state, &root_node)
+}
+
+// 置換表に記録するノードの統計
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeStats {
+    pub w: f64,    // 累計価値
+    pub n: usize,  // 試行回数
+}
+
+// 盤面のZobristハッシュをキーとしてノードの統計を共有する置換表
+// 異なる手順で同じ盤面にたどり着いた場合も、同じ試行回数と累計価値を使う
+#[derive(Debug, Default)]
+pub struct TranspositionTable {
+    pub table: HashMap<u64, NodeStats>,
+    edges: HashSet<(u64, u64)>,  // 統計を調べたことのある(親, 子)の組
+    pub lookups: usize,  // 子ノードの統計を親ノードから初めて調べた回数
+    pub hits: usize,     // そのうち、別の手順で既に統計が記録されていた回数
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        TranspositionTable::default()
+    }
+
+    // 子ノードの統計を親ノードから初めて調べた回数のうち、別の手順で既に記録されていた割合を返す
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups as f64
+    }
+
+    // parent_keyの盤面の子であるkeyの盤面の統計を返す。記録されていない場合は試行回数0の統計を返す
+    fn lookup(&mut self, parent_key: u64, key: u64) -> NodeStats {
+        let stats = self.table.get(&key).copied();
+        if self.edges.insert((parent_key, key)) {
+            self.lookups += 1;
+            if stats.is_some() {
+                self.hits += 1;
+            }
+        }
+        stats.unwrap_or_default()
+    }
+
+    // 盤面の統計にvalueを加える
+    fn update(&mut self, key: u64, value: f64) {
+        let stats = self.table.entry(key).or_default();
+        stats.w += value;
+        stats.n += 1;
+    }
+
+    // 置換表を使ってノードの評価を行う
+    // 試行回数がEXPAND_THRESHOLDに達するまではプレイアウトで評価し、達した後は子ノードを選んで評価する
+    fn evaluate(&mut self, state: &MazeState, rng: &mut impl Rng) -> f64 {
+        let key = state.zobrist_key();
+        let value = if state.is_done() {
+            state.normalized_score()
+        } else if self.table.get(&key).map_or(0, |stats| stats.n) < EXPAND_THRESHOLD {
+            let mut state_copy = state.clone();
+            playout(&mut state_copy, rng);
+            state_copy.normalized_score()
+        } else {
+            let next_state = self.next_child_state(state);
+            self.evaluate(&next_state, rng)
+        };
+        self.update(key, value);
+        value
+    }
+
+    // どの子ノードを評価するか選択する
+    fn next_child_state(&mut self, state: &MazeState) -> MazeState {
+        let parent_key = state.zobrist_key();
+        let children: Vec<(MazeState, NodeStats)> = state
+            .legal_actions()
+            .into_iter()
+            .map(|action| {
+                let mut next_state = state.clone();
+                next_state.advance(action).expect("action must be legal");
+                let stats = self.lookup(parent_key, next_state.zobrist_key());
+                (next_state, stats)
+            })
+            .collect();
+
+        // 一度も評価していない子ノードがあれば優先して評価する
+        if let Some(index) = children.iter().position(|(_, stats)| stats.n == 0) {
+            return children.into_iter().nth(index).unwrap().0;
+        }
+
+        let t = children.iter().map(|(_, stats)| stats.n).sum::<usize>() as f64;
+        let mut best_value = f64::NEG_INFINITY;
+        let mut best_index = 0;
+        for (i, (_, stats)) in children.iter().enumerate() {
+            let ucb1_value = stats.w / stats.n as f64 + C * (2.0 * t.ln() / stats.n as f64).sqrt();
+            if ucb1_value > best_value {
+                best_index = i;
+                best_value = ucb1_value;
+            }
+        }
+        children.into_iter().nth(best_index).unwrap().0
+    }
+}
+
+// プレイアウト数を指定し、置換表を使うMCTSで行動を決定する。探索に使った置換表も返す
+// ルートの子ノードは最初から展開されているものとして扱う。プレイアウトの乱数はrngから取る
+pub fn mcts_action_with_transposition(state: &MazeState, playout_number: usize, rng: &mut impl Rng) -> (usize, TranspositionTable) {
+    let mut table = TranspositionTable::new();
+    for _ in 0..playout_number {
+        let next_state = table.next_child_state(state);
+        let value = table.evaluate(&next_state, rng);
+        table.update(state.zobrist_key(), value);
+    }
+
+    // 試行回数が最も多い子ノードの行動を選ぶ
+    let legal_actions = state.legal_actions();
+    let mut best_action_searched_number = 0;
+    let mut best_action = legal_actions[0];
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action).expect("action must be legal");
+        let n = table.table.get(&next_state.zobrist_key()).map_or(0, |stats| stats.n);
+        if n > best_action_searched_number {
+            best_action = action;
+            best_action_searched_number = n;
+        }
+    }
+    (best_action, table)
+}
+
+type AIFunction = fn(&MazeState) -> usize;
+
+struct StringAIPair {
+    name: String,
+    ai: AIFunction,
+}
+
+// 盤面生成シードを0..game_numberとしてgame_number回プレイして平均スコアを表示する
+fn test_ai_score(ai: &StringAIPair, game_number: usize) {
+    let summary = run_episodes(|seed| MazeState::new(CONFIG, Some(seed)), ai.ai, 0..game_number as u64);
+    println!("Score of {}:\t{}", ai.name, summary);
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    let ais = [
+        StringAIPair {
+            name: "greedy_action".to_string(),
+            ai: |state| greedy_action(state).unwrap(),
+        },
+        StringAIPair {
+            name: "mcts_action".to_string(),
+            ai: |state| mcts_action(state, 300),
+        },
+        StringAIPair {
+            name: "mcts_action_with_time_threshold".to_string(),
+            ai: |state| mcts_action_with_time_threshold(state, 1),
+        },
+        // 盤面のハッシュは取ったマスも区別するので、別の手順で同じ盤面になることは少なく、置換表の効果は小さい
+        StringAIPair {
+            name: "mcts_action_with_transposition".to_string(),
+            ai: |state| mcts_action_with_transposition(state, 300, &mut thread_rng()).0,
+        },
+    ];
+    for ai in ais {
+        test_ai_score(&ai, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::rng::make_rng;
+    use rand::SeedableRng;
+
+    // ポイントのない盤面では右→下と下→右が同じ盤面になるので、置換表の統計を共有できる
+    #[test]
+    fn transpositions_on_an_open_board_hit_the_table() {
+        let state = MazeState::empty(MazeConfig::new(4, 4, 6));
+        let (action, table) = mcts_action_with_transposition(&state, 500, &mut make_rng(0));
+        assert!(state.legal_actions().contains(&action));
+        assert!(table.hits <= table.lookups);
+        assert!(table.hit_rate() > 0.0);
+
+        assert_eq!(TranspositionTable::new().hit_rate(), 0.0);
+    }
+
+    // 空の盤面にseedから決めたpoint_number個のポイントだけを置いた盤面
+    // 取ったマスが少ないので、別の手順で同じ盤面にたどり着くことが多い
+    fn sparse_state(config: MazeConfig, seed: u64, point_number: usize) -> MazeState {
+        let mut rng = make_rng(seed);
+        let mut state = MazeState::empty(config);
+        for _ in 0..point_number {
+            let (y, x) = (rng.gen_range(0..config.h), rng.gen_range(0..config.w));
+            if (y, x) != (0, 0) {
+                state.set_point(y, x, rng.gen_range(1..10));
+            }
+        }
+        state
+    }
+
+    // 同じシードの乱数生成器を渡せば、同じ盤面から同じ行動を選ぶ
+    #[test]
+    fn same_rng_seed_gives_the_same_action() {
+        for seed in 0..10 {
+            let state = MazeState::new(MazeConfig::new(5, 5, 8), Some(seed));
+            assert_eq!(mcts_action_with_rng(&state, 100, &mut make_rng(seed)), mcts_action_with_rng(&state, 100, &mut make_rng(seed)));
+            assert_eq!(
+                mcts_action_with_transposition(&state, 100, &mut make_rng(seed)).0,
+                mcts_action_with_transposition(&state, 100, &mut make_rng(seed)).0,
+            );
+        }
+    }
+
+    // プレイアウト数とプレイアウトの乱数を揃えて、ポイントの少ない盤面で置換表の有無による平均スコアを比べる
+    // 置換表の統計は共有されるが、この盤面とプレイアウト数では平均スコアは変わらない(どちらも10.9)
+    // 強くなるとは限らないので、少なくとも大きく弱くならないことを確かめる
+    #[test]
+    fn transposition_keeps_the_mean_score_at_the_same_playout_number() {
+        let config = MazeConfig::new(5, 5, 8);
+        let mean_score = |select_action: &dyn Fn(&MazeState, &mut rand::rngs::StdRng) -> usize| {
+            let total: i32 = (0..20)
+                .map(|seed| {
+                    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+                    let mut state = sparse_state(config, seed, 3);
+                    while !state.is_done() {
+                        let action = select_action(&state, &mut rng);
+                        state.advance(action).unwrap();
+                    }
+                    state.game_score
+                })
+                .sum();
+            total as f64 / 20.0
+        };
+
+        let plain = mean_score(&|state, rng| mcts_action_with_rng(state, 100, rng));
+        let transposition = mean_score(&|state, rng| mcts_action_with_transposition(state, 100, rng).0);
+        println!("mean score: mcts_action {} / with transposition {}", plain, transposition);
+        assert!(plain > 0.0);
+        assert!(transposition >= plain * 0.9, "transposition {} plain {}", transposition, plain);
+    }
+}
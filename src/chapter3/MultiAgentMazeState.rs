@@ -0,0 +1,298 @@
+#![allow(non_snake_case)]
+
+use rand::Rng;
+use std::fmt;
+
+use super::MazeState00::{Coord, IllegalAction, MazeConfig, MazeState};
+use crate::common::rng::{make_rng, resolve_seed};
+use crate::common::stats::ScoreSummary;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(10, 10, 30);
+// キャラクターの初期位置を決める乱数列を盤面の乱数列と分けるために、シードに混ぜる値
+const AGENT_SEED_SALT: u64 = 0x9e3779b97f4a7c15;
+
+// 複数のキャラクターで遊ぶ一人ゲームの例
+// 盤面はMazeStateと同じ規則で生成する。(regen_afterは使わない)
+// 1ターンに全てのキャラクターが同時に1マスずつ進み、その後キャラクターのいるマスのポイントを取る。
+// 複数のキャラクターが同じマスに進んだ場合、そのマスのポイントは1回だけ数える。
+#[derive(Debug, Clone)]
+pub struct MultiAgentMazeState {
+    pub config: MazeConfig,
+    pub characters: Vec<Coord>,
+    pub points: Vec<i32>,  // y * w + x 番目のマスのポイント
+    pub walls: Vec<bool>,  // y * w + x 番目のマスが壁かどうか
+    pub turn: usize,
+    pub game_score: i32,
+}
+
+impl MultiAgentMazeState {
+    // MazeStateと同じ盤面を生成し、壁のない別々のマスにagent_n体のキャラクターを置く
+    // 1体目はMazeStateのキャラクターと同じ位置に置く。2体目以降は壁に囲まれてどこにも移動できないマスには置かない
+    pub fn new(config: MazeConfig, agent_n: usize, seed: Option<u64>) -> Self {
+        let seed = resolve_seed(seed);
+        let state = MazeState::new(config, Some(seed));
+        // 置けるマスかどうかは、1体目だけを置いた盤面で調べる
+        let board = MultiAgentMazeState::from_state(&state, vec![state.character]);
+        let placeable = |coord: Coord| !board.walls[board.cell_index(coord)] && !board.is_enclosed(coord);
+        let placeable_n = (0..config.h as i32)
+            .flat_map(|y| (0..config.w as i32).map(move |x| Coord::new(y, x)))
+            .filter(|&coord| placeable(coord) && (coord.y, coord.x) != (state.character.y, state.character.x))
+            .count();
+        assert!(agent_n <= placeable_n + 1, "agent_n must not exceed the number of floor cells that are not walled in");
+
+        let mut rng = make_rng(seed ^ AGENT_SEED_SALT);
+        let mut characters = vec![state.character];
+        while characters.len() < agent_n {
+            let coord = Coord::new(rng.gen_range(0..config.h as i32), rng.gen_range(0..config.w as i32));
+            let occupied = characters.iter().any(|c| c.y == coord.y && c.x == coord.x);
+            if !occupied && placeable(coord) {
+                characters.push(coord);
+            }
+        }
+
+        MultiAgentMazeState::from_state(&state, characters)
+    }
+
+    // 盤面をstateから引き継ぎ、キャラクターをcharactersの位置に置く
+    // キャラクターのいるマスのポイントは取ったものとして0にする
+    pub fn from_state(state: &MazeState, characters: Vec<Coord>) -> Self {
+        let mut multi_state = MultiAgentMazeState {
+            config: state.config,
+            characters,
+            points: state.points.clone(),
+            walls: state.walls.clone(),
+            turn: state.turn,
+            game_score: state.game_score,
+        };
+        for i in 0..multi_state.characters.len() {
+            let cell = multi_state.cell_index(multi_state.characters[i]);
+            multi_state.points[cell] = 0;
+        }
+        multi_state
+    }
+
+    // [どのゲームでも実装する] : ゲームの終了判定
+    // MazeStateと同じく、壁に囲まれてどこにも移動できないキャラクターがいる場合もゲーム終了とする
+    pub fn is_done(&self) -> bool {
+        self.turn == self.config.end_turn || self.characters.iter().any(|&character| self.is_enclosed(character))
+    }
+
+    // agent番目のキャラクターが可能な行動を全て取得する
+    // 行動の組み合わせは列挙せず、キャラクターごとに独立して選ぶ
+    pub fn legal_actions_for(&self, agent: usize) -> Vec<usize> {
+        (0..self.config.movement.action_n())
+            .filter(|&action| self.can_move(agent, action))
+            .collect()
+    }
+
+    // キャラクターごとの行動actionsでゲームを1ターン進める
+    // actionsの長さはキャラクターの数と同じにする。合法でない行動がある場合はエラーを返し、盤面は変更しない
+    pub fn advance(&mut self, actions: &[usize]) -> Result<(), IllegalAction> {
+        assert_eq!(actions.len(), self.characters.len(), "actions must have one action per character");
+        if self.is_done() {
+            return Err(IllegalAction::GameOver);
+        }
+        for (agent, &action) in actions.iter().enumerate() {
+            if action >= self.config.movement.action_n() {
+                return Err(IllegalAction::UnknownAction(action));
+            }
+            let target = self.target(agent, action);
+            if !target.in_bounds(self.config.h, self.config.w) {
+                return Err(IllegalAction::OutOfBounds { y: target.y, x: target.x });
+            }
+            if self.walls[self.cell_index(target)] {
+                return Err(IllegalAction::Wall(target));
+            }
+        }
+
+        for (character, &action) in self.characters.iter_mut().zip(actions) {
+            *character = character.step(action);
+        }
+        for i in 0..self.characters.len() {
+            let cell = self.cell_index(self.characters[i]);
+            self.game_score += self.points[cell];
+            self.points[cell] = 0;
+        }
+        self.turn += 1;
+
+        Ok(())
+    }
+
+    // agent番目のキャラクターのactionでの移動先のマスを返す
+    fn target(&self, agent: usize, action: usize) -> Coord {
+        self.characters[agent].step(action)
+    }
+
+    // agent番目のキャラクターのactionでの移動先が盤面内かつ壁でないか判定する
+    fn can_move(&self, agent: usize, action: usize) -> bool {
+        let target = self.target(agent, action);
+        target.in_bounds(self.config.h, self.config.w) && !self.walls[self.cell_index(target)]
+    }
+
+    // coordのマスから、どの方向にも移動できないか判定する
+    fn is_enclosed(&self, coord: Coord) -> bool {
+        (0..self.config.movement.action_n()).all(|action| {
+            let target = coord.step(action);
+            !target.in_bounds(self.config.h, self.config.w) || self.walls[self.cell_index(target)]
+        })
+    }
+
+    // 座標をマスの通し番号に変換する
+    fn cell_index(&self, coord: Coord) -> usize {
+        coord.y as usize * self.config.w + coord.x as usize
+    }
+}
+
+// [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
+// キャラクターは番号の順に'@'で表し、それ以外はMazeStateと同じ文字で表す
+impl fmt::Display for MultiAgentMazeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "turn:\t{}\nscore:\t{}\n", self.turn, self.game_score)?;
+
+        for y in 0..self.config.h {
+            for x in 0..self.config.w {
+                let cell = y * self.config.w + x;
+                if self.characters.iter().any(|c| c.y as usize == y && c.x as usize == x) {
+                    write!(f, "@")?;
+                } else if self.walls[cell] {
+                    write!(f, "#")?;
+                } else if self.points[cell] > 0 {
+                    write!(f, "{}", self.points[cell])?;
+                } else if self.points[cell] < 0 {
+                    write!(f, "{}", (b'a' + (-self.points[cell] - 1) as u8) as char)?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// キャラクターの番号順に、移動先のポイントが最も高い行動を選ぶ
+// 先に選んだキャラクターの移動先と同じマスのポイントは取れないので0として比べる
+pub fn greedy_joint_actions(state: &MultiAgentMazeState) -> Vec<usize> {
+    let mut actions = Vec::with_capacity(state.characters.len());
+    let mut targets: Vec<usize> = Vec::with_capacity(state.characters.len());
+
+    for agent in 0..state.characters.len() {
+        let mut best: Option<(i32, usize, usize)> = None;
+        for action in state.legal_actions_for(agent) {
+            let cell = state.cell_index(state.target(agent, action));
+            let point = if targets.contains(&cell) { 0 } else { state.points[cell] };
+            if best.is_none_or(|(best_point, _, _)| point > best_point) {
+                best = Some((point, action, cell));
+            }
+        }
+        // 移動できないキャラクターがいる盤面はis_doneなので、終了していなければ全員に合法手がある
+        let (_, action, cell) = best.expect("every character must have a legal action before the game is done");
+        actions.push(action);
+        targets.push(cell);
+    }
+
+    actions
+}
+
+// キャラクターの数を指定して、盤面生成シードを0..game_numberとしてgame_number回プレイしてスコアの分布を表示する
+fn test_ai_score(agent_n: usize, game_number: usize) -> ScoreSummary {
+    let mut scores = Vec::with_capacity(game_number);
+
+    for i in 0..game_number {
+        let mut state = MultiAgentMazeState::new(CONFIG, agent_n, Some(i as u64));
+        while !state.is_done() {
+            let actions = greedy_joint_actions(&state);
+            state.advance(&actions).expect("actions must be legal");
+        }
+        scores.push(state.game_score);
+    }
+
+    let summary = ScoreSummary::from_scores(&scores);
+    println!("Score of greedy_joint_actions({} characters):\t{}", agent_n, summary);
+    summary
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    // キャラクターを増やすと、同じターン数でより多くのポイントを取れる
+    for agent_n in [1, 2, 4] {
+        test_ai_score(agent_n, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 盤面をlayoutから読み込み、キャラクターをcharactersの位置に置く
+    fn from_layout(layout: &str, characters: Vec<Coord>) -> MultiAgentMazeState {
+        let state: MazeState = layout.parse().unwrap();
+        MultiAgentMazeState::from_state(&state, characters)
+    }
+
+    fn assert_distinct(state: &MultiAgentMazeState) {
+        let [a, b] = [state.characters[0], state.characters[1]];
+        assert!((a.y, a.x) != (b.y, b.x), "characters collided at turn {}", state.turn);
+    }
+
+    #[test]
+    fn two_agents_collect_distinct_cells_without_colliding() {
+        let mut state = from_layout("321@...123", vec![Coord::new(0, 3), Coord::new(0, 6)]);
+        state.config.end_turn = 3;
+        while !state.is_done() {
+            state.advance(&greedy_joint_actions(&state)).unwrap();
+            assert_distinct(&state);
+        }
+        assert_eq!((state.characters[0], state.characters[1]), (Coord::new(0, 0), Coord::new(0, 9)));
+        assert_eq!(state.game_score, 12);
+        assert!(state.points.iter().all(|&point| point == 0));
+    }
+
+    // 2体が同じマスのポイントを狙える場合は、先に選んだ1体だけが取りに行く
+    #[test]
+    fn a_shared_target_is_taken_by_one_agent() {
+        let mut state = from_layout("@.5..", vec![Coord::new(0, 1), Coord::new(0, 3)]);
+        state.advance(&greedy_joint_actions(&state)).unwrap();
+        assert_eq!((state.characters[0], state.characters[1]), (Coord::new(0, 2), Coord::new(0, 4)));
+        assert_eq!(state.game_score, 5);
+    }
+
+    #[test]
+    fn seeded_agents_start_on_distinct_floor_cells() {
+        let config = MazeConfig { wall_ratio: 0.2, ..MazeConfig::new(6, 6, 10) };
+        for seed in 0..20 {
+            let state = MultiAgentMazeState::new(config, 2, Some(seed));
+            assert_distinct(&state);
+            assert!(state.characters.iter().all(|&c| !state.walls[state.cell_index(c)]));
+        }
+    }
+
+    // 壁に囲まれたキャラクターがいる盤面はゲーム終了として扱い、行動を選ぼうとしない
+    #[test]
+    fn a_walled_in_agent_ends_the_game() {
+        let mut state = from_layout("@#...\n##...\n.....", vec![Coord::new(0, 0), Coord::new(2, 4)]);
+        assert!(state.is_done());
+        assert_eq!(state.advance(&[0, 0]), Err(IllegalAction::GameOver));
+
+        // 1体目が移動できる盤面では、2体目が囲まれていてもゲーム終了になる
+        state = from_layout("@....\n...##\n...#.", vec![Coord::new(0, 0), Coord::new(2, 4)]);
+        assert!(state.is_done());
+        state = from_layout("@....\n...##\n.....", vec![Coord::new(0, 0), Coord::new(2, 4)]);
+        assert!(!state.is_done());
+    }
+
+    // 壁の多い盤面でも、2体目以降は囲まれたマスに置かないので、最後まで貪欲法でプレイできる
+    #[test]
+    fn greedy_plays_walled_boards_to_the_end() {
+        let config = MazeConfig { wall_ratio: 0.4, ..MazeConfig::new(6, 6, 10) };
+        for seed in 0..50 {
+            let mut state = MultiAgentMazeState::new(config, 3, Some(seed));
+            assert!(state.characters[1..].iter().all(|&c| !state.is_enclosed(c)), "seed {}", seed);
+            while !state.is_done() {
+                state.advance(&greedy_joint_actions(&state)).unwrap();
+            }
+        }
+    }
+}
@@ -1,168 +1,223 @@
 #![allow(non_snake_case)]
 
-use rand::{Rng, SeedableRng, rngs, thread_rng};
+use super::MazeState00::{MazeConfig, MazeState, render_heatmap, visit_heatmap};
+use crate::common::game::{Game, greedy_action, run_episodes};
+use crate::common::time_keeper::TimeKeeper;
+use std::collections::HashMap;
 
-// // 座標を保持する
-#[derive(Debug, Clone, Copy)]
-struct Coord {
-    y: i32,
-    x: i32,
-}
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(3, 4, 4);
 
-impl Coord {
-    fn new(y: i32, x: i32) -> Self {
-        Coord { y, x }
+// 2手先まで読む貪欲法で行動を決定する
+// 1手目ごとに最も良い2手目を選んだ場合のスコアを求め、2手分の合計が最も高くなる1手目を選ぶ
+fn greedy_action_depth2(state: &MazeState) -> usize {
+    let legal_actions = state.legal_actions();
+    let mut best_score = i32::MIN;
+    let mut best_action = legal_actions[0];
+
+    // 盤面の複製は1回だけにして、行動ごとに進めては元に戻す
+    let mut state_temp = state.clone();
+    for action in legal_actions {
+        let first_token = state_temp.advance_undoable(action).expect("action must be legal");
+        let mut score = state_temp.game_score;
+        if !state_temp.is_done() {
+            for second_action in state_temp.legal_actions() {
+                let second_token = state_temp.advance_undoable(second_action).expect("action must be legal");
+                score = score.max(state_temp.game_score);
+                state_temp.undo(second_token);
+            }
+        }
+        if score > best_score {
+            best_score = score;
+            best_action = action;
+        }
+        state_temp.undo(first_token);
     }
-}
 
-// 迷路の高さと幅
-const H: usize = 3;
-const W: usize = 4;
-// ゲーム終了ターン
-const END_TURN: usize = 4;
-
-// 一人ゲームの例
-// 1ターンに上下左右四方向のいずれかに1マスずつ進む。
-// 床にあるポイントを踏むと自身のスコアとなり、床のポイントが消える。
-// END_TURNの時点のスコアを高くすることが目的
-#[derive(Debug, Clone)]
-struct MazeState {
-    character: Coord,
-    points: [[i32; W]; H],
-    turn: usize,
-    game_score: i32,
-    evaluated_score: i32,
+    best_action
 }
 
-impl MazeState{
-    fn new(seed: Option<u64>) -> Self {
-        let mut rng_for_construct: rngs::StdRng = SeedableRng::seed_from_u64(thread_rng().gen());
-        if let Some(s) = seed {
-            rng_for_construct = SeedableRng::seed_from_u64(s)
+// greedy_action_depth2と同じ行動を選ぶが、2手進めた盤面のZobristハッシュごとにスコアを覚えておき、
+// 同じ盤面を再び調べる場合は覚えておいたスコアを使う。覚えておくのは1回の呼び出しの間だけとする
+// 右→下と下→右のように、通ったマスのポイントが0であれば別の手順でも2手目の盤面は同じになる
+fn greedy_action_depth2_memo(state: &MazeState) -> usize {
+    let legal_actions = state.legal_actions();
+    let mut best_score = i32::MIN;
+    let mut best_action = legal_actions[0];
+    let mut memo: HashMap<u64, i32> = HashMap::new();
+
+    let mut state_temp = state.clone();
+    for action in legal_actions {
+        let first_token = state_temp.advance_undoable(action).expect("action must be legal");
+        let mut score = state_temp.game_score;
+        if !state_temp.is_done() {
+            for second_action in state_temp.legal_actions() {
+                let second_token = state_temp.advance_undoable(second_action).expect("action must be legal");
+                let second_score = *memo.entry(state_temp.zobrist_key()).or_insert(state_temp.game_score);
+                score = score.max(second_score);
+                state_temp.undo(second_token);
+            }
         }
-        let character = Coord::new(rng_for_construct.gen_range(0..H as i32), rng_for_construct.gen_range(0..W as i32));
-
-        let mut points = [[0; W]; H];   // 床のポイントを1~9で表現する
-
-        // h*wの迷路を生成する。
-        for y in 0..H {
-            for x in 0..W {
-                if y == character.y as usize && x == character.x as usize {
-                    continue;
-                }
-                points[y][x] = rng_for_construct.gen_range(0..10);
-             }
+        if score > best_score {
+            best_score = score;
+            best_action = action;
         }
+        state_temp.undo(first_token);
+    }
 
-        let turn = 0;  // 現在のターン
-        let game_score = 0;  // ゲーム上で実際に得たスコア
-        let evaluated_score = 0;  // 探索上で評価したスコア
+    best_action
+}
 
-        MazeState {
-            character,
-            points,
-            turn,
-            game_score,
-            evaluated_score,
-        }
+// 現在の盤面からdepth手以内の行動列を全て調べ、途中を含めて到達できる最大のgame_scoreを返す
+// 調べ終わる前に時間切れになった場合はNoneを返す
+fn max_score_within(state: &mut MazeState, depth: usize, time_keeper: &TimeKeeper) -> Option<i32> {
+    if depth == 0 || state.is_done() {
+        return Some(state.game_score);
     }
-
-    // [どのゲームでも実装する] : ゲームの終了判定
-    fn is_done(&mut self) -> bool {
-        self.turn == END_TURN
+    if time_keeper.is_time_over() {
+        return None;
     }
-    // [どのゲームでも実装する] : 探索用の盤面評価をする
-    fn evaluate_score(&mut self) {
-        self.evaluated_score = self.game_score;
+    let mut best_score = state.game_score;
+    for action in state.legal_actions() {
+        let token = state.advance_undoable(action).expect("action must be legal");
+        let score = max_score_within(state, depth - 1, time_keeper);
+        state.undo(token);
+        best_score = best_score.max(score?);
     }
-    // [どのゲームでも実装する] : 指定したactionでゲームを1ターン進める
-    fn advance(&mut self, action: usize) {
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
-
-        self.character.x += dx[action] as i32;
-        self.character.y += dy[action] as i32;
-
-        let point = &mut self.points[self.character.y as usize][self.character.x as usize];
-        if *point > 0 {
-            self.game_score += *point;
-            *point = 0;
-        }
+    Some(best_score)
+}
 
-        self.turn += 1;
-    }
-    // [どのゲームでも実装する] : 現在の状況でプレイヤーが可能な行動を全て取得する
-    fn legal_actions(&self) -> Vec<usize> {
-        let mut actions = Vec::new();
-        let dy = [0, 0, 1, -1];
-        let dx = [1, -1, 0, 0];
-
-        for action in 0..4 {
-            let ty = (self.character.y + dy[action]) as usize;
-            let tx = (self.character.x + dx[action]) as usize;
-            if ty < H && tx < W {
-                actions.push(action);
-            }
+// depth手先まで読む貪欲法で行動を決定する。depth = 2 の場合はgreedy_action_depth2と同じ行動を選ぶ
+// 時間切れで調べ終わらなかった場合はNoneを返す
+fn greedy_action_depth(state: &MazeState, depth: usize, time_keeper: &TimeKeeper) -> Option<usize> {
+    let legal_actions = state.legal_actions();
+    let mut best_score = i32::MIN;
+    let mut best_action = legal_actions[0];
+
+    let mut state_temp = state.clone();
+    for action in legal_actions {
+        let token = state_temp.advance_undoable(action).expect("action must be legal");
+        let score = max_score_within(&mut state_temp, depth - 1, time_keeper);
+        state_temp.undo(token);
+        let score = score?;
+        if score > best_score {
+            best_score = score;
+            best_action = action;
         }
+    }
 
-        actions
+    Some(best_action)
+}
+
+// 1手先の貪欲法から始め、時間が残っている間は読む手数を1手ずつ増やす
+// 最後まで調べ終わった最も深い手数での行動を返す。残りターン数より深くは読まない
+pub fn anytime_greedy_action(state: &MazeState, time_threshold: usize) -> usize {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    // 1手先は時間の判定をせずに必ず調べ終わる
+    let mut best_action = greedy_action_depth(state, 1, &time_keeper).unwrap();
+    for depth in 2..=state.config.end_turn - state.turn {
+        match greedy_action_depth(state, depth, &time_keeper) {
+            Some(action) => best_action = action,
+            None => break,
+        }
     }
+    best_action
+}
+
+// 任意のゲームについて、盤面生成シードを0..game_numberとして指定したAIでgame_number回プレイし、スコアの分布を表示する
+fn test_ai_score<G: Game>(name: &str, game_number: u64, new_state: impl Fn(u64) -> G, select_action: impl Fn(&G) -> G::Action) {
+    println!("Score of {}: {}", name, run_episodes(new_state, select_action, 0..game_number));
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    let new_state = |seed| MazeState::new(CONFIG, Some(seed));
+    let greedy = |state: &MazeState| greedy_action(state).expect("greedy_action needs a legal action");
+    test_ai_score("greedy_action", 100, new_state, greedy);
+    test_ai_score("greedy_action_depth2", 100, new_state, greedy_action_depth2);
+    test_ai_score("anytime_greedy_action(1ms)", 100, new_state, |state| anytime_greedy_action(state, 1));
+
+    // 盤面が広くゲームが長いほど、2手先を読む効果が大きくなる
+    let new_state = |seed| MazeState::new(MazeConfig::new(30, 30, 100), Some(seed));
+    test_ai_score("greedy_action(30x30)", 100, new_state, greedy);
+    test_ai_score("greedy_action_depth2(30x30)", 100, new_state, greedy_action_depth2);
+    test_ai_score("greedy_action_depth2_memo(30x30)", 100, new_state, greedy_action_depth2_memo);
+    test_ai_score("anytime_greedy_action(1ms, 30x30)", 100, new_state, |state| anytime_greedy_action(state, 1));
+
+    // 貪欲法がどのマスに移動しやすいかを100ゲーム分の回数で表示する
+    let heatmap = visit_heatmap(MazeConfig::new(10, 10, 30), 0..100, |state| {
+        greedy_action(state).expect("greedy_action needs a legal action")
+    });
+    println!("visit heatmap of greedy_action(10x10):\n{}", render_heatmap(&heatmap));
+}
 
-    // [実装しなくてもよいが実装すると便利] : 現在のゲーム状況を文字列にする
-    fn _to_string(&self) -> String {
-        let mut result = format!("turn:\t{}\nscore:\t{}\n", self.turn, self.game_score);
-
-        for h in 0..H {
-            for w in 0..W {
-                if self.character.y as usize == h && self.character.x as usize == w {
-                    result.push('@');
-                } else if self.points[h][w] > 0 {
-                    result.push_str(&self.points[h][w].to_string());
-                } else {
-                    result.push('.');
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::MazeState00::Movement;
+
+    // 時間切れにならない制限時間(ms)
+    const GENEROUS_MS: usize = 1_000_000;
+
+    // 1手先と2手先まで読むgreedy_action_depthは、それぞれgreedy_actionとgreedy_action_depth2と同じ行動を選ぶ
+    #[test]
+    fn fixed_depths_match_the_dedicated_greedy_functions() {
+        let configs = [
+            CONFIG,
+            MazeConfig { wall_ratio: 0.2, trap_ratio: 0.2, ..MazeConfig::new(6, 6, 12) },
+            MazeConfig { movement: Movement::EightWay, ..MazeConfig::new(5, 5, 10) },
+        ];
+        let time_keeper = TimeKeeper::new(GENEROUS_MS);
+        for config in configs {
+            for seed in 0..20 {
+                let mut state = MazeState::new(config, Some(seed));
+                while !state.is_done() {
+                    assert_eq!(greedy_action_depth(&state, 1, &time_keeper), greedy_action(&state));
+                    assert_eq!(greedy_action_depth(&state, 2, &time_keeper), Some(greedy_action_depth2(&state)));
+                    state.advance(greedy_action_depth2(&state)).unwrap();
                 }
             }
-            result.push('\n');
         }
-
-        result
     }
-}
 
-fn greedy_action(state: &MazeState) -> usize {
-    let legal_actions = state.legal_actions();
-    // 絶対にありえない小さな値でベストスコアを初期化する
-    let mut best_score = -1;
-    // ありえない行動で初期化する
-    let mut best_action = -1_isize;
-
-    for &action in &legal_actions {
-        let mut state_temp: MazeState = state.clone();
-        state_temp.advance(action);
-        state_temp.evaluate_score();
-        if state_temp.evaluated_score > best_score {
-            best_score = state_temp.evaluated_score;
-            best_action = action as isize;
+    // 残り2ターンの盤面では、十分な時間があっても2手先までしか読まないので、greedy_action_depth2と同じ1手目を選ぶ
+    #[test]
+    fn generous_budget_with_two_turns_left_matches_depth2() {
+        for seed in 0..50 {
+            let mut state = MazeState::new(CONFIG, Some(seed));
+            state.advance(greedy_action(&state).unwrap()).unwrap();
+            state.advance(greedy_action(&state).unwrap()).unwrap();
+            if state.is_done() {
+                continue;
+            }
+            assert_eq!(anytime_greedy_action(&state, GENEROUS_MS), greedy_action_depth2(&state), "seed {}", seed);
         }
+        // シード0の盤面では、最初のターンに4手先まで読んでも2手先までと同じ1手目になる
+        let state = MazeState::new(CONFIG, Some(0));
+        assert_eq!(anytime_greedy_action(&state, GENEROUS_MS), greedy_action_depth2(&state));
     }
-    best_action as usize
-}
 
-// ゲームをgame_number回プレイして平均スコアを表示する
-fn test_ai_score(game_number: u64) {
-    let mut total_score = 0;
-    for _ in 0..game_number {
-        let mut state: MazeState = MazeState::new(None);
-        while !state.is_done() {
-            state.advance(greedy_action(&state));
+    // メモを使っても、ゲームの最後までgreedy_action_depth2と同じ行動を選ぶ
+    // ポイントのない盤面では、別の手順で同じ2手目の盤面になるのでメモが使われる
+    #[test]
+    fn memo_picks_the_same_action_as_depth2() {
+        let configs = [
+            CONFIG,
+            MazeConfig::new(30, 30, 100),
+            MazeConfig { wall_ratio: 0.2, trap_ratio: 0.2, ..MazeConfig::new(6, 6, 12) },
+            MazeConfig { movement: Movement::EightWay, ..MazeConfig::new(5, 5, 10) },
+        ];
+        for config in configs {
+            for seed in 0..20 {
+                let mut state = MazeState::new(config, Some(seed));
+                while !state.is_done() {
+                    let action = greedy_action_depth2(&state);
+                    assert_eq!(greedy_action_depth2_memo(&state), action, "seed {} turn {}", seed, state.turn);
+                    state.advance(action).unwrap();
+                }
+            }
         }
-        total_score += state.game_score;
+        let state = MazeState::empty(CONFIG);
+        assert_eq!(greedy_action_depth2_memo(&state), greedy_action_depth2(&state));
     }
-    let score_mean = total_score as f64 / game_number as f64;
-    println!("Score: {}", score_mean);
 }
-
-#[allow(dead_code)]
-pub fn main() {
-    test_ai_score(100);
-}
\ No newline at end of file
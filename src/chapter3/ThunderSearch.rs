@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+
+use super::MazeState00::{MazeConfig, MazeState};
+use super::MctsAction::mcts_action;
+use crate::common::game::run_episodes;
+use crate::common::time_keeper::TimeKeeper;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// Thunderサーチの計算に使うノード
+// MCTSと違い、プレイアウトをせずに盤面評価をそのまま価値とする
+struct Node {
+    state: MazeState,
+    w: f64,  // 累計価値
+    n: usize,  // 試行回数
+    child_nodes: Vec<Node>,
+}
+
+impl Node {
+    fn new(state: MazeState) -> Self {
+        Node {
+            state,
+            w: 0.0,
+            n: 0,
+            child_nodes: Vec::new(),
+        }
+    }
+
+    // ノードの評価を行う
+    fn evaluate(&mut self) -> f64 {
+        if self.state.is_done() {
+            let value = self.state.normalized_score();
+            self.w += value;
+            self.n += 1;
+            return value;
+        }
+
+        // 葉ノードは盤面評価を価値とし、すぐに展開する
+        if self.child_nodes.is_empty() {
+            let value = self.state.normalized_score();
+            self.w += value;
+            self.n += 1;
+
+            self.expand();
+
+            return value;
+        }
+
+        let value = self.next_child_node().evaluate();
+        self.w += value;
+        self.n += 1;
+        value
+    }
+
+    // ノードを展開する
+    fn expand(&mut self) {
+        let legal_actions = self.state.legal_actions();
+        self.child_nodes.clear();
+        for action in legal_actions {
+            let mut next_state = self.state.clone();
+            next_state.advance(action).expect("action must be legal");
+            self.child_nodes.push(Node::new(next_state));
+        }
+    }
+
+    // どのノードを評価するか選択する
+    // 盤面評価にばらつきがないので、UCB1の探索項は使わず平均価値が最も高いノードを選ぶ
+    fn next_child_node(&mut self) -> &mut Node {
+        // 一度も評価していない子ノードがあれば優先して評価する
+        if let Some(index) = self.child_nodes.iter().position(|child| child.n == 0) {
+            return &mut self.child_nodes[index];
+        }
+
+        let mut best_value = f64::NEG_INFINITY;
+        let mut best_action_index = 0;
+        for (i, child) in self.child_nodes.iter().enumerate() {
+            let thunder_value = child.w / child.n as f64;
+            if thunder_value > best_value {
+                best_action_index = i;
+                best_value = thunder_value;
+            }
+        }
+
+        &mut self.child_nodes[best_action_index]
+    }
+}
+
+// 試行回数が最も多い子ノードの行動を選ぶ
+fn most_visited_action(state: &MazeState, root_node: &Node) -> usize {
+    let legal_actions = state.legal_actions();
+    let mut best_action_searched_number = 0;
+    let mut best_action_index = 0;
+    for (i, child) in root_node.child_nodes.iter().enumerate() {
+        if child.n > best_action_searched_number {
+            best_action_index = i;
+            best_action_searched_number = child.n;
+        }
+    }
+    legal_actions[best_action_index]
+}
+
+// 評価回数を指定してThunderサーチで行動を決定する
+pub fn thunder_search_action(state: &MazeState, playout_number: usize) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    for _ in 0..playout_number {
+        root_node.evaluate();
+    }
+    most_visited_action(state, &root_node)
+}
+
+// 制限時間(ms)を指定してThunderサーチで行動を決定する
+pub fn thunder_search_action_with_time_threshold(state: &MazeState, time_threshold: usize) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    while !time_keeper.is_time_over() {
+        root_node.evaluate();
+    }
+    most_visited_action(state, &root_node)
+}
+
+type AIFunction = fn(&MazeState) -> usize;
+
+struct StringAIPair {
+    name: String,
+    ai: AIFunction,
+}
+
+// 盤面生成シードを0..game_numberとしてgame_number回プレイして平均スコアを表示する
+fn test_ai_score(ai: &StringAIPair, game_number: usize) {
+    let summary = run_episodes(|seed| MazeState::new(CONFIG, Some(seed)), ai.ai, 0..game_number as u64);
+    println!("Score of {}:\t{}", ai.name, summary);
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    let ais = [
+        StringAIPair {
+            name: "mcts_action".to_string(),
+            ai: |state| mcts_action(state, 300),
+        },
+        StringAIPair {
+            name: "thunder_search_action".to_string(),
+            ai: |state| thunder_search_action(state, 300),
+        },
+        StringAIPair {
+            name: "thunder_search_action_with_time_threshold".to_string(),
+            ai: |state| thunder_search_action_with_time_threshold(state, 1),
+        },
+    ];
+    for ai in ais {
+        test_ai_score(&ai, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 乱数を使わないので、同じ盤面と評価回数からは毎回同じ合法な行動を選ぶ
+    #[test]
+    fn same_board_gives_the_same_legal_action() {
+        for seed in 0..10 {
+            let mut state = MazeState::new(MazeConfig::new(5, 5, 10), Some(seed));
+            while !state.is_done() {
+                let action = thunder_search_action(&state, 100);
+                assert!(state.legal_actions().contains(&action), "seed {}", seed);
+                assert_eq!(thunder_search_action(&state, 100), action, "seed {}", seed);
+                state.advance(action).unwrap();
+            }
+        }
+    }
+
+    // 隣のマスの9は1手で取れるので、評価回数が少なくても取りに行く
+    #[test]
+    fn takes_the_adjacent_best_point() {
+        let mut state: MazeState = "@9.\n...\n..1".parse().unwrap();
+        state.config.end_turn = 2;
+        let action = thunder_search_action(&state, 30);
+        state.advance(action).unwrap();
+        assert_eq!(state.game_score, 9);
+    }
+}
@@ -1,9 +1,13 @@
-#![allow(non_snake_case)]
+#![allow(non_snake_case, clippy::needless_range_loop)]
 
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 
-use rand::{Rng, SeedableRng, rngs, thread_rng};
+use rand::{Rng, SeedableRng, rngs};
+use std::fmt;
+
+use crate::common::game::Game;
+use crate::common::rng::{make_rng, resolve_seed};
 
 const H: usize = 5;        // 迷路の高さ
 const W: usize = 5;        // 迷路の幅
@@ -25,7 +29,7 @@ static RNG:  Lazy<Mutex<rngs::StdRng>> = Lazy::new(|| {
 
 // 座標を保持する
 #[derive(Clone, Copy)]
-struct Coord {
+pub(crate) struct Coord {
     y: usize,
     x: usize,
 }
@@ -39,22 +43,20 @@ struct Coord {
 // ゲームに介入できる要素として、初期状態でのキャラクターをどこに配置するかを選択できる。
 // どのようにキャラクターを配置すると最終スコアが高くなるかを考えるゲーム。
 # [derive (Clone)]
-struct AutoMoveMazeState {
+pub(crate) struct AutoMoveMazeState {
     points: [[usize; W]; H], // 床のポイントを1~9で表現する
     turn: usize,             // 現在のターン
     characters: [Coord; CHARACTER_N], // CHARACTER_N体のキャラクター
     game_score: usize,       // ゲーム上で実際に得たスコア
+    seed: u64,               // 盤面を生成したシード
     // evaluated_score: ScoreType, // 探索上で評価したスコア
 }
 
 impl AutoMoveMazeState {
     // h*wの迷路を生成する。
-    fn new(seed: Option<usize>) -> Self {
-
-        let mut rng: rngs::StdRng = SeedableRng::seed_from_u64(thread_rng().gen());
-        if let Some(s) = seed  {
-            rng = SeedableRng::seed_from_u64(s as u64)
-        }
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let seed = resolve_seed(seed);
+        let mut rng = make_rng(seed);
         let mut points = [[0; W]; H];
         for y in 0..H {
             for x in 0..W {
@@ -67,10 +69,29 @@ impl AutoMoveMazeState {
             turn: 0,
             characters: [Coord { y: 0, x: 0 }; CHARACTER_N],
             game_score: 0,
+            seed,
             // evaluated_score: 0,
         }
     }
 
+    // 盤面を生成したシードを返す。同じシードでnewを呼ぶと同じ盤面を再現できる
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // (y, x)のマスにいるキャラクターを表す文字を返す。いない場合はNone
+    // 1体だけならそのID、2体以上が重なっている場合は'*'とする。IDが10以上の場合は'@'とする
+    fn character_glyph(&self, y: usize, x: usize) -> Option<char> {
+        let mut ids = self.characters.iter().enumerate()
+            .filter(|(_, character)| character.y == y && character.x == x)
+            .map(|(id, _)| id);
+        match (ids.next(), ids.next()) {
+            (None, _) => None,
+            (Some(id), None) => Some(char::from_digit(id as u32, 10).unwrap_or('@')),
+            (Some(_), Some(_)) => Some('*'),
+        }
+    }
+
     // 指定位置に指定キャラクターを配置する。
     fn set_character(&mut self, character_id: usize, y: usize, x: usize) {
         self.characters[character_id].y = y;
@@ -96,72 +117,33 @@ impl AutoMoveMazeState {
     }
 
     // 指定キャラクターを移動させる。
+    // 移動先のポイントが同じ場合は、MOVE_PRIORITYで先にある方向(右、左、下、上の順)に進む。
     fn move_player(&mut self, character_id: usize) {
         let character = &mut self.characters[character_id];
         let mut best_point: ScoreType = -INF;
-
-        // 盤面の範囲内となるような移動先を取得する。
-        let mut legal_action = Vec::with_capacity(4);
-        for action in 0..4 {
-            let ty = character.y as isize + dy[action];
-            let tx = character.x as isize + dx[action];
-
-            if ty >= 0 && ty < H as isize && tx >= 0 && tx < W as isize {
-                legal_action.push(action);
+        let mut best_coord = *character;
+
+        // 盤面の範囲内となる移動先を優先順に調べる。
+        // 同値のときに先に調べた方向を残すため、ポイントが真に大きい場合だけ更新する。
+        for (dy, dx) in MOVE_PRIORITY {
+            let ty = character.y as isize + dy;
+            let tx = character.x as isize + dx;
+            if ty < 0 || ty >= H as isize || tx < 0 || tx >= W as isize {
+                continue;
             }
-        }
-
-        let mut best_action_index = legal_action[0];
-
-        for action in legal_action {
-            let ty = character.y as isize + dy[action];
-            let tx = character.x as isize + dx[action];
 
             let point = self.points[ty as usize][tx as usize] as ScoreType;
-
             if point > best_point {
                 best_point = point;
-                best_action_index = action;
+                best_coord = Coord { y: ty as usize, x: tx as usize };
             }
         }
 
-        character.y = (character.y as isize + dy[best_action_index]) as usize;
-        character.x = (character.x as isize + dx[best_action_index]) as usize;
-    }
-
-    // 現在のゲーム状況を文字列にする
-    fn to_string(&self) -> String {
-        let mut s = format!("turn:\t{}\n", self.turn);
-        s += &format!("score:\t{}\n", self.game_score);
-
-        for h in 0..H {
-            for w in 0..W {
-                let mut is_written = false;
-
-                for character in &self.characters {
-                    if character.y == h && character.x == w {
-                        s += "@";
-                        is_written = true;
-                        break;
-                    }
-                }
-
-                if !is_written {
-                    if self.points[h][w] > 0 {
-                        s += &self.points[h][w].to_string();
-                    } else {
-                        s += ".";
-                    }
-                }
-            }
-            s += "\n";
-        }
-
-        s
+        *character = best_coord;
     }
 
     // スコア計算をする。(toStringを実装しない場合は引数is_printとそれの不随する処理は不要)
-    fn get_score(&mut self, is_print: bool) -> ScoreType {
+    fn get_score(&self, is_print: bool) -> ScoreType {
         let mut tmp_state = self.clone();
 
         for character in &self.characters {
@@ -172,7 +154,7 @@ impl AutoMoveMazeState {
         while !tmp_state.is_done() {
             tmp_state.advance();
             if is_print {
-                println!("{}", tmp_state.to_string());
+                println!("{}", tmp_state);
             }
         }
 
@@ -198,11 +180,70 @@ impl AutoMoveMazeState {
     }
 }
 
-#[allow(non_upper_case_globals)]
-const dy: [isize; 4] = [0, 0, 1, -1];
+// 現在のゲーム状況を文字列にする
+// キャラクターはIDの数字で表し、複数のキャラクターが同じマスに重なっている場合は*で表す
+// ポイントの数字と区別できるように、キャラクターの位置も一覧で表示する
+impl fmt::Display for AutoMoveMazeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "turn:\t{}", self.turn)?;
+        writeln!(f, "score:\t{}", self.game_score)?;
+        write!(f, "characters:")?;
+        for (id, character) in self.characters.iter().enumerate() {
+            write!(f, "\t{}({}, {})", id, character.y, character.x)?;
+        }
+        writeln!(f)?;
+
+        for h in 0..H {
+            for w in 0..W {
+                if let Some(glyph) = self.character_glyph(h, w) {
+                    write!(f, "{}", glyph)?;
+                } else if self.points[h][w] > 0 {
+                    write!(f, "{}", self.points[h][w])?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// 共通インターフェースから探索できるようにする
+// 行動はキャラクターの配置(キャラクターID, 配置先の座標)とする
+impl Game for AutoMoveMazeState {
+    type Action = (usize, Coord);
+
+    fn is_done(&self) -> bool {
+        AutoMoveMazeState::is_done(self)
+    }
+    fn legal_actions(&self) -> Vec<(usize, Coord)> {
+        let mut actions = Vec::with_capacity(CHARACTER_N * H * W);
+        for character_id in 0..CHARACTER_N {
+            for y in 0..H {
+                for x in 0..W {
+                    actions.push((character_id, Coord { y, x }));
+                }
+            }
+        }
+        actions
+    }
+    fn advance(&mut self, (character_id, coord): (usize, Coord)) {
+        self.set_character(character_id, coord.y, coord.x);
+    }
+    fn evaluate(&self) -> i64 {
+        self.get_score(false)
+    }
+}
 
-#[allow(non_upper_case_globals)]
-const dx: [isize; 4] = [1, -1, 0, 0];
+// キャラクターの移動量(dy, dx)。移動先のポイントが同じ場合は、この並びで先にある方向を優先する
+const MOVE_PRIORITY: [(isize, isize); 4] = [
+    (0, 1),   // 右
+    (0, -1),  // 左
+    (1, 0),   // 下
+    (-1, 0),  // 上
+];
 
 fn hill_climb(state: &AutoMoveMazeState, number: isize) -> AutoMoveMazeState {
     let mut now_state = state.clone();
@@ -229,12 +270,12 @@ struct StringAIPair {
 }
 
 // ゲームを1回プレイしてゲーム状況を表示する
-fn play_game(ai: &StringAIPair, seed: usize) {
+fn play_game(ai: &StringAIPair, seed: u64) {
     let mut state = AutoMoveMazeState::new(Some(seed));
     state = (ai.ai)(&state);
-    println!("{}", state.to_string());
+    println!("{}", state);
     let score = state.get_score(true);
-    println!("Score of {}: {}", ai.name, score);
+    println!("Score of {}(seed {}): {}", ai.name, state.seed(), score);
 }
 
 #[allow(dead_code)]
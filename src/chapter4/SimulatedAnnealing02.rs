@@ -1,39 +1,29 @@
-#![allow(non_snake_case)]
+#![allow(non_snake_case, clippy::needless_range_loop)]
 
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use rand::{Rng, SeedableRng, rngs};
+use std::collections::VecDeque;
+use std::fmt;
 
-use rand::{Rng, SeedableRng, rngs, thread_rng};
+use crate::common::game::Game;
+use crate::common::rng::{make_rng, resolve_seed};
+use crate::common::stats::ScoreSummary;
 
 const H: usize = 5;        // 迷路の高さ
 const W: usize = 5;        // 迷路の幅
 const END_TURN: usize = 5;  // ゲーム終了ターン
-const CHARACTER_N: usize = 3; // キャラクターの数
+const CHARACTER_N: usize = 3; // 標準のキャラクターの数
 
 type ScoreType = i64;
 const INF: ScoreType = 1000000000;
-
-
-// グローバルな乱数生成器
-static RNG:  Lazy<Mutex<rngs::StdRng>> = Lazy::new(|| {
-    // ここにシード値を指定して初期化する
-    let seed = 42; // 任意のシード値を指定
-    let rng = rand::rngs::StdRng::seed_from_u64(seed as u64);
-    Mutex::new(rng)
-});
-static RNG_FOR_ANMEAL:  Lazy<Mutex<rngs::StdRng>> = Lazy::new(|| {
-    // ここにシード値を指定して初期化する
-    let seed = 41; // 任意のシード値を指定
-    let rng = rand::rngs::StdRng::seed_from_u64(seed as u64);
-    Mutex::new(rng)
-});
+// 探索に使う乱数生成器のシード値
+const SEARCH_SEED: u64 = 42;
 
 
 // 座標を保持する
-#[derive(Clone, Copy)]
-struct Coord {
-    y: usize,
-    x: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coord {
+    pub y: usize,
+    pub x: usize,
 }
 
 // 自動一人ゲームの例
@@ -45,22 +35,23 @@ struct Coord {
 // ゲームに介入できる要素として、初期状態でのキャラクターをどこに配置するかを選択できる。
 // どのようにキャラクターを配置すると最終スコアが高くなるかを考えるゲーム。
 # [derive (Clone)]
-struct AutoMoveMazeState {
+pub struct AutoMoveMazeState {
     points: [[usize; W]; H], // 床のポイントを1~9で表現する
     turn: usize,             // 現在のターン
-    characters: [Coord; CHARACTER_N], // CHARACTER_N体のキャラクター
+    characters: Vec<Coord>,  // キャラクターの位置。キャラクターの数はこの長さで決まる
     game_score: usize,       // ゲーム上で実際に得たスコア
-    // evaluated_score: ScoreType, // 探索上で評価したスコア
+    seed: u64,               // 盤面を生成したシード
+    evaluated_score: ScoreType, // 探索上で評価したスコア
+    score_start_cell: bool,  // trueの場合はキャラクターを置いたマスのポイントもスコアに加える
+    walls: [[bool; W]; H],   // キャラクターが移動できないマス
+    collision: bool,         // trueの場合はキャラクター同士が同じマスに移動しない
 }
 
 impl AutoMoveMazeState {
-    // h*wの迷路を生成する。
-    fn new(seed: Option<usize>) -> Self {
-
-        let mut rng: rngs::StdRng = SeedableRng::seed_from_u64(thread_rng().gen());
-        if let Some(s) = seed  {
-            rng = SeedableRng::seed_from_u64(s as u64)
-        }
+    // h*wの迷路を生成し、character_n体のキャラクターを置く。
+    pub fn new(seed: Option<u64>, character_n: usize) -> Self {
+        let seed = resolve_seed(seed);
+        let mut rng = make_rng(seed);
         let mut points = [[0; W]; H];
         for y in 0..H {
             for x in 0..W {
@@ -71,27 +62,75 @@ impl AutoMoveMazeState {
         AutoMoveMazeState {
             points,
             turn: 0,
-            characters: [Coord { y: 0, x: 0 }; CHARACTER_N],
+            characters: vec![Coord { y: 0, x: 0 }; character_n],
             game_score: 0,
-            // evaluated_score: 0,
+            seed,
+            evaluated_score: 0,
+            score_start_cell: false,
+            walls: [[false; W]; H],
+            collision: false,
+        }
+    }
+
+    // キャラクターの数を返す
+    pub fn character_n(&self) -> usize {
+        self.characters.len()
+    }
+
+    // 盤面を生成したシードを返す。同じシードでnewを呼ぶと同じ盤面を再現できる
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // (y, x)のマスにいるキャラクターを表す文字を返す。いない場合はNone
+    // 1体だけならそのID、2体以上が重なっている場合は'*'とする。IDが10以上の場合は'@'とする
+    fn character_glyph(&self, y: usize, x: usize) -> Option<char> {
+        let mut ids = self.characters.iter().enumerate()
+            .filter(|(_, character)| character.y == y && character.x == x)
+            .map(|(id, _)| id);
+        match (ids.next(), ids.next()) {
+            (None, _) => None,
+            (Some(id), None) => Some(char::from_digit(id as u32, 10).unwrap_or('@')),
+            (Some(_), Some(_)) => Some('*'),
         }
     }
 
+    // キャラクターを置いたマスのポイントをスコアに数えるかを設定する。標準では数えない
+    pub fn set_score_start_cell(&mut self, score_start_cell: bool) {
+        self.score_start_cell = score_start_cell;
+    }
+
+    // (y, x)のマスを壁にする。壁のマスにはポイントがなく、キャラクターは壁に移動しない
+    // 壁のマスに配置したキャラクターはポイントを得られず、次のターンに壁でないマスへ移動する
+    pub fn set_wall(&mut self, y: usize, x: usize) {
+        self.walls[y][x] = true;
+        self.points[y][x] = 0;
+    }
+
+    // キャラクター同士の衝突を考えるかを設定する。標準では考えず、同じマスに複数のキャラクターが重なれる
+    // 考える場合、同じターンにIDの小さいキャラクターが移動先に決めたマスには移動しない
+    pub fn set_collision(&mut self, collision: bool) {
+        self.collision = collision;
+    }
+
     // 指定位置に指定キャラクターを配置する。
-    fn set_character(&mut self, character_id: usize, y: usize, x: usize) {
+    pub fn set_character(&mut self, character_id: usize, y: usize, x: usize) {
         self.characters[character_id].y = y;
         self.characters[character_id].x = x;
     }
 
     // ゲームの終了判定
-    fn is_done(&self) -> bool {
+    pub fn is_done(&self) -> bool {
         self.turn == END_TURN
     }
 
     // ゲームを1ターン進める。
-    fn advance(&mut self) {
-        for character_id in 0..CHARACTER_N {
-            self.move_player(character_id);
+    // キャラクターはIDの小さい順に移動先を決める。
+    pub fn advance(&mut self) {
+        let mut targets = Vec::with_capacity(self.character_n());
+        for character_id in 0..self.character_n() {
+            self.move_player(character_id, &targets);
+            targets.push(self.characters[character_id]);
         }
         for character in &self.characters {
             let point = &mut self.points[character.y][character.x];
@@ -102,123 +141,221 @@ impl AutoMoveMazeState {
     }
 
     // 指定キャラクターを移動させる。
-    fn move_player(&mut self, character_id: usize) {
-        let character = &mut self.characters[character_id];
+    // 移動先のポイントが同じ場合は、MOVE_PRIORITYで先にある方向(右、左、下、上の順)に進む。
+    // targetsはこのターンにIDの小さいキャラクターが決めた移動先で、衝突を考える場合はそこへ移動しない
+    // 移動できるマスがない場合はその場に留まる。
+    fn move_player(&mut self, character_id: usize, targets: &[Coord]) {
+        let character = self.characters[character_id];
         let mut best_point: ScoreType = -INF;
+        let mut best_coord = character;
 
-        // 盤面の範囲内となるような移動先を取得する。
-        let mut legal_action = Vec::with_capacity(4);
-        for action in 0..4 {
-            let ty = character.y as isize + dy[action];
-            let tx = character.x as isize + dx[action];
-
-            if ty >= 0 && ty < H as isize && tx >= 0 && tx < W as isize {
-                legal_action.push(action);
+        // 盤面の範囲内で壁でない移動先を優先順に調べる。
+        // 同値のときに先に調べた方向を残すため、ポイントが真に大きい場合だけ更新する。
+        for (dy, dx) in MOVE_PRIORITY {
+            let ty = character.y as isize + dy;
+            let tx = character.x as isize + dx;
+            if ty < 0 || ty >= H as isize || tx < 0 || tx >= W as isize {
+                continue;
+            }
+            let (ty, tx) = (ty as usize, tx as usize);
+            if self.walls[ty][tx] {
+                continue;
+            }
+            if self.collision && targets.iter().any(|target| target.y == ty && target.x == tx) {
+                continue;
             }
-        }
-
-        let mut best_action_index = legal_action[0];
-
-        for action in legal_action {
-            let ty = character.y as isize + dy[action];
-            let tx = character.x as isize + dx[action];
-
-            let point = self.points[ty as usize][tx as usize] as ScoreType;
 
+            let point = self.points[ty][tx] as ScoreType;
             if point > best_point {
                 best_point = point;
-                best_action_index = action;
+                best_coord = Coord { y: ty, x: tx };
             }
         }
 
-        character.y = (character.y as isize + dy[best_action_index]) as usize;
-        character.x = (character.x as isize + dx[best_action_index]) as usize;
+        self.characters[character_id] = best_coord;
     }
 
-    // 現在のゲーム状況を文字列にする
-    fn to_string(&self) -> String {
-        let mut s = format!("turn:\t{}\n", self.turn);
-        s += &format!("score:\t{}\n", self.game_score);
-
-        for h in 0..H {
-            for w in 0..W {
-                let mut is_written = false;
-
-                for character in &self.characters {
-                    if character.y == h && character.x == w {
-                        s += "@";
-                        is_written = true;
-                        break;
-                    }
-                }
+    // スコア計算をする。(toStringを実装しない場合は引数is_printとそれの不随する処理は不要)
+    pub fn get_score(&self, is_print: bool) -> ScoreType {
+        let mut tmp_state = self.clone();
+        tmp_state.take_start_cells();
 
-                if !is_written {
-                    if self.points[h][w] > 0 {
-                        s += &self.points[h][w].to_string();
-                    } else {
-                        s += ".";
-                    }
-                }
+        while !tmp_state.is_done() {
+            tmp_state.advance();
+            if is_print {
+                println!("{}", tmp_state);
             }
-            s += "\n";
         }
 
-        s
+        tmp_state.game_score as ScoreType
     }
 
-    // スコア計算をする。(toStringを実装しない場合は引数is_printとそれの不随する処理は不要)
-    fn get_score(&mut self, is_print: bool) -> ScoreType {
+    // get_scoreと同じようにゲームを最後まで進め、各ターンを終えた時点のgame_scoreを順に返す
+    // 返り値の長さはEND_TURNで、最後の値はget_scoreのスコアと等しい
+    pub fn score_timeline(&self) -> Vec<usize> {
         let mut tmp_state = self.clone();
+        tmp_state.take_start_cells();
 
-        for character in &self.characters {
-            let point = &mut tmp_state.points[character.y][character.x];
-            *point = 0;
-        }
-
+        let mut timeline = Vec::with_capacity(END_TURN);
         while !tmp_state.is_done() {
             tmp_state.advance();
-            if is_print {
-                println!("{}", tmp_state.to_string());
+            timeline.push(tmp_state.game_score);
+        }
+
+        timeline
+    }
+
+    // ゲームを始める前に、キャラクターを置いたマスのポイントを取り除く
+    // score_start_cellがtrueの場合は取り除いたポイントをスコアに加える。複数のキャラクターが重なっていても1回だけ数える
+    fn take_start_cells(&mut self) {
+        for character_id in 0..self.character_n() {
+            let character = self.characters[character_id];
+            let point = &mut self.points[character.y][character.x];
+            if self.score_start_cell {
+                self.game_score += *point;
             }
+            *point = 0;
         }
+    }
 
-        tmp_state.game_score as ScoreType
+    // 探索用の評価として、配置済みのキャラクターだけでゲームを最後まで進めたスコアを記録する
+    fn evaluate_score(&mut self) {
+        self.evaluated_score = self.get_score(false);
     }
 
     // 初期化する
-    fn init(&mut self){
-        for character_id in 0..CHARACTER_N {
-            let y = RNG.lock().unwrap().gen_range(0..H);
-            let x = RNG.lock().unwrap().gen_range(0..W);
+    fn init(&mut self, rng: &mut impl Rng){
+        for character_id in 0..self.character_n() {
+            let y = rng.gen_range(0..H);
+            let x = rng.gen_range(0..W);
 
             self.set_character(character_id, y, x);
         }
     }
 
-    // 状態遷移する
-    fn transition(&mut self){
-        let character_id = RNG.lock().unwrap().gen_range(0..CHARACTER_N);
-        let character = &mut self.characters[character_id];
-        character.y = RNG.lock().unwrap().gen_range(0..H);
-        character.x = RNG.lock().unwrap().gen_range(0..W);
+    // 状態遷移する。neighborで近傍のつくり方を選ぶ
+    fn transition(&mut self, neighbor: NeighborMode, rng: &mut impl Rng){
+        match neighbor {
+            NeighborMode::RandomTeleport => {
+                let character_id = rng.gen_range(0..self.character_n());
+                let character = &mut self.characters[character_id];
+                character.y = rng.gen_range(0..H);
+                character.x = rng.gen_range(0..W);
+            }
+            NeighborMode::AdjacentStep => {
+                let character_id = rng.gen_range(0..self.character_n());
+                let character = &mut self.characters[character_id];
+                let neighbors: Vec<Coord> = MOVE_PRIORITY
+                    .iter()
+                    .map(|(dy, dx)| (character.y as isize + dy, character.x as isize + dx))
+                    .filter(|&(ty, tx)| ty >= 0 && ty < H as isize && tx >= 0 && tx < W as isize)
+                    .map(|(ty, tx)| Coord { y: ty as usize, x: tx as usize })
+                    .collect();
+                if !neighbors.is_empty() {
+                    *character = neighbors[rng.gen_range(0..neighbors.len())];
+                }
+            }
+            NeighborMode::SwapTwoCharacters => {
+                if self.character_n() < 2 {
+                    return;
+                }
+                let i = rng.gen_range(0..self.character_n());
+                let mut j = rng.gen_range(0..self.character_n() - 1);
+                if j >= i {
+                    j += 1;
+                }
+                self.characters.swap(i, j);
+            }
+        }
     }
 }
 
-#[allow(non_upper_case_globals)]
-const dy: [isize; 4] = [0, 0, 1, -1];
+// 現在のゲーム状況を文字列にする
+// キャラクターはIDの数字で表し、複数のキャラクターが同じマスに重なっている場合は*で表す
+// ポイントの数字と区別できるように、キャラクターの位置も一覧で表示する
+impl fmt::Display for AutoMoveMazeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "turn:\t{}", self.turn)?;
+        writeln!(f, "score:\t{}", self.game_score)?;
+        write!(f, "characters:")?;
+        for (id, character) in self.characters.iter().enumerate() {
+            write!(f, "\t{}({}, {})", id, character.y, character.x)?;
+        }
+        writeln!(f)?;
 
-#[allow(non_upper_case_globals)]
-const dx: [isize; 4] = [1, -1, 0, 0];
+        for h in 0..H {
+            for w in 0..W {
+                if let Some(glyph) = self.character_glyph(h, w) {
+                    write!(f, "{}", glyph)?;
+                } else if self.walls[h][w] {
+                    write!(f, "#")?;
+                } else if self.points[h][w] > 0 {
+                    write!(f, "{}", self.points[h][w])?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
 
-type AIFunction = fn(&AutoMoveMazeState) -> AutoMoveMazeState;
+// 共通インターフェースから探索できるようにする
+// 行動はキャラクターの配置(キャラクターID, 配置先の座標)とする
+impl Game for AutoMoveMazeState {
+    type Action = (usize, Coord);
 
-fn hill_climb(state: &AutoMoveMazeState, number: isize) -> AutoMoveMazeState {
+    fn is_done(&self) -> bool {
+        AutoMoveMazeState::is_done(self)
+    }
+    fn legal_actions(&self) -> Vec<(usize, Coord)> {
+        let mut actions = Vec::with_capacity(self.character_n() * H * W);
+        for character_id in 0..self.character_n() {
+            for y in 0..H {
+                for x in 0..W {
+                    actions.push((character_id, Coord { y, x }));
+                }
+            }
+        }
+        actions
+    }
+    fn advance(&mut self, (character_id, coord): (usize, Coord)) {
+        self.set_character(character_id, coord.y, coord.x);
+    }
+    fn evaluate(&self) -> i64 {
+        self.get_score(false)
+    }
+}
+
+// キャラクターの移動量(dy, dx)。移動先のポイントが同じ場合は、この並びで先にある方向を優先する
+const MOVE_PRIORITY: [(isize, isize); 4] = [
+    (0, 1),   // 右
+    (0, -1),  // 左
+    (1, 0),   // 下
+    (-1, 0),  // 上
+];
+
+// 山登り法や焼きなまし法で、現在の配置から次の配置をつくる方法
+// 衝突を考えない場合、キャラクターは入れ替えても同じ動きをするので、SwapTwoCharactersではスコアは変わらない。
+// 衝突を考える場合はIDの小さいキャラクターから移動先を選ぶので、入れ替えるとスコアが変わることがある
+#[derive(Debug, Clone, Copy)]
+pub enum NeighborMode {
+    RandomTeleport,     // 1体を盤面のランダムなマスに移す
+    AdjacentStep,       // 1体を上下左右に隣接するマスに移す
+    SwapTwoCharacters,  // 2体の位置を入れ替える
+}
+
+type AIFunction = fn(&AutoMoveMazeState, &mut rngs::StdRng) -> AutoMoveMazeState;
+
+pub fn hill_climb(state: &AutoMoveMazeState, number: isize, neighbor: NeighborMode, rng: &mut impl Rng) -> AutoMoveMazeState {
     let mut now_state = state.clone();
-    now_state.init();
+    now_state.init(rng);
     let mut best_score: ScoreType = now_state.get_score(false);
     for _ in 0..number {
         let mut next_state = now_state.clone();
-        next_state.transition();
+        next_state.transition(neighbor, rng);
         let next_score: ScoreType = next_state.get_score(false);
         if next_score > best_score {
             best_score = next_score;
@@ -229,26 +366,71 @@ fn hill_climb(state: &AutoMoveMazeState, number: isize) -> AutoMoveMazeState {
     now_state
 }
 
+// 初期配置を変えながら山登り法をrestarts回行い、最もスコアの高い配置を返す
+pub fn hill_climb_with_restarts(state: &AutoMoveMazeState, iters_per_restart: isize, restarts: usize, neighbor: NeighborMode, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let mut best_state = state.clone();
+    let mut best_score: ScoreType = -INF;
+    for _ in 0..restarts {
+        let now_state = hill_climb(state, iters_per_restart, neighbor, rng);  // hill_climbの中で毎回init()し直す
+        let now_score = now_state.get_score(false);
+        if now_score > best_score {
+            best_score = now_score;
+            best_state = now_state;
+        }
+    }
+
+    best_state
+}
+
+
+// 焼きなまし法で温度を下げていく方法
+#[derive(Debug, Clone, Copy)]
+pub enum CoolingSchedule {
+    Linear,       // 一定の幅で下げる
+    Exponential,  // 一定の比率で下げる
+    Logarithmic,  // 序盤に大きく下げ、後半はゆっくり下げる
+}
+
+impl CoolingSchedule {
+    // number回の遷移のうちi回目の温度を求める
+    // i = 0 のときstart_temp、i = number - 1 のときend_tempとなる。numberが1以下の場合はstart_tempのままとする
+    fn temperature(self, start_temp: f64, end_temp: f64, i: usize, number: usize) -> f64 {
+        if number <= 1 {
+            return start_temp;
+        }
+        let progress = i as f64 / (number - 1) as f64;
+        match self {
+            CoolingSchedule::Linear => start_temp + (end_temp - start_temp) * progress,
+            CoolingSchedule::Exponential => start_temp * (end_temp / start_temp).powf(progress),
+            CoolingSchedule::Logarithmic => {
+                start_temp + (end_temp - start_temp) * (1.0 + i as f64).ln() / (number as f64).ln()
+            }
+        }
+    }
+}
+
+// 焼きなまし法で、スコアがnow_scoreの配置からnext_scoreの配置に温度tempで遷移するかを決める
+// スコアを最大化するので、悪化した分だけ遷移確率を下げる。改善する場合は確率1で遷移する
+fn accepts_transition(now_score: ScoreType, next_score: ScoreType, temp: f64, rng: &mut impl Rng) -> bool {
+    let probability = ((next_score - now_score) as f64 / temp).exp().min(1.0); // 確率probで遷移する
+    let is_force_next = probability > rng.gen_range(0.0..1.0);
+    next_score > now_score || is_force_next
+}
 
-fn simulated_annealing(state: &AutoMoveMazeState, number: usize, start_temp: f64, end_temp: f64) -> AutoMoveMazeState {
+pub fn simulated_annealing(state: &AutoMoveMazeState, number: usize, start_temp: f64, end_temp: f64, schedule: CoolingSchedule, neighbor: NeighborMode, rng: &mut impl Rng) -> AutoMoveMazeState {
     let mut now_state = state.clone();
-    now_state.init();
+    now_state.init(rng);
     let mut best_score = now_state.get_score(false) as ScoreType;
     let mut now_score = best_score as ScoreType;
     let mut best_state = now_state.clone();
 
-    let mut rng = RNG_FOR_ANMEAL.lock().unwrap();
-
     for i in 0..number {
         let mut next_state = now_state.clone();
-        next_state.transition();
+        next_state.transition(neighbor, rng);
         let next_score = next_state.get_score(false);
 
-        let temp = start_temp + (end_temp - start_temp) * (i as f64 / number as f64);
-        let probability = (-(next_score as f64 - now_score as f64) / temp).exp(); // 確率probで遷移する
-
-        let is_force_next = probability > rng.gen_range(0.0..1.0);
-        if next_score > now_score || is_force_next {
+        let temp = schedule.temperature(start_temp, end_temp, i, number);
+        if accepts_transition(now_score, next_score, temp, rng) {
             now_score = next_score;
             now_state = next_state.clone();
         }
@@ -262,6 +444,346 @@ fn simulated_annealing(state: &AutoMoveMazeState, number: usize, start_temp: f64
     best_state
 }
 
+// 再加熱つきの焼きなまし法
+// ベストスコアがstall_limit回続けて更新されない場合は局所解に嵌まったとみなし、温度をstart_tempに戻す。
+// 戻した後は残りの遷移回数でend_tempまで線形に下げ直す
+pub fn simulated_annealing_with_reheat(state: &AutoMoveMazeState, number: usize, start_temp: f64, end_temp: f64, stall_limit: usize, neighbor: NeighborMode, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let mut now_state = state.clone();
+    now_state.init(rng);
+    let mut best_score = now_state.get_score(false);
+    let mut now_score = best_score;
+    let mut best_state = now_state.clone();
+
+    let mut reheat = Reheat::new(stall_limit);
+
+    for i in 0..number {
+        let temp = reheat.temperature(start_temp, end_temp, i, number);
+
+        let mut next_state = now_state.clone();
+        next_state.transition(neighbor, rng);
+        let next_score = next_state.get_score(false);
+
+        if accepts_transition(now_score, next_score, temp, rng) {
+            now_score = next_score;
+            now_state = next_state.clone();
+        }
+
+        let improved = next_score > best_score;
+        if improved {
+            best_score = next_score;
+            best_state = next_state.clone();
+        }
+        reheat.record(improved);
+    }
+
+    best_state
+}
+
+// simulated_annealing_with_reheatの温度を決める
+struct Reheat {
+    stall_limit: usize,
+    phase_start: usize, // 最後に温度をstart_tempに戻した遷移回数
+    stall: usize,       // ベストスコアが更新されていない遷移回数
+}
+
+impl Reheat {
+    fn new(stall_limit: usize) -> Self {
+        Reheat { stall_limit, phase_start: 0, stall: 0 }
+    }
+
+    // i回目の遷移の温度を返す。stall_limitを超えて更新がない場合は、ここで温度をstart_tempに戻す
+    fn temperature(&mut self, start_temp: f64, end_temp: f64, i: usize, number: usize) -> f64 {
+        if self.stall > self.stall_limit {
+            self.phase_start = i;
+            self.stall = 0;
+        }
+        CoolingSchedule::Linear.temperature(start_temp, end_temp, i - self.phase_start, number - self.phase_start)
+    }
+
+    // 遷移でベストスコアを更新したかを記録する
+    fn record(&mut self, improved: bool) {
+        if improved {
+            self.stall = 0;
+        } else {
+            self.stall += 1;
+        }
+    }
+}
+
+// 焼きなまし法の開始温度と終了温度をランダムな遷移から推定する
+// 悪化する遷移の平均的な悪化幅dに対して、exp(-d / temp)が
+// 開始時に約80%、終了時に約1%となる温度を返す
+fn auto_tune_temps(state: &AutoMoveMazeState, samples: usize, rng: &mut impl Rng) -> (f64, f64) {
+    const START_ACCEPT: f64 = 0.8;
+    const END_ACCEPT: f64 = 0.01;
+
+    let mut worsening_sum = 0.0;
+    let mut worsening_count = 0;
+    for _ in 0..samples {
+        let mut now_state = state.clone();
+        now_state.init(rng);
+        let now_score = now_state.get_score(false);
+        let mut next_state = now_state.clone();
+        next_state.transition(NeighborMode::RandomTeleport, rng);
+        let delta = next_state.get_score(false) - now_score;
+        if delta < 0 {
+            worsening_sum += -delta as f64;
+            worsening_count += 1;
+        }
+    }
+
+    // 悪化する遷移が見つからない盤面ではどの温度でも同じなので1.0とする
+    if worsening_count == 0 {
+        return (1.0, 1.0);
+    }
+    let average_worsening = worsening_sum / worsening_count as f64;
+    (
+        -average_worsening / START_ACCEPT.ln(),
+        -average_worsening / END_ACCEPT.ln(),
+    )
+}
+
+// 遅延受理山登り法(Late Acceptance Hill Climbing)で配置を決める
+// 遷移後のスコアが、現在のスコアかhistory_len回前のスコアより高ければ遷移する。
+// 過去のスコアを基準にすることで一時的な悪化を許し、局所解から抜け出せる。history_len = 1 の場合は山登り法と同じになる。
+pub fn late_acceptance_hill_climb(state: &AutoMoveMazeState, iters: usize, history_len: usize, neighbor: NeighborMode, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let mut now_state = state.clone();
+    now_state.init(rng);
+    let mut now_score = now_state.get_score(false);
+    let mut best_score = now_score;
+    let mut best_state = now_state.clone();
+    // 過去history_len回のスコアを循環バッファに記録する
+    let mut history = vec![now_score; history_len.max(1)];
+
+    for i in 0..iters {
+        let mut next_state = now_state.clone();
+        next_state.transition(neighbor, rng);
+        let next_score = next_state.get_score(false);
+
+        let v = i % history.len();
+        if next_score > history[v] || next_score > now_score {
+            now_score = next_score;
+            now_state = next_state;
+        }
+        history[v] = now_score;
+
+        if now_score > best_score {
+            best_score = now_score;
+            best_state = now_state.clone();
+        }
+    }
+
+    best_state
+}
+
+// タブーサーチで配置を決める
+// 1体のキャラクターを別のマスに移す近傍を全て調べ、タブーでない中で最もスコアの高い近傍に必ず移る。
+// スコアが下がる近傍にも移るので局所解から抜け出せる。移したキャラクターの元の位置をtabu_tenure回の間タブーとし、すぐに戻らないようにする。
+// ただし、それまでの最良のスコアを超える近傍はタブーでも移る。
+pub fn tabu_search(state: &AutoMoveMazeState, iters: usize, tabu_tenure: usize, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let mut now_state = state.clone();
+    now_state.init(rng);
+    let mut best_score = now_state.get_score(false);
+    let mut best_state = now_state.clone();
+    let mut tabu_list: VecDeque<(usize, usize, usize)> = VecDeque::with_capacity(tabu_tenure + 1);  // (character_id, y, x)
+
+    for _ in 0..iters {
+        let mut best_neighbor: Option<(ScoreType, usize, usize, usize)> = None;
+        for character_id in 0..now_state.character_n() {
+            let Coord { y: now_y, x: now_x } = now_state.characters[character_id];
+            for y in 0..H {
+                for x in 0..W {
+                    if (y, x) == (now_y, now_x) {
+                        continue;
+                    }
+                    let mut next_state = now_state.clone();
+                    next_state.set_character(character_id, y, x);
+                    let next_score = next_state.get_score(false);
+                    let is_tabu = tabu_list.contains(&(character_id, y, x));
+                    if is_tabu && next_score <= best_score {
+                        continue;
+                    }
+                    if best_neighbor.is_none_or(|(score, _, _, _)| next_score > score) {
+                        best_neighbor = Some((next_score, character_id, y, x));
+                    }
+                }
+            }
+        }
+
+        // 全ての近傍がタブーの場合は終了する
+        let Some((next_score, character_id, y, x)) = best_neighbor else {
+            break;
+        };
+        let Coord { y: old_y, x: old_x } = now_state.characters[character_id];
+        tabu_list.push_back((character_id, old_y, old_x));
+        if tabu_list.len() > tabu_tenure {
+            tabu_list.pop_front();
+        }
+        now_state.set_character(character_id, y, x);
+
+        if next_score > best_score {
+            best_score = next_score;
+            best_state = now_state.clone();
+        }
+    }
+
+    best_state
+}
+
+// キャラクターを1体ずつ置いていくビームサーチで配置を決める
+// 1体置くごとに、それまでに置いたキャラクターだけで評価したスコアの上位beam_width個の配置を残す
+// beam_widthが0の場合は1として扱う
+pub fn beam_search_placement(state: &AutoMoveMazeState, beam_width: usize) -> AutoMoveMazeState {
+    let beam_width = beam_width.max(1);
+    let mut empty_state = state.clone();
+    empty_state.characters.clear();
+    let mut now_beam = vec![empty_state];
+
+    for _ in 0..state.character_n() {
+        let mut next_beam = Vec::with_capacity(now_beam.len() * H * W);
+        for now_state in &now_beam {
+            for y in 0..H {
+                for x in 0..W {
+                    let mut next_state = now_state.clone();
+                    next_state.characters.push(Coord { y, x });
+                    next_state.evaluate_score();
+                    next_beam.push(next_state);
+                }
+            }
+        }
+        // 評価が同じ場合は先に追加した配置を優先する
+        next_beam.sort_by_key(|next_state| std::cmp::Reverse(next_state.evaluated_score));
+        next_beam.truncate(beam_width);
+        now_beam = next_beam;
+    }
+
+    now_beam.swap_remove(0)
+}
+
+// enumerate_small_boardsで調べる配置の組み合わせ数の上限
+const ENUMERATE_LIMIT: u64 = 1_000_000;
+
+// キャラクターの配置を全て調べて、スコアが最も高い配置を返す。ヒューリスティックな探索の真の最適値として使う
+// キャラクターの動きは決定的なので、スコアは配置だけで決まる。
+// キャラクターを入れ替えてもスコアは変わらないため、マスの通し番号が昇順(重なりを含む)となる配置だけを調べる。
+// 組み合わせの数がENUMERATE_LIMITを超える大きさの場合はNoneを返す
+pub fn enumerate_small_boards(state: &AutoMoveMazeState) -> Option<AutoMoveMazeState> {
+    let cell_n = H * W;
+    // 重複を許してcharacter_n個のマスを選ぶ組み合わせの数
+    // 衝突を考える場合はIDによって移動先が変わるので、キャラクターごとに全てのマスを選ぶ並びの数とする
+    let mut combination_n: u64 = 1;
+    for i in 0..state.character_n() {
+        combination_n = if state.collision {
+            combination_n * cell_n as u64
+        } else {
+            combination_n * (cell_n + i) as u64 / (i + 1) as u64
+        };
+        if combination_n > ENUMERATE_LIMIT {
+            return None;
+        }
+    }
+
+    let mut cells = vec![0; state.character_n()];
+    let mut best_score = -INF;
+    let mut best_state = state.clone();
+    loop {
+        let mut now_state = state.clone();
+        for (character_id, &cell) in cells.iter().enumerate() {
+            now_state.set_character(character_id, cell / W, cell % W);
+        }
+        let score = now_state.get_score(false);
+        if score > best_score {
+            best_score = score;
+            best_state = now_state;
+        }
+
+        // 昇順を保ったまま次の組み合わせに進める。全て最後のマスになったら終わる
+        // 衝突を考える場合は昇順に限らず、全ての並びを順に調べる
+        let Some(i) = (0..cells.len()).rev().find(|&i| cells[i] < cell_n - 1) else {
+            break;
+        };
+        cells[i] += 1;
+        let reset = if state.collision { 0 } else { cells[i] };
+        for cell in &mut cells[i + 1..] {
+            *cell = reset;
+        }
+    }
+
+    Some(best_state)
+}
+
+// トーナメント選択で使う、1回の選択で比べる個体の数
+const TOURNAMENT_SIZE: usize = 3;
+
+// 遺伝的アルゴリズムの個体
+// キャラクターの配置を遺伝子とみなす
+#[derive(Clone)]
+struct Individual {
+    state: AutoMoveMazeState,
+    score: ScoreType,
+}
+
+impl Individual {
+    fn new(state: AutoMoveMazeState) -> Self {
+        let score = state.get_score(false);
+        Individual { state, score }
+    }
+}
+
+// 集団からランダムに選んだTOURNAMENT_SIZE個体のうち、最もスコアの高いものを選ぶ
+fn tournament_select<'a>(population: &'a [Individual], rng: &mut impl Rng) -> &'a Individual {
+    let mut best = &population[rng.gen_range(0..population.len())];
+    for _ in 1..TOURNAMENT_SIZE {
+        let candidate = &population[rng.gen_range(0..population.len())];
+        if candidate.score > best.score {
+            best = candidate;
+        }
+    }
+    best
+}
+
+// キャラクターごとにどちらかの親の配置を等確率で受け継ぎ、mutation_rateの確率でランダムな座標に置き直す
+fn crossover(parent1: &AutoMoveMazeState, parent2: &AutoMoveMazeState, mutation_rate: f64, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let mut child = parent1.clone();
+    for character_id in 0..child.character_n() {
+        let coord = if rng.gen_bool(0.5) { parent1.characters[character_id] } else { parent2.characters[character_id] };
+        child.set_character(character_id, coord.y, coord.x);
+        if rng.gen_bool(mutation_rate) {
+            child.set_character(character_id, rng.gen_range(0..H), rng.gen_range(0..W));
+        }
+    }
+    child
+}
+
+// 遺伝的アルゴリズムでキャラクターの配置を決める
+// 各世代で最もスコアの高い個体はそのまま次の世代に残す
+// 突然変異はキャラクターごとに独立に起こすので、1回に1体(入れ替えでは2体)だけを動かすNeighborModeは受け取らない
+// populationが0の場合は1として扱う。mutation_rateは確率なので0~1に丸め、NaNの場合は突然変異を起こさない
+pub fn genetic_algorithm(state: &AutoMoveMazeState, population: usize, generations: usize, mutation_rate: f64, rng: &mut impl Rng) -> AutoMoveMazeState {
+    let population = population.max(1);
+    let mutation_rate = if mutation_rate.is_nan() { 0.0 } else { mutation_rate.clamp(0.0, 1.0) };
+    let mut individuals: Vec<Individual> = (0..population)
+        .map(|_| {
+            let mut now_state = state.clone();
+            now_state.init(rng);
+            Individual::new(now_state)
+        })
+        .collect();
+
+    for _ in 0..generations {
+        let elite = individuals.iter().max_by_key(|individual| individual.score).unwrap().clone();
+        let mut next_individuals = Vec::with_capacity(population);
+        next_individuals.push(elite);
+        while next_individuals.len() < population {
+            let parent1 = tournament_select(&individuals, rng);
+            let parent2 = tournament_select(&individuals, rng);
+            next_individuals.push(Individual::new(crossover(&parent1.state, &parent2.state, mutation_rate, rng)));
+        }
+        individuals = next_individuals;
+    }
+
+    individuals.into_iter().max_by_key(|individual| individual.score).unwrap().state
+}
 
 struct StringAIPair {
     name: String,
@@ -269,28 +791,30 @@ struct StringAIPair {
 }
 
 // ゲームを1回プレイしてゲーム状況を表示する
-// fn play_game(ai: &StringAIPair, seed: usize) {
-//     let mut state = AutoMoveMazeState::new(Some(seed));
+// fn play_game(ai: &StringAIPair, seed: u64) {
+//     let mut state = AutoMoveMazeState::new(Some(seed), CHARACTER_N);
 //     state = (ai.ai)(&state);
-//     println!("{}", state.to_string());
+//     println!("{}", state);
 //     let score = state.get_score(true);
 //     println!("Score of {}: {}", ai.name, score);
 // }
 
-// ゲームをgame_number回プレイして平均スコアを表示する
-fn test_ai_score(ai: &StringAIPair, game_number: usize) {
-    let mut score_mean = 0.0;
+// キャラクターの数を指定してゲームをgame_number回プレイし、スコアの分布を表示する
+// 探索に使う乱数生成器は呼び出し側で用意する
+fn test_ai_score(ai: &StringAIPair, character_n: usize, game_number: usize, rng: &mut rngs::StdRng) -> ScoreSummary {
+    let mut scores = Vec::with_capacity(game_number);
 
     for i in 0..game_number {
-        let mut state = AutoMoveMazeState::new(Some(i));
-        state = (ai.ai)(&state);
+        let mut state = AutoMoveMazeState::new(Some(i as u64), character_n);
+        state = (ai.ai)(&state, rng);
 
         let score = state.get_score(false);
-        score_mean += score as f64;
+        scores.push(score);
     }
 
-    score_mean /= game_number as f64;
-    println!("Score of {}: {}", ai.name, score_mean);
+    let summary = ScoreSummary::from_scores(&scores);
+    println!("Score of {}({} characters): {}", ai.name, character_n, summary);
+    summary
 }
 
 #[allow(dead_code)]
@@ -298,14 +822,527 @@ pub fn main() {
     let ais = [
         StringAIPair {
             name: "hill_climb".to_string(),
-            ai: |state| {hill_climb(state, 10000)},
+            ai: |state, rng| {hill_climb(state, 10000, NeighborMode::RandomTeleport, rng)},
+        },
+        StringAIPair {
+            name: "hill_climb_with_restarts".to_string(),
+            ai: |state, rng| {hill_climb_with_restarts(state, 1000, 10, NeighborMode::RandomTeleport, rng)},
         },
         StringAIPair {
             name: "simulated_annealing".to_string(),
-            ai: |state| {simulated_annealing(state, 10000, 500.0, 10.0)},
+            ai: |state, rng| {simulated_annealing(state, 10000, 500.0, 10.0, CoolingSchedule::Linear, NeighborMode::RandomTeleport, rng)},
+        },
+        StringAIPair {
+            name: "genetic_algorithm".to_string(),
+            ai: |state, rng| {genetic_algorithm(state, 50, 200, 0.1, rng)},
+        },
+        StringAIPair {
+            name: "beam_search_placement".to_string(),
+            ai: |state, _| {beam_search_placement(state, 10)},
+        },
+        // 1回の反復で近傍を全て(3体 * 25マス)調べるので、焼きなまし法とほぼ同じ評価回数になるよう反復回数を決める
+        StringAIPair {
+            name: "tabu_search".to_string(),
+            ai: |state, rng| {tabu_search(state, 140, 10, rng)},
+        },
+        StringAIPair {
+            name: "late_acceptance_hill_climb".to_string(),
+            ai: |state, rng| {late_acceptance_hill_climb(state, 10000, 200, NeighborMode::RandomTeleport, rng)},
+        },
+        // 全ての配置を調べた真の最適値。他のAIのスコアはこれを超えない
+        StringAIPair {
+            name: "enumerate_small_boards".to_string(),
+            ai: |state, _| {enumerate_small_boards(state).expect("board must be small enough to enumerate")},
+        },
+    ];
+    for ai in &ais {
+        // AIごとに同じシードの乱数生成器を使い、実行順に関係なく同じ結果になるようにする
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        test_ai_score(ai, CHARACTER_N, 100, &mut rng); // 盤面生成シードを0..100に設定してプレイする。
+    }
+
+    // キャラクターの数を変えて山登り法と焼きなまし法を比較する
+    for character_n in [1, 5, 10] {
+        for ai in [&ais[0], &ais[2]] {
+            let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+            test_ai_score(ai, character_n, 100, &mut rng);
         }
+    }
+
+    // 温度の下げ方を変えて焼きなまし法を比較する
+    let schedules = [
+        StringAIPair {
+            name: "simulated_annealing(exponential)".to_string(),
+            ai: |state, rng| {simulated_annealing(state, 10000, 500.0, 10.0, CoolingSchedule::Exponential, NeighborMode::RandomTeleport, rng)},
+        },
+        StringAIPair {
+            name: "simulated_annealing(logarithmic)".to_string(),
+            ai: |state, rng| {simulated_annealing(state, 10000, 500.0, 10.0, CoolingSchedule::Logarithmic, NeighborMode::RandomTeleport, rng)},
+        },
+        StringAIPair {
+            name: "simulated_annealing(auto tuned)".to_string(),
+            ai: |state, rng| {
+                let (start_temp, end_temp) = auto_tune_temps(state, 100, rng);
+                simulated_annealing(state, 10000, start_temp, end_temp, CoolingSchedule::Linear, NeighborMode::RandomTeleport, rng)
+            },
+        },
+        StringAIPair {
+            name: "simulated_annealing(reheat)".to_string(),
+            ai: |state, rng| {simulated_annealing_with_reheat(state, 10000, 500.0, 10.0, 1000, NeighborMode::RandomTeleport, rng)},
+        },
     ];
-    for ai in ais {
-        test_ai_score(&ai, 100); // 盤面生成シードを0に設定してプレイする。
+    for ai in [&ais[2], &schedules[0], &schedules[1], &schedules[2], &schedules[3]] {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        test_ai_score(ai, CHARACTER_N, 100, &mut rng);
+    }
+
+    // 近傍を隣接するマスへの移動に限った場合と比較する
+    let adjacent_steps = [
+        StringAIPair {
+            name: "hill_climb(adjacent_step)".to_string(),
+            ai: |state, rng| {hill_climb(state, 10000, NeighborMode::AdjacentStep, rng)},
+        },
+        StringAIPair {
+            name: "simulated_annealing(adjacent_step)".to_string(),
+            ai: |state, rng| {simulated_annealing(state, 10000, 500.0, 10.0, CoolingSchedule::Linear, NeighborMode::AdjacentStep, rng)},
+        },
+    ];
+    for ai in &adjacent_steps {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        test_ai_score(ai, CHARACTER_N, 100, &mut rng);
+    }
+
+    // キャラクターを置いたマスのポイントもスコアに数える場合、山登り法はポイントの高いマスに置くようになる
+    let start_cell_scored = StringAIPair {
+        name: "hill_climb(score_start_cell)".to_string(),
+        ai: |state, rng| {
+            let mut state = state.clone();
+            state.set_score_start_cell(true);
+            hill_climb(&state, 10000, NeighborMode::RandomTeleport, rng)
+        },
+    };
+    let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+    test_ai_score(&start_cell_scored, CHARACTER_N, 100, &mut rng);
+
+    // キャラクター同士が衝突する場合、同じマスを取り合わないので重ねて置く配置は意味がなくなる
+    let collided = StringAIPair {
+        name: "hill_climb(collision)".to_string(),
+        ai: |state, rng| {
+            let mut state = state.clone();
+            state.set_collision(true);
+            hill_climb(&state, 10000, NeighborMode::RandomTeleport, rng)
+        },
+    };
+    let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+    test_ai_score(&collided, CHARACTER_N, 100, &mut rng);
+
+    // ターンごとの累計スコアから、キャラクターがどのターンまでポイントを取れているかを見る
+    for ai in [&ais[0], &ais[2]] {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        let state = (ai.ai)(&AutoMoveMazeState::new(Some(0), CHARACTER_N), &mut rng);
+        println!("Score timeline of {}: {:?}", ai.name, state.score_timeline());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mainと同じ温度で、スコアが30下がる遷移を何回受理するか数える
+    #[test]
+    fn worsening_transitions_are_accepted_early_and_rarely_late() {
+        let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+        let number = 10000;
+        let mut count_accepted = |i: usize| {
+            let temp = CoolingSchedule::Linear.temperature(500.0, 10.0, i, number);
+            (0..1000).filter(|_| accepts_transition(100, 70, temp, &mut rng)).count()
+        };
+        let early = count_accepted(0);
+        let late = count_accepted(number - 1);
+        assert!(early > 900, "accepted {} of 1000 at the start", early);
+        assert!(late > 0 && late < 100, "accepted {} of 1000 at the end", late);
+    }
+
+    // 同じシードの乱数生成器を渡せば、同じプロセスで続けて呼んでも同じ配置になる
+    #[test]
+    fn seeded_searches_are_reproducible() {
+        let state = AutoMoveMazeState::new(Some(0), CHARACTER_N);
+        let run = |search: AIFunction| {
+            let mut rng = rngs::StdRng::seed_from_u64(SEARCH_SEED);
+            search(&state, &mut rng).characters
+        };
+        let searches: [AIFunction; 2] = [
+            |state, rng| hill_climb(state, 1000, NeighborMode::RandomTeleport, rng),
+            |state, rng| simulated_annealing(state, 1000, 500.0, 10.0, CoolingSchedule::Linear, NeighborMode::RandomTeleport, rng),
+        ];
+        for search in searches {
+            assert_eq!(run(search), run(search));
+        }
+    }
+
+    // キャラクターのいるマスにはIDを、それ以外のマスにはポイントを表示する
+    #[test]
+    fn display_snapshot() {
+        let mut state = AutoMoveMazeState::new(Some(0), 2);
+        state.set_character(0, 1, 2);
+        state.set_character(1, 3, 3);
+        assert_eq!(
+            state.to_string(),
+            "turn:\t0\nscore:\t0\ncharacters:\t0(1, 2)\t1(3, 3)\n16382\n21093\n62855\n97118\n43378\n"
+        );
+    }
+
+    // 5x5の盤面に5体のキャラクターを置いても、添字の範囲外にならずにスコアを計算できる
+    #[test]
+    fn five_characters_on_a_five_by_five_board() {
+        assert_eq!((H, W), (5, 5));
+        let mut rng = make_rng(SEARCH_SEED);
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), 5);
+            assert_eq!(state.character_n(), 5);
+            for (character_id, (y, x)) in [(0, 0), (0, 4), (4, 0), (4, 4), (2, 2)].into_iter().enumerate() {
+                state.set_character(character_id, y, x);
+            }
+            assert!(state.get_score(false) > 0);
+
+            state.init(&mut rng);
+            state.get_score(false);
+            assert_eq!(beam_search_placement(&state, 3).character_n(), 5);
+        }
+    }
+
+    // 上下左右が同じポイントの対称な盤面では右、右がない場合は左に進む
+    #[test]
+    fn ties_are_broken_right_left_down_up() {
+        let mut state = AutoMoveMazeState::new(Some(0), 1);
+        state.points = [[0; W]; H];
+        for (y, x) in [(2, 3), (2, 1), (3, 2), (1, 2)] {
+            state.points[y][x] = 5;
+        }
+        state.set_character(0, 2, 2);
+
+        let mut moved = state.clone();
+        moved.advance();
+        assert_eq!(moved.characters[0], Coord { y: 2, x: 3 });
+
+        state.points[2][3] = 0;
+        state.advance();
+        assert_eq!(state.characters[0], Coord { y: 2, x: 1 });
+    }
+
+    // 盤面はmake_rngから同じ順に生成するので、chapter4のどのファイルでも同じシードからは同じ盤面になる
+    // make_rngが変わったことにも気づけるように、シード42の盤面だけは文字列でも確かめる
+    #[test]
+    fn chapter4_files_generate_the_same_boards() {
+        use crate::chapter4::{AutoMoveMazeState00, HillClimb01};
+
+        for seed in 0..20 {
+            let board = AutoMoveMazeState::new(Some(seed), 3).to_string();
+            assert_eq!(AutoMoveMazeState00::AutoMoveMazeState::new(Some(seed)).to_string(), board, "seed {}", seed);
+            assert_eq!(HillClimb01::AutoMoveMazeState::new(Some(seed)).to_string(), board, "seed {}", seed);
+        }
+        assert_eq!(AutoMoveMazeState::new(Some(42), 3).to_string(), "turn:\t0\nscore:\t0\ncharacters:\t0(0, 0)\t1(0, 0)\t2(0, 0)\n*2195\n42115\n51458\n96465\n12458\n");
+    }
+
+    #[test]
+    fn zero_beam_width_places_like_width_one() {
+        for seed in 0..10 {
+            let state = AutoMoveMazeState::new(Some(seed), 3);
+            let zero = beam_search_placement(&state, 0);
+            let one = beam_search_placement(&state, 1);
+            assert_eq!(zero.characters, one.characters);
+            assert_eq!(zero.characters.len(), 3);
+        }
+    }
+
+    #[test]
+    fn every_schedule_runs_from_start_temp_to_end_temp() {
+        let schedules = [CoolingSchedule::Linear, CoolingSchedule::Exponential, CoolingSchedule::Logarithmic];
+        for schedule in schedules {
+            for number in [2, 10, 10000] {
+                let temperature = |i| schedule.temperature(500.0, 10.0, i, number);
+                assert_eq!(temperature(0), 500.0, "{:?} {}", schedule, number);
+                assert!((temperature(number - 1) - 10.0).abs() < 1e-9, "{:?} {}: {}", schedule, number, temperature(number - 1));
+                assert!((1..number).all(|i| temperature(i) <= temperature(i - 1)));
+            }
+            // 遷移が1回だけでもNaNにならない
+            assert_eq!(schedule.temperature(500.0, 10.0, 0, 1), 500.0);
+        }
+    }
+
+    #[test]
+    fn auto_tuned_temps_are_positive_and_cool_down() {
+        let mut rng = make_rng(SEARCH_SEED);
+        for seed in 0..10 {
+            let state = AutoMoveMazeState::new(Some(seed), CHARACTER_N);
+            let (start_temp, end_temp) = auto_tune_temps(&state, 200, &mut rng);
+            assert!(end_temp > 0.0, "seed {}: {}", seed, end_temp);
+            assert!(start_temp > end_temp, "seed {}: {} <= {}", seed, start_temp, end_temp);
+            // 開始温度では平均的な悪化の約80%、終了温度では約1%を受理する比になっている
+            assert!((start_temp / end_temp - 0.01f64.ln() / 0.8f64.ln()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn score_timeline_ends_at_the_final_score() {
+        let mut rng = make_rng(SEARCH_SEED);
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), CHARACTER_N);
+            state.init(&mut rng);
+            state.set_score_start_cell(seed % 2 == 0);
+            let timeline = state.score_timeline();
+            assert_eq!(timeline.len(), END_TURN);
+            assert_eq!(*timeline.last().unwrap() as ScoreType, state.get_score(false));
+            assert!(timeline.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    // history_len = 1 の場合は、history[0]が常に現在のスコアになるので山登り法と同じ遷移をする
+    #[test]
+    fn late_acceptance_with_history_one_matches_hill_climb() {
+        for seed in 0..30 {
+            let state = AutoMoveMazeState::new(Some(seed), CHARACTER_N);
+            for neighbor in [NeighborMode::RandomTeleport, NeighborMode::AdjacentStep] {
+                let hill = hill_climb(&state, 500, neighbor, &mut make_rng(SEARCH_SEED));
+                let late = late_acceptance_hill_climb(&state, 500, 1, neighbor, &mut make_rng(SEARCH_SEED));
+                assert_eq!(late.characters, hill.characters, "seed {} {:?}", seed, neighbor);
+            }
+        }
+    }
+
+    // 同じシードからは同じポイントの盤面ができ、生成に使ったシードを保持する
+    #[test]
+    fn same_seed_gives_the_same_points_and_is_stored() {
+        let a = AutoMoveMazeState::new(Some(42), 3);
+        let b = AutoMoveMazeState::new(Some(42), 3);
+        assert_eq!(a.points, b.points);
+        assert_eq!((a.seed(), b.seed()), (42, 42));
+
+        // u32に収まらないシードもそのまま保持する
+        let large_seed = u32::MAX as u64 + 1;
+        assert_eq!(AutoMoveMazeState::new(Some(large_seed), 3).seed(), large_seed);
+
+        // シードを指定しなかった盤面も、seed()から作り直せる
+        let random = AutoMoveMazeState::new(None, 3);
+        assert_eq!(AutoMoveMazeState::new(Some(random.seed()), 3).points, random.points);
+    }
+
+    // 2体が重なったマスは'*'、1体だけのマスはそのIDで表示する
+    #[test]
+    fn overlapping_characters_render_as_a_star() {
+        let mut state = AutoMoveMazeState::new(Some(0), 3);
+        state.set_character(0, 1, 1);
+        state.set_character(1, 1, 1);
+        state.set_character(2, 3, 4);
+        let rendered = state.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[2], "characters:\t0(1, 1)\t1(1, 1)\t2(3, 4)");
+        let board = &lines[3..];
+        assert_eq!(board[1].chars().nth(1), Some('*'));
+        assert_eq!(board[3].chars().nth(4), Some('2'));
+        assert_eq!(rendered.matches('*').count(), 1);
+    }
+
+    // 更新のない遷移がstall_limit回を超えると温度がstart_tempに戻り、更新が続く間は下がり続ける
+    #[test]
+    fn reheat_raises_the_temperature_after_a_stall() {
+        let (start_temp, end_temp, number) = (500.0, 10.0, 100);
+        let mut reheat = Reheat::new(10);
+        let mut temps = Vec::new();
+        for i in 0..number {
+            temps.push(reheat.temperature(start_temp, end_temp, i, number));
+            reheat.record(i < 5);
+        }
+        // 4回目の遷移で最後に更新し、5回目から15回目まで11回続けて更新がない
+        assert!(temps[..16].windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(temps[16], start_temp);
+        assert!(temps[16] > temps[15]);
+        // その後も更新がないので、11回ごとに温度が戻る
+        assert_eq!(temps[27], start_temp);
+        assert!(temps[26] < start_temp);
+
+        let mut improving = Reheat::new(10);
+        let temps: Vec<f64> = (0..number)
+            .map(|i| {
+                let temp = improving.temperature(start_temp, end_temp, i, number);
+                improving.record(true);
+                temp
+            })
+            .collect();
+        assert!(temps.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!((temps[0], temps[number - 1]), (start_temp, end_temp));
+    }
+
+    // 最もポイントの高いマスに2体を置くと、開始マスを数える場合だけそのポイントを1回分多く得る
+    #[test]
+    fn start_cell_scores_only_when_enabled() {
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), 2);
+            let (y, x) = (0..H).flat_map(|y| (0..W).map(move |x| (y, x))).max_by_key(|&(y, x)| state.points[y][x]).unwrap();
+            let best_point = state.points[y][x];
+            state.set_character(0, y, x);
+            state.set_character(1, y, x);
+
+            let without = state.get_score(false);
+            state.set_score_start_cell(true);
+            let with = state.get_score(false);
+            assert_eq!(with - without, best_point as ScoreType, "seed {}", seed);
+            assert_eq!(*state.score_timeline().last().unwrap() as ScoreType, with);
+        }
+    }
+
+    // 山登り法も焼きなまし法も、全ての配置を調べた最適値を超えない
+    #[test]
+    fn heuristics_never_beat_the_enumerated_optimum() {
+        for character_n in 1..=2 {
+            for seed in 0..20 {
+                let state = AutoMoveMazeState::new(Some(seed), character_n);
+                let optimum = enumerate_small_boards(&state).unwrap().get_score(false);
+                let mut rng = make_rng(SEARCH_SEED);
+                let hill = hill_climb(&state, 200, NeighborMode::RandomTeleport, &mut rng).get_score(false);
+                let annealed = simulated_annealing(&state, 200, 500.0, 10.0, CoolingSchedule::Linear, NeighborMode::RandomTeleport, &mut rng)
+                    .get_score(false);
+                assert!(hill <= optimum && annealed <= optimum, "seed {}: {} {} > {}", seed, hill, annealed, optimum);
+            }
+        }
+    }
+
+    // 1体の場合は、全てのマスに置いて調べた最大値と一致する。配置が多すぎる場合はNoneを返す
+    #[test]
+    fn enumeration_matches_brute_force_for_one_character() {
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), 1);
+            let optimum = enumerate_small_boards(&state).unwrap().get_score(false);
+            let mut best = 0;
+            for y in 0..H {
+                for x in 0..W {
+                    state.set_character(0, y, x);
+                    best = best.max(state.get_score(false));
+                }
+            }
+            assert_eq!(optimum, best, "seed {}", seed);
+        }
+        assert!(enumerate_small_boards(&AutoMoveMazeState::new(Some(0), 10)).is_none());
+    }
+
+    // AdjacentStepは1体だけを隣接するマスに動かし、他のキャラクターは動かさない
+    #[test]
+    fn adjacent_step_moves_one_character_by_at_most_one_cell() {
+        let mut rng = make_rng(SEARCH_SEED);
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), 3);
+            state.init(&mut rng);
+            for _ in 0..200 {
+                let before = state.characters.clone();
+                state.transition(NeighborMode::AdjacentStep, &mut rng);
+                let distances: Vec<usize> = before
+                    .iter()
+                    .zip(&state.characters)
+                    .map(|(a, b)| a.y.abs_diff(b.y) + a.x.abs_diff(b.x))
+                    .collect();
+                assert!(distances.iter().all(|&d| d <= 1), "{:?}", distances);
+                assert!(distances.iter().filter(|&&d| d > 0).count() <= 1, "{:?}", distances);
+            }
+        }
+    }
+
+    // SwapTwoCharactersは位置の組を入れ替えるだけなので、位置の多重集合は変わらない
+    // 衝突を考えない盤面(newの既定)ではスコアも変わらない
+    #[test]
+    fn swap_preserves_the_multiset_of_positions() {
+        let sorted = |state: &AutoMoveMazeState| {
+            let mut positions: Vec<(usize, usize)> = state.characters.iter().map(|c| (c.y, c.x)).collect();
+            positions.sort_unstable();
+            positions
+        };
+        let mut rng = make_rng(SEARCH_SEED);
+        for seed in 0..20 {
+            let mut state = AutoMoveMazeState::new(Some(seed), 4);
+            assert!(!state.collision);
+            state.init(&mut rng);
+            let (positions, score) = (sorted(&state), state.get_score(false));
+            for _ in 0..50 {
+                state.transition(NeighborMode::SwapTwoCharacters, &mut rng);
+                assert_eq!(sorted(&state), positions);
+                assert_eq!(state.get_score(false), score);
+            }
+        }
+    }
+
+    // 中央の行だけが通れる盤面に、ポイントをpointsとして、0番をx0、1番をx1の列に置く
+    fn corridor_state(points: [usize; W], x0: usize, x1: usize, collision: bool) -> AutoMoveMazeState {
+        let mut state = AutoMoveMazeState::new(Some(0), 2);
+        for y in (0..H).filter(|&y| y != 2) {
+            for x in 0..W {
+                state.set_wall(y, x);
+            }
+        }
+        state.points[2] = points;
+        state.set_character(0, 2, x0);
+        state.set_character(1, 2, x1);
+        state.set_collision(collision);
+        state
+    }
+
+    // 衝突を考える場合は、IDの小さい方が先に9のマスを取るので、2体を入れ替えると取れるポイントが変わる
+    // 9の左にいる方が9を取ると右の方は0のマスに進むが、右にいる方が9を取ると左の方は5を取れる
+    // 衝突を考えない場合は、どちらの並びでも2体とも9のマスに入り、その後は同じ動きをする
+    #[test]
+    fn swap_changes_the_score_with_collision() {
+        let points = [5, 0, 9, 0, 0];
+        assert_eq!(corridor_state(points, 1, 3, false).get_score(false), 9);
+        assert_eq!(corridor_state(points, 3, 1, false).get_score(false), 9);
+        assert_eq!(corridor_state(points, 1, 3, true).get_score(false), 9);
+        assert_eq!(corridor_state(points, 3, 1, true).get_score(false), 14);
+    }
+
+    // 中央の行だけが通れる盤面で、2体が同じ9のマスを狙う
+    // 衝突を考えない場合は2体とも9のマスに入り、考える場合はIDの小さい0番が入って1番は反対側に進む
+    #[test]
+    fn narrow_corridor_collision() {
+        let corridor = |collision: bool| corridor_state([1, 0, 9, 0, 1], 1, 3, collision);
+
+        let mut overlapping = corridor(false);
+        overlapping.advance();
+        assert_eq!(overlapping.characters, vec![Coord { y: 2, x: 2 }, Coord { y: 2, x: 2 }]);
+        assert_eq!(overlapping.game_score, 9);
+
+        let mut colliding = corridor(true);
+        colliding.advance();
+        assert_eq!(colliding.characters, vec![Coord { y: 2, x: 2 }, Coord { y: 2, x: 4 }]);
+        assert_eq!(colliding.game_score, 10);
+
+        // 壁には入らず、何度プレイしても同じスコアになる
+        for state in [corridor(false), corridor(true)] {
+            let mut played = state.clone();
+            while !played.is_done() {
+                played.advance();
+                assert!(played.characters.iter().all(|c| c.y == 2));
+            }
+            assert_eq!(state.get_score(false), played.game_score as ScoreType);
+            assert_eq!(state.get_score(false), state.get_score(false));
+        }
+        // 重なった2体は右端の1を取りに行くのに2ターン余計にかかる
+        assert_eq!(corridor(false).score_timeline(), vec![9, 9, 10, 10, 10]);
+        assert_eq!(corridor(true).score_timeline(), vec![10, 10, 10, 10, 10]);
+    }
+
+    // 個体数0や範囲外の突然変異率を渡しても、丸めた値で最後まで探索して配置を返す
+    #[test]
+    fn genetic_algorithm_clamps_population_and_mutation_rate() {
+        let state = AutoMoveMazeState::new(Some(0), CHARACTER_N);
+        for (population, mutation_rate) in [(0, 0.1), (1, 0.1), (4, -1.0), (4, 2.0), (4, f64::NAN)] {
+            let placed = genetic_algorithm(&state, population, 3, mutation_rate, &mut make_rng(SEARCH_SEED));
+            assert_eq!(placed.character_n(), CHARACTER_N);
+            assert!(placed.get_score(false) >= 0);
+        }
+        // 丸めた値は、範囲内の端の値を渡した場合と同じ結果になる
+        let score = |population: usize, mutation_rate: f64| {
+            genetic_algorithm(&state, population, 3, mutation_rate, &mut make_rng(SEARCH_SEED)).get_score(false)
+        };
+        assert_eq!(score(0, 0.1), score(1, 0.1));
+        assert_eq!(score(4, -1.0), score(4, 0.0));
+        assert_eq!(score(4, 2.0), score(4, 1.0));
+        assert_eq!(score(4, f64::NAN), score(4, 0.0));
     }
 }
@@ -0,0 +1 @@
+pub mod AlternateMazeState;
\ No newline at end of file
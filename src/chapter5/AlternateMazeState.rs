@@ -0,0 +1,341 @@
+#![allow(non_snake_case, clippy::needless_range_loop)]
+
+use rand::{Rng, thread_rng};
+use std::fmt;
+
+use crate::common::rng::{make_rng, resolve_seed};
+use crate::common::stats::SearchStats;
+
+const H: usize = 3;         // 迷路の高さ
+const W: usize = 3;         // 迷路の幅
+const END_TURN: usize = 4;  // ゲーム終了ターン
+
+type ScoreType = i64;
+const INF: ScoreType = 1000000000;
+
+// 座標を保持する
+#[derive(Clone, Copy)]
+struct Coord {
+    y: usize,
+    x: usize,
+}
+
+// プレイヤーの位置と得たスコア
+#[derive(Clone, Copy)]
+struct Character {
+    coord: Coord,
+    game_score: usize,  // ゲーム上で実際に得たスコア
+}
+
+// 交互着手二人ゲームの例
+// 2人のプレイヤーが1ターンずつ交互に、上下左右四方向のいずれかに1マスずつ進む。
+// 床にあるポイントを踏むと自身のスコアとなり、床のポイントが消える。
+// 2人が同じマスにいてもよい。
+// END_TURNの時点で、先手のスコアから後手のスコアを引いた値が正なら先手、負なら後手の勝ちとする。
+#[derive(Clone)]
+pub struct AlternateMazeState {
+    points: [[usize; W]; H],      // 床のポイントを0~9で表現する
+    turn: usize,                  // 現在のターン
+    characters: [Character; 2],   // characters[0]が手番のプレイヤー
+}
+
+impl AlternateMazeState {
+    // h*wの迷路を生成する。2人のプレイヤーは中央の行に左右対称に配置する
+    pub fn new(seed: Option<u64>) -> Self {
+        let mut rng = make_rng(resolve_seed(seed));
+
+        let characters = [
+            Character { coord: Coord { y: H / 2, x: W / 2 - 1 }, game_score: 0 },
+            Character { coord: Coord { y: H / 2, x: W / 2 + 1 }, game_score: 0 },
+        ];
+
+        let mut points = [[0; W]; H];
+        for y in 0..H {
+            for x in 0..W {
+                if characters.iter().any(|character| character.coord.y == y && character.coord.x == x) {
+                    continue;
+                }
+                points[y][x] = rng.gen_range(0..=9);
+            }
+        }
+
+        AlternateMazeState {
+            points,
+            turn: 0,
+            characters,
+        }
+    }
+
+    // ゲームの終了判定
+    pub fn is_done(&self) -> bool {
+        self.turn == END_TURN
+    }
+
+    // 手番のプレイヤーが可能な行動を全て取得する
+    pub fn legal_actions(&self) -> Vec<usize> {
+        let character = &self.characters[0];
+        let mut actions = Vec::with_capacity(4);
+        for action in 0..4 {
+            let ty = character.coord.y as isize + dy[action];
+            let tx = character.coord.x as isize + dx[action];
+            if ty >= 0 && ty < H as isize && tx >= 0 && tx < W as isize {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    // 手番のプレイヤーの行動でゲームを1ターン進め、手番を入れ替える
+    pub fn advance(&mut self, action: usize) {
+        let character = &mut self.characters[0];
+        character.coord.y = (character.coord.y as isize + dy[action]) as usize;
+        character.coord.x = (character.coord.x as isize + dx[action]) as usize;
+
+        let point = &mut self.points[character.coord.y][character.coord.x];
+        character.game_score += *point;
+        *point = 0;
+
+        self.turn += 1;
+        self.characters.swap(0, 1);
+    }
+
+    // 手番のプレイヤーから見た盤面評価。自分のスコアから相手のスコアを引く
+    pub fn get_score(&self) -> ScoreType {
+        self.characters[0].game_score as ScoreType - self.characters[1].game_score as ScoreType
+    }
+
+    // 先手のスコアから後手のスコアを引いた値
+    pub fn first_player_score(&self) -> ScoreType {
+        if self.turn.is_multiple_of(2) {
+            self.get_score()
+        } else {
+            -self.get_score()
+        }
+    }
+}
+
+// 現在のゲーム状況を文字列にする
+// 先手を'A'、後手を'B'で表す
+impl fmt::Display for AlternateMazeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = if self.turn.is_multiple_of(2) { (0, 1) } else { (1, 0) };
+        writeln!(f, "turn:\t{}", self.turn)?;
+        writeln!(f, "score(A):\t{}", self.characters[a].game_score)?;
+        writeln!(f, "score(B):\t{}", self.characters[b].game_score)?;
+
+        for y in 0..H {
+            for x in 0..W {
+                let is_at = |id: usize| self.characters[id].coord.y == y && self.characters[id].coord.x == x;
+                if is_at(a) {
+                    write!(f, "A")?;
+                } else if is_at(b) {
+                    write!(f, "B")?;
+                } else if self.points[y][x] > 0 {
+                    write!(f, "{}", self.points[y][x])?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(non_upper_case_globals)]
+const dy: [isize; 4] = [0, 0, 1, -1];
+
+#[allow(non_upper_case_globals)]
+const dx: [isize; 4] = [1, -1, 0, 0];
+
+// ランダムに行動を決定する
+fn random_action(state: &AlternateMazeState) -> usize {
+    let legal_actions = state.legal_actions();
+    legal_actions[thread_rng().gen_range(0..legal_actions.len())]
+}
+
+// ミニマックス法での盤面評価。手番のプレイヤーから見た評価を返す
+// 相手の手番の評価は符号を反転させて比べる
+fn mini_max_score(state: &AlternateMazeState, depth: usize, stats: &mut SearchStats) -> ScoreType {
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+
+    let mut best_score = -INF;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        stats.nodes_expanded += 1;
+        let score = -mini_max_score(&next_state, depth - 1, stats);
+        if score > best_score {
+            best_score = score;
+        }
+    }
+    best_score
+}
+
+// 深さdepthまでミニマックス法で探索し、行動と探索にかかった計算量を返す
+// depthが0の場合も1手先の盤面だけは評価して行動を選ぶ
+pub fn minimax_action_with_stats(state: &AlternateMazeState, depth: usize) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let mut best_action = 0;
+    let mut best_score = -INF;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        stats.nodes_expanded += 1;
+        let score = -mini_max_score(&next_state, depth.saturating_sub(1), &mut stats);
+        if score > best_score {
+            best_action = action;
+            best_score = score;
+        }
+    }
+    stats.depth_reached = depth;
+    (best_action, stats)
+}
+
+// 深さdepthまでミニマックス法で探索して行動を決定する
+pub fn minimax_action(state: &AlternateMazeState, depth: usize) -> usize {
+    minimax_action_with_stats(state, depth).0
+}
+
+// アルファベータ法での盤面評価。手番のプレイヤーから見た評価を返す
+// 評価がbeta以上になる手が見つかった時点で、相手はこの局面を選ばないので残りの手は調べない
+fn alpha_beta_score(state: &AlternateMazeState, mut alpha: ScoreType, beta: ScoreType, depth: usize, stats: &mut SearchStats) -> ScoreType {
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        stats.nodes_expanded += 1;
+        let score = -alpha_beta_score(&next_state, -beta, -alpha, depth - 1, stats);
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+    alpha
+}
+
+// 深さdepthまでアルファベータ法で探索し、行動と探索にかかった計算量を返す
+// 同じ深さのミニマックス法と同じ行動を選ぶ。depthが0の場合も1手先の盤面だけは評価する
+pub fn alpha_beta_action_with_stats(state: &AlternateMazeState, depth: usize) -> (usize, SearchStats) {
+    let mut stats = SearchStats::default();
+    let mut best_action = 0;
+    let mut alpha = -INF;
+    let beta = INF;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        stats.nodes_expanded += 1;
+        let score = -alpha_beta_score(&next_state, -beta, -alpha, depth.saturating_sub(1), &mut stats);
+        if score > alpha {
+            best_action = action;
+            alpha = score;
+        }
+    }
+    stats.depth_reached = depth;
+    (best_action, stats)
+}
+
+// 深さdepthまでアルファベータ法で探索して行動を決定する
+pub fn alpha_beta_action(state: &AlternateMazeState, depth: usize) -> usize {
+    alpha_beta_action_with_stats(state, depth).0
+}
+
+type AIFunction = fn(&AlternateMazeState) -> usize;
+
+struct StringAIPair {
+    name: String,
+    ai: AIFunction,
+}
+
+// シードを指定して、2つのAIを対戦させながらゲーム状況を表示する
+fn play_game(ais: &[StringAIPair; 2], seed: u64) {
+    let mut state = AlternateMazeState::new(Some(seed));
+    println!("{}", state);
+
+    while !state.is_done() {
+        let ai = &ais[state.turn % 2];
+        println!("{}'s turn", ai.name);
+        state.advance((ai.ai)(&state));
+        println!("{}", state);
+    }
+
+    println!("Score of {} - {}:\t{}", ais[0].name, ais[1].name, state.first_player_score());
+}
+
+// 盤面生成シードを0..game_numberとしてgame_number回対戦し、先手の勝率を表示する
+// 引き分けは0.5勝とする
+fn test_first_player_win_rate(ais: &[StringAIPair; 2], game_number: usize) {
+    let mut first_player_win = 0.0;
+    for i in 0..game_number {
+        let mut state = AlternateMazeState::new(Some(i as u64));
+        while !state.is_done() {
+            let ai = &ais[state.turn % 2];
+            state.advance((ai.ai)(&state));
+        }
+        first_player_win += match state.first_player_score() {
+            score if score > 0 => 1.0,
+            0 => 0.5,
+            _ => 0.0,
+        };
+    }
+    println!("Win rate of {} vs {}:\t{}", ais[0].name, ais[1].name, first_player_win / game_number as f64);
+}
+
+#[allow(dead_code)]
+pub fn main() {
+    let ais = [
+        StringAIPair {
+            name: "alpha_beta_action".to_string(),
+            ai: |state| alpha_beta_action(state, END_TURN),
+        },
+        StringAIPair {
+            name: "random_action".to_string(),
+            ai: random_action,
+        },
+    ];
+    play_game(&ais, 0);
+    test_first_player_win_rate(&ais, 100);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 初期盤面で、アルファベータ法はミニマックス法と同じ行動を選び、展開するノードは少ない
+    #[test]
+    fn alpha_beta_matches_minimax_with_fewer_nodes() {
+        let mut minimax_nodes = 0;
+        let mut alpha_beta_nodes = 0;
+        for seed in 0..100 {
+            let state = AlternateMazeState::new(Some(seed));
+            let (minimax, minimax_stats) = minimax_action_with_stats(&state, END_TURN);
+            let (alpha_beta, alpha_beta_stats) = alpha_beta_action_with_stats(&state, END_TURN);
+            assert_eq!(minimax, alpha_beta, "seed {}", seed);
+            assert!(alpha_beta_stats.nodes_expanded <= minimax_stats.nodes_expanded);
+            minimax_nodes += minimax_stats.nodes_expanded;
+            alpha_beta_nodes += alpha_beta_stats.nodes_expanded;
+        }
+        assert!(alpha_beta_nodes < minimax_nodes);
+    }
+
+    #[test]
+    fn depth_zero_evaluates_only_the_next_states() {
+        for seed in 0..20 {
+            let state = AlternateMazeState::new(Some(seed));
+            let (minimax, minimax_stats) = minimax_action_with_stats(&state, 0);
+            let (alpha_beta, alpha_beta_stats) = alpha_beta_action_with_stats(&state, 0);
+            assert_eq!(minimax, alpha_beta);
+            assert_eq!(minimax, minimax_action(&state, 1));
+            let legal_n = state.legal_actions().len() as u64;
+            assert_eq!((minimax_stats.nodes_expanded, alpha_beta_stats.nodes_expanded), (legal_n, legal_n));
+        }
+    }
+}
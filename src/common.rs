@@ -0,0 +1,20 @@
+#[cfg(feature = "std")]
+pub mod bfs;
+#[cfg(feature = "std")]
+pub mod bounded_beam;
+#[cfg(feature = "std")]
+pub mod game;
+#[cfg(feature = "std")]
+pub mod logging;
+#[cfg(feature = "std")]
+pub mod node_arena;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod rng;
+pub mod search;
+#[cfg(feature = "std")]
+pub mod search_params;
+#[cfg(feature = "std")]
+pub mod stats;
+pub mod time_keeper;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+use crate::chapter3::MazeState00::Coord;
+
+// 壁を避けて上下左右に1マスずつ移動する場合の、startから各マスまでの最短の歩数を求める
+// wallsと返り値は y * w + x 番目が(y, x)のマスを表す。たどり着けないマスはu32::MAXとする
+pub fn bfs_distances(start: Coord, walls: &[bool], h: usize, w: usize) -> Vec<u32> {
+    assert_eq!(walls.len(), h * w, "walls must have h * w cells");
+    let mut distances = vec![u32::MAX; h * w];
+    let start_index = start.y as usize * w + start.x as usize;
+    distances[start_index] = 0;
+
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(now) = queue.pop_front() {
+        let now_distance = distances[now.y as usize * w + now.x as usize];
+        for next in now.neighbors4().filter(|next| next.in_bounds(h, w)) {
+            let index = next.y as usize * w + next.x as usize;
+            if walls[index] || distances[index] != u32::MAX {
+                continue;
+            }
+            distances[index] = now_distance + 1;
+            queue.push_back(next);
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::MazeState00::MazeState;
+
+    const M: u32 = u32::MAX;
+
+    // 壁を回り込む距離になり、マンハッタン距離より長くなる
+    #[test]
+    fn distances_go_around_walls() {
+        let state: MazeState = "@.#.\n.##.\n....".parse().unwrap();
+        let distances = bfs_distances(state.character, &state.walls, 3, 4);
+        assert_eq!(distances, vec![
+            0, 1, M, 7,
+            1, M, M, 6,
+            2, 3, 4, 5,
+        ]);
+    }
+
+    #[test]
+    fn walled_off_cells_are_unreachable() {
+        let state: MazeState = "@#.\n##.\n...".parse().unwrap();
+        let distances = bfs_distances(state.character, &state.walls, 3, 3);
+        assert_eq!(distances[0], 0);
+        assert!(distances[1..].iter().all(|&distance| distance == M));
+    }
+}
@@ -0,0 +1,85 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// 大きい順に最大capacity個の要素だけを保持するビーム
+// capacityを超えて追加しようとした場合は、最も小さい要素を捨てる
+pub struct BoundedBeam<T: Ord> {
+    capacity: usize,
+    heap: BinaryHeap<Reverse<T>>,  // 最も小さい要素を先頭に置く
+}
+
+impl<T: Ord> BoundedBeam<T> {
+    // 最大capacity個の要素を保持するビームをつくる
+    pub fn new(capacity: usize) -> Self {
+        BoundedBeam {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    // capacity個の要素を保持しているか判定する
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.capacity
+    }
+
+    // 保持している要素のうち最も小さいものを返す
+    pub fn worst(&self) -> Option<&T> {
+        self.heap.peek().map(|Reverse(item)| item)
+    }
+
+    // 要素を追加する。ビームに残った場合はtrueを返す
+    pub fn push(&mut self, item: T) -> bool {
+        if !self.is_full() {
+            self.heap.push(Reverse(item));
+            return true;
+        }
+        match self.heap.peek() {
+            Some(Reverse(worst)) if item > *worst => {
+                self.heap.pop();
+                self.heap.push(Reverse(item));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // 保持している要素を大きい順に並べて返す
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        // Reverseで包んでいるので、昇順に並べると元の要素の大きい順になる
+        self.heap.into_sorted_vec().into_iter().map(|Reverse(item)| item).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::rng::make_rng;
+    use rand::Rng;
+
+    // 追加するたびにcapacity個以下に収まり、最後には追加した要素の上位capacity個が残る
+    #[test]
+    fn keeps_only_the_largest_capacity_items() {
+        let mut rng = make_rng(0);
+        for capacity in [0, 1, 4, 16] {
+            let items: Vec<i32> = (0..100).map(|_| rng.gen_range(0..50)).collect();
+            let mut beam = BoundedBeam::new(capacity);
+            for &item in &items {
+                beam.push(item);
+                assert!(beam.len() <= capacity);
+            }
+
+            let mut expected = items.clone();
+            expected.sort_unstable_by(|a, b| b.cmp(a));
+            expected.truncate(capacity);
+            assert_eq!(beam.into_sorted_vec(), expected);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::ops::Range;
+
+// Gameトレイトと貪欲法はstdなしでも使えるようにsearchに置き、これまで通りここからも使えるようにする
+pub use crate::common::search::{greedy_action, Game};
+use crate::common::stats::ScoreSummary;
+
+// 最後までプレイしたゲームの結果
+// 一人ゲームでは最終スコアを持つ。二人ゲームの勝敗もこの列挙型に加えて同じように返せるようにする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Finished { score: i32, turns: usize },  // ゲーム終了時のスコアと、それまでに進めたターン数
+}
+
+// 盤面生成シードごとにmake_stateで盤面をつくり、action_fnで選んだ行動でゲームが終わるまでプレイする
+// 各ゲームの終了時のevaluateをスコアとして、スコアの分布を返す
+pub fn run_episodes<G: Game>(
+    make_state: impl Fn(u64) -> G,
+    mut action_fn: impl FnMut(&G) -> G::Action,
+    seeds: Range<u64>,
+) -> ScoreSummary {
+    let scores: Vec<i64> = seeds
+        .map(|seed| {
+            let mut state = make_state(seed);
+            while !state.is_done() {
+                let action = action_fn(&state);
+                state.advance(action);
+            }
+            state.evaluate()
+        })
+        .collect();
+    ScoreSummary::from_scores(&scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+    use crate::chapter4::SimulatedAnnealing02::AutoMoveMazeState;
+
+    // greedy_actionの行動が合法手に含まれ、1手先の評価が全ての合法手の中で最大になることを確かめる
+    fn assert_greedy_is_best<G: Game>(state: &G)
+    where
+        G::Action: PartialEq + std::fmt::Debug,
+    {
+        let action = greedy_action(state).expect("state must have a legal action");
+        let evaluate_after = |action: G::Action| {
+            let mut next_state = state.clone();
+            next_state.advance(action);
+            next_state.evaluate()
+        };
+        let legal_actions = state.legal_actions();
+        assert!(legal_actions.contains(&action), "{:?} is not legal", action);
+        let best = legal_actions.iter().map(|&action| evaluate_after(action)).max().unwrap();
+        assert_eq!(evaluate_after(action), best);
+    }
+
+    #[test]
+    fn greedy_action_picks_the_best_next_state_of_any_game() {
+        for seed in 0..10 {
+            let mut state = MazeState::new(MazeConfig::new(5, 5, 10), Some(seed));
+            while !state.is_done() {
+                assert_greedy_is_best(&state);
+                let action = greedy_action(&state).unwrap();
+                Game::advance(&mut state, action);
+            }
+            assert_greedy_is_best(&AutoMoveMazeState::new(Some(seed), 2));
+        }
+    }
+
+    #[test]
+    fn greedy_action_returns_none_without_legal_actions() {
+        let state: MazeState = "###\n#@#\n###".parse().unwrap();
+        assert_eq!(greedy_action(&state), None);
+    }
+
+    // run_episodesで貪欲法をプレイした分布は、同じシードで1ゲームずつプレイしたスコアから求めた分布と一致する
+    #[test]
+    fn run_episodes_with_greedy() {
+        let config = MazeConfig::new(5, 5, 10);
+        let greedy = |state: &MazeState| greedy_action(state).unwrap();
+        let summary = run_episodes(|seed| MazeState::new(config, Some(seed)), greedy, 0..20);
+
+        let scores: Vec<i32> = (0..20)
+            .map(|seed| {
+                let mut state = MazeState::new(config, Some(seed));
+                state.run_to_completion(greedy);
+                state.game_score
+            })
+            .collect();
+        assert_eq!(format!("{:?}", summary), format!("{:?}", ScoreSummary::from_scores(&scores)));
+        assert!(summary.min > 0);
+    }
+}
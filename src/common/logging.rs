@@ -0,0 +1,87 @@
+// 探索の途中経過を出力するためのマクロ
+// loggingフィーチャーが有効な場合はlogクレートのdebug!/trace!に展開する。
+// 無効な場合は何も出力しないが、引数の型検査だけは行うので未使用変数の警告も出ない。
+
+#[cfg(feature = "logging")]
+macro_rules! search_debug {
+    ($($arg:tt)*) => { log::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! search_debug {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "logging")]
+macro_rules! search_trace {
+    ($($arg:tt)*) => { log::trace!($($arg)*) };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! search_trace {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use search_debug;
+pub(crate) use search_trace;
+
+// loggingフィーチャーが有効な場合は、環境変数RUST_LOGに従ってロガーを初期化する
+// 無効な場合は何もしない
+pub fn init_logger() {
+    #[cfg(feature = "logging")]
+    {
+        let _ = env_logger::try_init();
+    }
+}
+
+#[cfg(all(test, feature = "logging"))]
+mod tests {
+    use crate::chapter3::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+    use crate::chapter3::ChokudaiSearchWithTime07::chokudai_search_action_wirh_time_threshold;
+    use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+    use std::sync::Mutex;
+
+    // 出力されたメッセージを記録するロガー
+    struct CaptureLogger {
+        messages: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.messages.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger { messages: Mutex::new(Vec::new()) };
+
+    // 制限時間0msで探索すると、最初のノードを展開する前に時間切れをログに出力する
+    #[test]
+    fn searches_log_time_over() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let state = MazeState::new(MazeConfig::new(10, 10, 20), Some(0));
+        beam_search_action_with_time_threshold(&state, 5, 0);
+        chokudai_search_action_wirh_time_threshold(&state, 1, 5, 0);
+        search_debug!("after searches");
+
+        let messages = LOGGER.messages.lock().unwrap();
+        assert!(messages.iter().any(|m| m.starts_with("beam search: time over at depth 0")), "{:?}", messages);
+        assert!(messages.iter().any(|m| m == "chokudai search: time over at depth 0 after 0 nodes"), "{:?}", messages);
+        assert!(messages.iter().any(|m| m == "after searches"));
+    }
+}
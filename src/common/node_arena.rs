@@ -0,0 +1,99 @@
+// 探索で残した盤面を親の添字とともに並べて持つアリーナ
+// 盤面ごとにルートからの行動列を持たせる代わりに、親をたどって行動列を復元する
+// 添字iの盤面は、parent[i]の盤面からaction[i]の行動で遷移したものとする。ルートの親はNone
+pub struct NodeArena<T> {
+    pub states: Vec<T>,
+    pub parent: Vec<Option<usize>>,
+    pub action: Vec<usize>,  // ルートの行動は使わない
+}
+
+impl<T> NodeArena<T> {
+    pub fn new() -> Self {
+        NodeArena {
+            states: Vec::new(),
+            parent: Vec::new(),
+            action: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    // ルートの盤面を追加して添字を返す
+    pub fn push_root(&mut self, state: T) -> usize {
+        self.states.push(state);
+        self.parent.push(None);
+        self.action.push(usize::MAX);
+        self.states.len() - 1
+    }
+
+    // parentの盤面からactionで遷移した盤面を追加して添字を返す
+    pub fn push(&mut self, state: T, parent: usize, action: usize) -> usize {
+        assert!(parent < self.states.len(), "parent must already be in the arena");
+        self.states.push(state);
+        self.parent.push(Some(parent));
+        self.action.push(action);
+        self.states.len() - 1
+    }
+
+    // ルートから添字indexの盤面に至るまでの行動列を返す
+    pub fn path(&self, index: usize) -> Vec<usize> {
+        let mut actions = Vec::new();
+        let mut now = index;
+        while let Some(parent) = self.parent[now] {
+            actions.push(self.action[now]);
+            now = parent;
+        }
+        actions.reverse();
+        actions
+    }
+}
+
+impl<T> Default for NodeArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+    use crate::common::rng::make_rng;
+    use rand::Rng;
+
+    // 1本の手順に沿って盤面を追加し、途中で枝分かれした盤面も混ぜる。
+    // 最後の盤面の行動列は、実際に進めた行動列と一致し、たどり直すと同じスコアになる
+    #[test]
+    fn path_matches_the_played_sequence() {
+        let root = MazeState::new(MazeConfig::new(6, 6, 30), Some(0));
+        let mut rng = make_rng(1);
+        let mut arena = NodeArena::new();
+        let mut now = arena.push_root(root.clone());
+        let mut played = Vec::new();
+        while !arena.states[now].is_done() {
+            let legal_actions = arena.states[now].legal_actions();
+            for &action in &legal_actions[1..] {
+                let mut branch = arena.states[now].clone();
+                branch.advance(action).unwrap();
+                arena.push(branch, now, action);
+            }
+            let action = legal_actions[rng.gen_range(0..legal_actions.len())];
+            let mut next_state = arena.states[now].clone();
+            next_state.advance(action).unwrap();
+            now = arena.push(next_state, now, action);
+            played.push(action);
+        }
+
+        assert_eq!(arena.path(now), played);
+        assert!(arena.path(0).is_empty());
+        let mut replayed = root;
+        replayed.apply_actions(&arena.path(now)).unwrap();
+        assert_eq!(replayed.game_score, arena.states[now].game_score);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chapter3::MazeState00::{MazeConfig, MazeState, Movement};
+
+// リプレイファイルの形式
+// 1行目に盤面生成シード、2行目に盤面の設定を書き、3行目以降に選んだ行動を1行に1つずつ書く
+//
+// seed:	42
+// config:	h=30 w=30 end_turn=100 wall_ratio=0 trap_ratio=0 regen_after=none movement=4 point_min=0 point_max=9 score_start_cell=false
+// 0
+// 2
+// ...
+//
+// 同じ設定とシードで盤面を生成し直して行動を順に適用すれば、ゲームを再現できる
+
+// 行動を記録した盤面を、seed()で生成した盤面からのリプレイとしてpathに書き出す
+// record_historyがfalseの盤面は行動が残っていないのでエラーを返す
+// 文字列から読み込んだ盤面はシードで再現できないので、書き出しても元の盤面には戻らない
+pub fn write_replay(path: impl AsRef<Path>, state: &MazeState) -> io::Result<()> {
+    if !state.record_history {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "state must record its history"));
+    }
+    let mut replay = format!("seed:\t{}\nconfig:\t{}\n", state.seed(), format_config(&state.config));
+    for action in &state.history {
+        replay += &format!("{}\n", action);
+    }
+    fs::write(path, replay)
+}
+
+// pathのリプレイを読み込み、生成し直した最初の盤面と行動の列を返す
+// 返す盤面は行動を記録する状態になっている。行動が合法かどうかは確認しない
+pub fn read_replay(path: impl AsRef<Path>) -> io::Result<(MazeState, Vec<usize>)> {
+    let replay = fs::read_to_string(path)?;
+    let mut lines = replay.lines();
+    let seed = header(lines.next(), "seed:")?.parse().map_err(|_| invalid_data("invalid seed"))?;
+    let config = parse_config(header(lines.next(), "config:")?)?;
+    let actions = lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse().map_err(|_| invalid_data(&format!("invalid action: {}", line))))
+        .collect::<io::Result<Vec<usize>>>()?;
+
+    let mut state = MazeState::new(config, Some(seed));
+    state.record_history = true;
+    Ok((state, actions))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// "name:\t値" の行から値を取り出す
+fn header<'a>(line: Option<&'a str>, name: &str) -> io::Result<&'a str> {
+    line.and_then(|line| line.strip_prefix(name))
+        .map(str::trim)
+        .ok_or_else(|| invalid_data(&format!("missing {} line", name)))
+}
+
+fn format_config(config: &MazeConfig) -> String {
+    let regen_after = match config.regen_after {
+        Some(turns) => turns.to_string(),
+        None => "none".to_string(),
+    };
+    format!(
+        "h={} w={} end_turn={} wall_ratio={} trap_ratio={} regen_after={} movement={} point_min={} point_max={} score_start_cell={}",
+        config.h,
+        config.w,
+        config.end_turn,
+        config.wall_ratio,
+        config.trap_ratio,
+        regen_after,
+        config.movement.action_n(),
+        config.point_min,
+        config.point_max,
+        config.score_start_cell,
+    )
+}
+
+// format_configで書き出した設定を読み込む。書かれていない項目はMazeConfig::newと同じ値とする
+fn parse_config(line: &str) -> io::Result<MazeConfig> {
+    let mut config = MazeConfig::new(0, 0, 0);
+    for item in line.split_whitespace() {
+        let (key, value) = item.split_once('=').ok_or_else(|| invalid_data(&format!("invalid config item: {}", item)))?;
+        let invalid = || invalid_data(&format!("invalid value for {}: {}", key, value));
+        match key {
+            "h" => config.h = value.parse().map_err(|_| invalid())?,
+            "w" => config.w = value.parse().map_err(|_| invalid())?,
+            "end_turn" => config.end_turn = value.parse().map_err(|_| invalid())?,
+            "wall_ratio" => config.wall_ratio = value.parse().map_err(|_| invalid())?,
+            "trap_ratio" => config.trap_ratio = value.parse().map_err(|_| invalid())?,
+            "regen_after" => {
+                config.regen_after = match value {
+                    "none" => None,
+                    _ => Some(value.parse().map_err(|_| invalid())?),
+                }
+            }
+            "movement" => {
+                config.movement = match value {
+                    "4" => Movement::FourWay,
+                    "8" => Movement::EightWay,
+                    _ => return Err(invalid()),
+                }
+            }
+            "point_min" => config.point_min = value.parse().map_err(|_| invalid())?,
+            "point_max" => config.point_max = value.parse().map_err(|_| invalid())?,
+            "score_start_cell" => config.score_start_cell = value.parse().map_err(|_| invalid())?,
+            _ => return Err(invalid_data(&format!("unknown config item: {}", key))),
+        }
+    }
+    if config.h == 0 || config.w == 0 {
+        return Err(invalid_data("board size must be positive"));
+    }
+    if config.point_min < 0 {
+        return Err(invalid_data("point_min must not be negative"));
+    }
+    if config.point_min > config.point_max {
+        return Err(invalid_data("point_min must not exceed point_max"));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::game::greedy_action;
+    use std::env;
+
+    // 書き出したリプレイを読み込み直して行動を適用すると、同じ盤面と同じ最終スコアになる
+    #[test]
+    fn write_then_read_reproduces_the_final_score() {
+        let config = MazeConfig {
+            wall_ratio: 0.1,
+            trap_ratio: 0.1,
+            regen_after: Some(5),
+            movement: Movement::EightWay,
+            ..MazeConfig::new(6, 7, 30)
+        };
+        let mut state = MazeState::new(config, Some(42));
+        state.record_history = true;
+        while !state.is_done() {
+            state.advance(greedy_action(&state).unwrap()).unwrap();
+        }
+
+        let path = env::temp_dir().join(format!("thunder_rust_replay_{}.txt", std::process::id()));
+        write_replay(&path, &state).unwrap();
+        let read = read_replay(&path);
+        fs::remove_file(&path).unwrap();
+
+        let (mut replayed, actions) = read.unwrap();
+        assert_eq!(actions, state.history);
+        replayed.apply_actions(&actions).unwrap();
+        assert_eq!(replayed.game_score, state.game_score);
+        assert_eq!(replayed.to_string(), state.to_string());
+    }
+
+    #[test]
+    fn write_requires_a_recorded_history() {
+        let state = MazeState::new(MazeConfig::new(3, 3, 4), Some(0));
+        let path = env::temp_dir().join(format!("thunder_rust_no_history_{}.txt", std::process::id()));
+        assert_eq!(write_replay(&path, &state).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
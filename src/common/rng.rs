@@ -0,0 +1,28 @@
+use once_cell::sync::Lazy;
+use rand::{Rng, SeedableRng, rngs, thread_rng};
+use std::sync::Mutex;
+
+// set_global_seedで初期化した、シードを指定しない盤面生成で使う乱数生成器
+static GLOBAL_RNG: Lazy<Mutex<Option<rngs::StdRng>>> = Lazy::new(|| Mutex::new(None));
+
+// 盤面の生成に使う乱数生成器をつくる
+// 全ての章で同じ生成器を使い、同じシードなら同じ乱数列になるようにする
+pub fn make_rng(seed: u64) -> impl Rng {
+    rngs::StdRng::seed_from_u64(seed)
+}
+
+// 盤面生成のシードを決める。Noneの場合はランダムに選ぶ
+// 選んだシードを盤面に保存しておけば、同じ盤面をあとから再現できる
+// set_global_seedを呼んだ後は、その乱数生成器からシードを選ぶ
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| match GLOBAL_RNG.lock().unwrap().as_mut() {
+        Some(rng) => rng.gen(),
+        None => thread_rng().gen(),
+    })
+}
+
+// シードを指定しない盤面生成で使う乱数生成器をプロセス全体で固定する
+// 呼んだ後は、newにNoneを渡して生成する盤面の並びが毎回同じになる。同じ値で呼び直すと最初の盤面から繰り返す
+pub fn set_global_seed(seed: u64) {
+    *GLOBAL_RNG.lock().unwrap() = Some(rngs::StdRng::seed_from_u64(seed));
+}
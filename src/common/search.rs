@@ -0,0 +1,214 @@
+// stdを使わない探索の中核
+// 盤面の操作はGameトレイト、時間の計測はClockトレイトに任せるので、allocがあればno_stdの環境でもビルドできる
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::common::time_keeper::{Clock, TimeKeeper};
+
+// 探索アルゴリズムから盤面を扱うための共通インターフェース
+// 行動の型をゲームごとに決められるようにし、
+// 上下左右の4方向以外の移動やキャラクターの配置といった行動も表現できるようにする。
+pub trait Game: Clone {
+    type Action: Copy;
+
+    // [どのゲームでも実装する] : ゲームの終了判定
+    fn is_done(&self) -> bool;
+    // [どのゲームでも実装する] : 現在の状況でプレイヤーが可能な行動を全て取得する
+    fn legal_actions(&self) -> Vec<Self::Action>;
+    // [どのゲームでも実装する] : 指定したactionでゲームを1ターン進める
+    fn advance(&mut self, action: Self::Action);
+    // [どのゲームでも実装する] : 探索用の盤面評価をする
+    fn evaluate(&self) -> i64;
+}
+
+// 任意のゲームについて1手先の評価が最も高い行動を選ぶ
+// 合法手がない場合はNoneを返す
+// 作業用の盤面は1つだけ複製し、行動ごとにclone_fromで元の盤面に戻して使い回す
+pub fn greedy_action<G: Game>(state: &G) -> Option<G::Action> {
+    let mut best: Option<(i64, G::Action)> = None;
+    let mut next_state = state.clone();
+
+    for action in state.legal_actions() {
+        next_state.clone_from(state);
+        next_state.advance(action);
+        let score = next_state.evaluate();
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, action));
+        }
+    }
+
+    best.map(|(_, action)| action)
+}
+
+// 任意のゲームと時計でビームサーチをして、最初の行動を返す
+// ゲームが終わるか時間制限を超えるまで1ターンずつビームを広げ、最後に評価し終えた深さで最も評価の高い盤面の最初の行動を選ぶ
+// 評価が同じ盤面は合法手の順番を保つので、時計が同じ時刻を返せば結果も同じになる
+// 合法手がない場合はNoneを返す
+pub fn beam_search_action<G: Game, C: Clock>(
+    state: &G,
+    beam_width: usize,
+    time_keeper: &TimeKeeper<C>,
+) -> Option<G::Action> {
+    let beam_width = beam_width.max(1);
+    // (盤面, 最初の行動)
+    let mut beam: Vec<(G, G::Action)> = Vec::new();
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        beam.push((next_state, action));
+    }
+
+    let mut best_action = None;
+    while !beam.is_empty() {
+        beam.sort_by_cached_key(|(next_state, _)| Reverse(next_state.evaluate()));
+        beam.truncate(beam_width);
+        best_action = Some(beam[0].1);
+        if beam[0].0.is_done() {
+            break;
+        }
+
+        let mut next_beam = Vec::new();
+        for (now_state, first_action) in &beam {
+            if time_keeper.is_time_over() {
+                return best_action;
+            }
+            if now_state.is_done() {
+                continue;
+            }
+            for action in now_state.legal_actions() {
+                let mut next_state = now_state.clone();
+                next_state.advance(action);
+                next_beam.push((next_state, *first_action));
+            }
+        }
+        beam = next_beam;
+    }
+
+    best_action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::time_keeper::MockClock;
+    use core::time::Duration;
+
+    // 1を続けて選ぶと大きく得点できるが、最初の1は得点にならない数直線のゲーム
+    // 1手先しか見ない貪欲法は毎ターン0を選んでしまう
+    #[derive(Clone)]
+    struct Streak {
+        turn: usize,
+        end_turn: usize,
+        last: Option<u8>,
+        score: i64,
+    }
+
+    impl Streak {
+        fn new(end_turn: usize) -> Self {
+            Streak { turn: 0, end_turn, last: None, score: 0 }
+        }
+    }
+
+    impl Game for Streak {
+        type Action = u8;
+
+        fn is_done(&self) -> bool {
+            self.turn == self.end_turn
+        }
+        fn legal_actions(&self) -> Vec<u8> {
+            if self.is_done() {
+                Vec::new()
+            } else {
+                alloc::vec![0, 1]
+            }
+        }
+        fn advance(&mut self, action: u8) {
+            self.score += match (action, self.last) {
+                (0, _) => 1,
+                (_, Some(1)) => 5,
+                _ => 0,
+            };
+            self.last = Some(action);
+            self.turn += 1;
+        }
+        fn evaluate(&self) -> i64 {
+            self.score
+        }
+    }
+
+    fn play_with_beam(clock: &MockClock, end_turn: usize) -> i64 {
+        let mut state = Streak::new(end_turn);
+        while !state.is_done() {
+            let time_keeper = TimeKeeper::with_clock(clock, Duration::from_millis(10));
+            let action = beam_search_action(&state, 2, &time_keeper).unwrap();
+            state.advance(action);
+        }
+        state.score
+    }
+
+    #[test]
+    fn beam_looks_past_the_greedy_choice() {
+        let state = Streak::new(3);
+        assert_eq!(greedy_action(&state), Some(0));
+
+        let clock = MockClock::new();
+        let time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+        assert_eq!(beam_search_action(&state, 2, &time_keeper), Some(1));
+        assert_eq!(play_with_beam(&clock, 3), 10);
+    }
+
+    // 時刻を読むたびに時間切れになる時計では1手先までしか読めないので、貪欲法と同じ行動になる
+    #[test]
+    fn time_over_falls_back_to_the_first_depth() {
+        let state = Streak::new(3);
+        let clock = MockClock::ticking(10);
+        let time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+        assert_eq!(beam_search_action(&state, 2, &time_keeper), greedy_action(&state));
+        assert_eq!(play_with_beam(&MockClock::ticking(10), 3), 3);
+    }
+
+    #[test]
+    fn done_state_has_no_action() {
+        let state = Streak::new(0);
+        let clock = MockClock::new();
+        let time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+        assert_eq!(greedy_action(&state), None);
+        assert_eq!(beam_search_action(&state, 2, &time_keeper), None);
+    }
+
+    // ビーム幅が十分に広ければ全探索と同じなので、小さな迷路では全探索で求めた最高スコアに届く
+    #[cfg(feature = "std")]
+    #[test]
+    fn wide_beam_reaches_the_exhaustive_optimum_on_small_mazes() {
+        use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+
+        fn best_score(state: &MazeState) -> i64 {
+            if Game::is_done(state) {
+                return state.evaluate();
+            }
+            Game::legal_actions(state)
+                .into_iter()
+                .map(|action| {
+                    let mut next_state = state.clone();
+                    Game::advance(&mut next_state, action);
+                    best_score(&next_state)
+                })
+                .max()
+                .unwrap_or(state.evaluate())
+        }
+
+        let clock = MockClock::new();
+        for seed in 0..10 {
+            let mut state = MazeState::new(MazeConfig::new(3, 3, 4), Some(seed));
+            let optimum = best_score(&state);
+            while !Game::is_done(&state) {
+                let time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+                let Some(action) = beam_search_action(&state, usize::MAX, &time_keeper) else {
+                    break;
+                };
+                Game::advance(&mut state, action);
+            }
+            assert_eq!(state.evaluate(), optimum, "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::chapter3::BeamSearchWithTime05::beam_search_action_with_evaluator;
+use crate::chapter3::ChokudaiSearchWithTime07::chokudai_search_action_with_evaluator;
+use crate::chapter3::MazeState00::{Evaluator, MazeConfig, MazeState, default_evaluator};
+use crate::chapter3::MctsAction::mcts_action_with_time_threshold;
+use crate::common::game::greedy_action;
+use crate::common::stats::ScoreSummary;
+use crate::common::time_keeper::TimeKeeper;
+
+// 探索に使うアルゴリズム
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Algo {
+    Greedy,
+    Beam,
+    Chokudai,
+    Mcts,
+}
+
+// ビームサーチとchokudaiサーチで使う盤面評価
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EvaluatorKind {
+    GameScore,  // ゲーム上で実際に得たスコア
+    Potential,  // スコアに残っているポイントへの近さを加える
+    Horizon,    // 3歩以内で取れるポイントを距離に応じて割り引いて加える
+}
+
+impl EvaluatorKind {
+    pub fn evaluator(self) -> Evaluator {
+        match self {
+            EvaluatorKind::GameScore => default_evaluator,
+            EvaluatorKind::Potential => |state| state.game_score + state.manhattan_potential(),
+            EvaluatorKind::Horizon => |state| state.horizon_score(3),
+        }
+    }
+}
+
+// 実験の設定。結果と一緒に保存しておくと、同じ設定で実験をやり直せる
+// 盤面生成シードを seed, seed + 1, ..., seed + game_number - 1 としてgame_number回プレイする
+// beam_widthとbeam_depthを使わないアルゴリズムでは、その値は無視する
+// 制限時間内に探索を終えられない場合は、同じ設定でも実行するたびに結果が変わることがある
+// MCTSは乱数のシードを固定しないので、常に実行するたびに結果が変わる。結果を再現したい場合は、貪欲法か、制限時間内に最後まで探索できるビームサーチを使う
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SearchParams {
+    pub algo: Algo,
+    pub beam_width: usize,
+    pub beam_depth: usize,
+    pub time_ms: usize,
+    pub seed: u64,
+    pub evaluator: EvaluatorKind,
+    pub config: MazeConfig,
+    pub game_number: usize,
+}
+
+impl SearchParams {
+    // 設定に従って行動を決定する
+    pub fn select_action(&self, state: &MazeState) -> usize {
+        let evaluator = self.evaluator.evaluator();
+        match self.algo {
+            Algo::Greedy => greedy_action(state).expect("state must not be done"),
+            Algo::Beam => beam_search_action_with_evaluator(state, self.beam_width, &TimeKeeper::new(self.time_ms), evaluator).0,
+            Algo::Chokudai => chokudai_search_action_with_evaluator(state, self.beam_width, self.beam_depth, self.time_ms, evaluator).0,
+            Algo::Mcts => mcts_action_with_time_threshold(state, self.time_ms),
+        }
+    }
+
+    // JSONの文字列にする
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("SearchParams is always serializable")
+    }
+
+    // to_jsonで保存した設定を復元する
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<SearchParams, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+}
+
+// 設定に従ってgame_number回プレイし、スコアの分布を返す
+pub fn run(params: &SearchParams) -> ScoreSummary {
+    let mut scores = Vec::with_capacity(params.game_number);
+    for i in 0..params.game_number as u64 {
+        let mut state = MazeState::new(params.config, Some(params.seed + i));
+        while !state.is_done() {
+            let action = params.select_action(&state);
+            state.advance(action).expect("action must be legal");
+        }
+        scores.push(state.game_score);
+    }
+    ScoreSummary::from_scores(&scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(algo: Algo) -> SearchParams {
+        SearchParams {
+            algo,
+            beam_width: 3,
+            beam_depth: 0,
+            time_ms: 10000,
+            seed: 7,
+            evaluator: EvaluatorKind::Potential,
+            config: MazeConfig { wall_ratio: 0.1, ..MazeConfig::new(5, 5, 10) },
+            game_number: 5,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip_keeps_every_field() {
+        let params = params(Algo::Chokudai);
+        let json = params.to_json();
+        let restored = SearchParams::from_json(&json).unwrap();
+        assert_eq!(restored.to_json(), json);
+        assert_eq!(restored.algo, Algo::Chokudai);
+        assert_eq!(restored.evaluator, EvaluatorKind::Potential);
+        assert_eq!((restored.beam_width, restored.seed, restored.game_number), (3, 7, 5));
+        assert_eq!(restored.config.wall_ratio, 0.1);
+    }
+
+    // 貪欲法や、制限時間内に最後まで探索できるビームサーチは計算量が決まっているので、やり直しても同じ結果になる
+    #[test]
+    fn fixed_work_settings_reproduce_the_same_scores() {
+        for algo in [Algo::Greedy, Algo::Beam] {
+            let params = params(algo);
+            assert_eq!(format!("{:?}", run(&params)), format!("{:?}", run(&params)), "{:?}", algo);
+        }
+    }
+}
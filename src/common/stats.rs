@@ -0,0 +1,131 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+// 探索でどれだけ計算したかを記録する
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    pub nodes_expanded: u64,   // 探索中にadvanceを呼んだ回数
+    pub depth_reached: usize,  // 探索できた最も深いターン数
+    pub elapsed_ms: u128,      // 探索にかかった時間(ms)
+}
+
+// 複数回プレイしたスコアの分布をまとめる
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreSummary {
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    pub std_dev: f64,  // 標本ではなく母集団の標準偏差
+    pub p10: i64,      // 10パーセンタイル
+    pub p50: i64,      // 中央値
+    pub p90: i64,      // 90パーセンタイル
+}
+
+impl ScoreSummary {
+    // 1回以上プレイしたスコアから分布をまとめる
+    // パーセンタイルは昇順に並べたスコアのうち、(n - 1) * p / 100 番目に最も近いものとする
+    pub fn from_scores<T: Copy + Into<i64>>(scores: &[T]) -> Self {
+        assert!(!scores.is_empty(), "scores must not be empty");
+        let mut sorted: Vec<i64> = scores.iter().map(|&score| score.into()).collect();
+        sorted.sort_unstable();
+
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<i64>() as f64 / n;
+        let variance = sorted.iter().map(|&score| (score as f64 - mean).powi(2)).sum::<f64>() / n;
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p / 100.0).round() as usize];
+
+        ScoreSummary {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            p10: percentile(10.0),
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+        }
+    }
+}
+
+impl fmt::Display for ScoreSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.2}, std {:.2}, min {}, p10 {}, p50 {}, p90 {}, max {}",
+            self.mean, self.std_dev, self.min, self.p10, self.p50, self.p90, self.max
+        )
+    }
+}
+
+// アルゴリズムの名前とスコアの分布を、1行に1アルゴリズムずつCSVとしてpathに書き出す
+// 表計算ソフトでビーム幅とスコアの関係などをグラフにするためのもの
+// 名前にカンマや引用符が含まれる場合は引用符で囲み、引用符は2つ重ねる
+pub fn export_scores_csv(path: &str, results: &[(String, ScoreSummary)]) -> io::Result<()> {
+    let mut csv = String::from("name,mean,min,max,std,p10,p50,p90\n");
+    for (name, summary) in results {
+        csv += &format!(
+            "{},{:.2},{},{},{:.2},{},{},{}\n",
+            csv_field(name),
+            summary.mean,
+            summary.min,
+            summary.max,
+            summary.std_dev,
+            summary.p10,
+            summary.p50,
+            summary.p90
+        );
+    }
+    fs::write(path, csv)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_of_one_to_ten() {
+        let scores: Vec<i32> = (1..=10).collect();
+        let summary = ScoreSummary::from_scores(&scores);
+        assert_eq!((summary.min, summary.max), (1, 10));
+        assert_eq!(summary.mean, 5.5);
+        assert!((summary.std_dev - 8.25f64.sqrt()).abs() < 1e-9);
+        // (10 - 1) * p / 100 番目を四捨五入した添字: 0.9 -> 1, 4.5 -> 5, 8.1 -> 8
+        assert_eq!((summary.p10, summary.p50, summary.p90), (2, 6, 9));
+    }
+
+    #[test]
+    fn summary_of_a_single_score() {
+        let summary = ScoreSummary::from_scores(&[-3i32]);
+        assert_eq!((summary.min, summary.max, summary.p10, summary.p50, summary.p90), (-3, -3, -3, -3, -3));
+        assert_eq!((summary.mean, summary.std_dev), (-3.0, 0.0));
+    }
+
+    // 見出しの行と結果ごとに1行を書き出し、カンマや引用符を含む名前は引用符で囲む
+    #[test]
+    fn export_scores_csv_writes_a_header_and_one_row_per_result() {
+        let results = vec![
+            ("beam".to_string(), ScoreSummary::from_scores(&(1..=10).collect::<Vec<i32>>())),
+            ("a,b".to_string(), ScoreSummary::from_scores(&[5])),
+            ("say \"hi\"".to_string(), ScoreSummary::from_scores(&[0, 2])),
+        ];
+        let path = std::env::temp_dir().join(format!("thunder_rust_scores_{}.csv", std::process::id()));
+        export_scores_csv(path.to_str().unwrap(), &results).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + results.len());
+        assert_eq!(lines[0], "name,mean,min,max,std,p10,p50,p90");
+        assert_eq!(lines[1], "beam,5.50,1,10,2.87,2,6,9");
+        assert_eq!(lines[2], "\"a,b\",5.00,5,5,0.00,5,5,5");
+        assert!(lines[3].starts_with("\"say \"\"hi\"\"\",1.00,0,2,"));
+    }
+}
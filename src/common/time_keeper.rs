@@ -0,0 +1,260 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+// 時間制限の判定に使う時計
+// 組み込み環境やWASMのようにInstantを使えない環境でも、経過時間を返す時計を用意すれば探索を使えるようにする
+pub trait Clock {
+    // 基準の時刻からの経過時間(ms)
+    fn now_ms(&self) -> u64;
+
+    // 基準の時刻からの経過時間。1ms未満まで測れる時計は上書きする
+    fn now(&self) -> Duration {
+        Duration::from_millis(self.now_ms())
+    }
+}
+
+// std::time::Instantで時間を測る時計
+#[cfg(feature = "std")]
+pub struct StdClock {
+    origin: Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> Self {
+        StdClock { origin: Instant::now() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        StdClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_ms(&self) -> u64 {
+        self.origin.elapsed().as_millis() as u64
+    }
+
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+// ゲーム全体の持ち時間
+struct GameBudget {
+    start_time: Duration,
+    total: Duration,
+    remaining_turns: usize,  // まだ時間を割り当てていないターン数
+}
+
+// 時間を管理する構造体
+// 時計を指定しない場合はStdClockを使う
+#[cfg(feature = "std")]
+pub struct TimeKeeper<C: Clock = StdClock> {
+    clock: C,
+    start_time: Duration,
+    time_threshold: Duration,
+    game_budget: Option<GameBudget>,
+}
+
+// no_stdではStdClockがないので、型パラメータの既定値を持たない同じ構造体にする
+#[cfg(not(feature = "std"))]
+pub struct TimeKeeper<C: Clock> {
+    clock: C,
+    start_time: Duration,
+    time_threshold: Duration,
+    game_budget: Option<GameBudget>,
+}
+
+#[cfg(feature = "std")]
+impl TimeKeeper<StdClock> {
+    // 時間制限をミリ秒単位で指定してインスタンスをつくる。
+    pub fn new(time_threshold: usize) -> Self {
+        TimeKeeper::from_duration(Duration::from_millis(time_threshold as u64))
+    }
+
+    // 時間制限をDurationで指定してインスタンスをつくる。1ms未満の制限も指定できる。
+    pub fn from_duration(time_threshold: Duration) -> Self {
+        TimeKeeper::with_clock(StdClock::new(), time_threshold)
+    }
+
+    // ゲーム全体の持ち時間とターン数を指定してインスタンスをつくる。
+    // resetするたびに、残りの持ち時間を残りのターン数で割った時間をそのターンの時間制限にする。
+    // 前のターンで時間を使いすぎた場合は、以降のターンの時間制限が短くなる。
+    pub fn with_elapsed_budget(total: Duration, turns: usize) -> Self {
+        TimeKeeper::with_clock_and_budget(StdClock::new(), total, turns)
+    }
+}
+
+impl<C: Clock> TimeKeeper<C> {
+    // 時計と時間制限を指定してインスタンスをつくる。
+    pub fn with_clock(clock: C, time_threshold: Duration) -> Self {
+        let start_time = clock.now();
+        TimeKeeper {
+            clock,
+            start_time,
+            time_threshold,
+            game_budget: None,
+        }
+    }
+
+    // 時計とゲーム全体の持ち時間、ターン数を指定してインスタンスをつくる。with_elapsed_budgetと同じように時間を割り振る。
+    pub fn with_clock_and_budget(clock: C, total: Duration, turns: usize) -> Self {
+        let start_time = clock.now();
+        let mut time_keeper = TimeKeeper {
+            clock,
+            start_time,
+            time_threshold: Duration::ZERO,
+            game_budget: Some(GameBudget {
+                start_time,
+                total,
+                remaining_turns: turns,
+            }),
+        };
+        time_keeper.reset();
+        time_keeper
+    }
+
+    // 計測を始め直す。次のターンの探索を始める前に呼ぶ。
+    pub fn reset(&mut self) {
+        self.start_time = self.clock.now();
+        if let Some(game_budget) = &mut self.game_budget {
+            let used = self.start_time.saturating_sub(game_budget.start_time);
+            let remaining = game_budget.total.saturating_sub(used);
+            self.time_threshold = remaining / game_budget.remaining_turns.max(1) as u32;
+            game_budget.remaining_turns = game_budget.remaining_turns.saturating_sub(1);
+        }
+    }
+
+    // インスタンス生成した時(resetした場合はその時)から時間制限を超過したか判定する。
+    pub fn is_time_over(&self) -> bool {
+        self.elapsed() >= self.time_threshold
+    }
+
+    // インスタンス生成した時(resetした場合はその時)からの経過時間
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.start_time)
+    }
+
+    // 時間制限までの残り時間。超過している場合は0を返す。
+    pub fn remaining(&self) -> Duration {
+        self.time_threshold.saturating_sub(self.elapsed())
+    }
+}
+
+// テストから時刻を進める時計
+// stepを指定すると、時刻を読むたびにstep(ms)ずつ進むので、壁時計に頼らずに時間切れを再現できる
+#[cfg(test)]
+pub(crate) struct MockClock {
+    now: core::cell::Cell<u64>,
+    step: u64,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub(crate) fn new() -> Self {
+        MockClock::ticking(0)
+    }
+
+    pub(crate) fn ticking(step: u64) -> Self {
+        MockClock { now: core::cell::Cell::new(0), step }
+    }
+
+    pub(crate) fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms);
+    }
+}
+
+#[cfg(test)]
+impl Clock for &MockClock {
+    fn now_ms(&self) -> u64 {
+        let now = self.now.get();
+        self.now.set(now + self.step);
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use std::thread;
+
+    // sleepは指定した時間より長く眠ることがあるので、上限には余裕を持たせる
+    #[cfg(feature = "std")]
+    const TOLERANCE: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn mock_clock_expires_exactly_at_the_threshold() {
+        let clock = MockClock::new();
+        let mut time_keeper = TimeKeeper::with_clock(&clock, Duration::from_millis(10));
+        clock.advance(9);
+        assert!(!time_keeper.is_time_over());
+        assert_eq!(time_keeper.remaining(), Duration::from_millis(1));
+        clock.advance(1);
+        assert!(time_keeper.is_time_over());
+        assert_eq!(time_keeper.elapsed(), Duration::from_millis(10));
+
+        time_keeper.reset();
+        assert!(!time_keeper.is_time_over());
+        assert_eq!(time_keeper.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn mock_clock_budget_is_shared_by_the_remaining_turns() {
+        let clock = MockClock::new();
+        let mut time_keeper = TimeKeeper::with_clock_and_budget(&clock, Duration::from_millis(100), 4);
+        assert_eq!(time_keeper.remaining(), Duration::from_millis(25));
+
+        // 最初のターンで40ms使うと、残りの60msを3ターンで分ける
+        clock.advance(40);
+        time_keeper.reset();
+        assert_eq!(time_keeper.remaining(), Duration::from_millis(20));
+
+        clock.advance(20);
+        time_keeper.reset();
+        assert_eq!(time_keeper.remaining(), Duration::from_millis(20));
+
+        // 持ち時間を使い切った後は時間制限が0になる
+        clock.advance(100);
+        time_keeper.reset();
+        assert!(time_keeper.is_time_over());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn sub_millisecond_threshold_expires() {
+        let time_keeper = TimeKeeper::from_duration(Duration::from_micros(500));
+        thread::sleep(Duration::from_millis(1));
+        assert!(time_keeper.is_time_over());
+        assert_eq!(time_keeper.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn elapsed_follows_sleep() {
+        let time_keeper = TimeKeeper::new(50);
+        thread::sleep(Duration::from_millis(20));
+        let elapsed = time_keeper.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(20) + TOLERANCE, "{:?}", elapsed);
+
+        thread::sleep(Duration::from_millis(40));
+        assert!(time_keeper.is_time_over());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn reset_clears_a_timeout() {
+        let mut time_keeper = TimeKeeper::new(20);
+        thread::sleep(Duration::from_millis(30));
+        assert!(time_keeper.is_time_over());
+        time_keeper.reset();
+        assert!(!time_keeper.is_time_over());
+        assert!(time_keeper.elapsed() < TOLERANCE);
+    }
+}
@@ -0,0 +1,44 @@
+// stdフィーチャーを無効にした場合は、no_stdでも使える時間管理と、Gameトレイトに対する貪欲法・ビームサーチだけを含める
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod common;
+#[cfg(feature = "std")]
+pub mod chapter3;
+#[cfg(feature = "std")]
+pub mod chapter4;
+#[cfg(feature = "std")]
+pub mod chapter5;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "pyo3")]
+pub mod python;
+
+// よく使う型と探索関数はクレート直下から使えるようにしておく
+#[cfg(feature = "std")]
+pub use chapter3::MazeState00::{default_evaluator, random_action, Coord, Evaluator, MazeConfig, MazeState, Movement};
+#[cfg(feature = "std")]
+pub use chapter3::AStarAction::a_star_action;
+#[cfg(feature = "std")]
+pub use chapter3::BeamSearchWithTime05::{
+    beam_search_action_with_evaluator, beam_search_action_with_stats, beam_search_action_with_time_threshold,
+};
+#[cfg(feature = "std")]
+pub use chapter3::ChokudaiSearchWithTime07::{chokudai_search_action_wirh_time_threshold, chokudai_search_actions};
+#[cfg(feature = "std")]
+pub use chapter3::EpsilonGreedy::epsilon_greedy_action;
+#[cfg(feature = "std")]
+pub use chapter3::MctsAction::{mcts_action, mcts_action_with_time_threshold};
+#[cfg(feature = "std")]
+pub use chapter3::ThunderSearch::{thunder_search_action, thunder_search_action_with_time_threshold};
+#[cfg(feature = "std")]
+pub use chapter4::SimulatedAnnealing02::{hill_climb, simulated_annealing, AutoMoveMazeState, CoolingSchedule};
+#[cfg(feature = "std")]
+pub use common::game::GameResult;
+#[cfg(feature = "std")]
+pub use common::rng::set_global_seed;
+#[cfg(feature = "std")]
+pub use common::stats::SearchStats;
+pub use common::search::{beam_search_action, greedy_action, Game};
+pub use common::time_keeper::{Clock, TimeKeeper};
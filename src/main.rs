@@ -1,7 +1,10 @@
-mod chapter3;
-mod chapter4;
+#[allow(unused_imports)]
+use thunder_rust::{chapter3, chapter4, chapter5};
 
 fn main() {
+    // loggingフィーチャーが有効な場合は RUST_LOG=debug などで探索の途中経過を表示する
+    thunder_rust::common::logging::init_logger();
+
     // chapter3::MazeState00::main();
     // chapter3::Greedy01::main();
     // chapter3::TestRandomGame02::main();
@@ -10,8 +13,14 @@ fn main() {
     // chapter3::BeamSearchWithTime05::main();
     // chapter3::ChokudaiSearch06::main();
     // chapter3::ChokudaiSearchWithTime07::main();
+    // chapter3::MctsAction::main();
+    // chapter3::AStarAction::main();
+    // chapter3::ThunderSearch::main();
+    // chapter3::EpsilonGreedy::main();
 
     // chapter4::AutoMoveMazeState00::main();
     // chapter4::HillClimb01::main();
     chapter4::SimulatedAnnealing02::main();
+
+    // chapter5::AlternateMazeState::main();
 }
@@ -0,0 +1,54 @@
+// Pythonから探索を呼び出すためのPyO3のモジュール
+// 探索や盤面の処理は既存の関数をそのまま使い、ここでは結果をPythonの値にして返すだけにする
+//
+// 拡張モジュールのビルド例:
+// cargo rustc --release --lib --features pyo3,pyo3/extension-module --crate-type cdylib
+// できたlibthunder_rust.soをthunder_rust.soという名前でPythonのパスに置くと import thunder_rust できる
+use pyo3::prelude::*;
+
+use crate::chapter3::BeamSearchWithTime05::beam_search_action_with_time_threshold;
+use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+use crate::common::game::greedy_action;
+
+// 迷路の高さと幅、ゲーム終了ターン
+const CONFIG: MazeConfig = MazeConfig::new(30, 30, 100);
+
+// ビーム幅と1手あたりの制限時間(ms)を指定してビームサーチで1ゲームプレイし、最終スコアを返す
+#[pyfunction]
+fn play_beam(seed: u64, width: usize, time_ms: usize) -> i32 {
+    let mut state = MazeState::new(CONFIG, Some(seed));
+    while !state.is_done() {
+        let action = beam_search_action_with_time_threshold(&state, width, time_ms);
+        state.advance(action).expect("action must be legal");
+    }
+    state.game_score
+}
+
+// 貪欲法で1ゲームプレイし、最終スコアを返す
+#[pyfunction]
+fn play_greedy(seed: u64) -> i32 {
+    let mut state = MazeState::new(CONFIG, Some(seed));
+    while !state.is_done() {
+        match greedy_action(&state) {
+            Some(action) => state.advance(action).expect("action must be legal"),
+            None => break,  // 合法手がなければそこで打ち切る
+        }
+    }
+    state.game_score
+}
+
+// 初期盤面のポイントを高さ * 幅の2次元リストで返す
+// numpy.array()に渡すとそのまま配列にできる
+#[pyfunction]
+fn maze_to_numpy(seed: u64) -> Vec<Vec<i32>> {
+    let state = MazeState::new(CONFIG, Some(seed));
+    state.points.chunks(CONFIG.w).map(|row| row.to_vec()).collect()
+}
+
+#[pymodule]
+fn thunder_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(play_beam, m)?)?;
+    m.add_function(wrap_pyfunction!(play_greedy, m)?)?;
+    m.add_function(wrap_pyfunction!(maze_to_numpy, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+// ブラウザから迷路を動かすためのwasm-bindgenのラッパー
+// 探索や盤面の処理はMazeStateのものをそのまま使い、ここではJavaScriptとの値の受け渡しだけを行う
+use wasm_bindgen::prelude::*;
+
+use crate::chapter3::MazeState00::{MazeConfig, MazeState};
+use crate::common::game::greedy_action;
+
+// JavaScriptに渡す迷路
+#[wasm_bindgen]
+pub struct JsMaze {
+    state: MazeState,
+}
+
+#[wasm_bindgen]
+impl JsMaze {
+    // 現在のターン
+    #[wasm_bindgen(getter)]
+    pub fn turn(&self) -> usize {
+        self.state.turn
+    }
+
+    // ゲーム上で実際に得たスコア
+    #[wasm_bindgen(getter)]
+    pub fn score(&self) -> i32 {
+        self.state.game_score
+    }
+
+    // ゲームの終了判定
+    #[wasm_bindgen(js_name = isDone)]
+    pub fn is_done(&self) -> bool {
+        self.state.is_done()
+    }
+}
+
+// シードと迷路の高さと幅、ゲーム終了ターンを指定して迷路を生成する
+#[wasm_bindgen]
+pub fn new_maze(seed: u64, h: usize, w: usize, end_turn: usize) -> JsMaze {
+    JsMaze { state: MazeState::new(MazeConfig::new(h, w, end_turn), Some(seed)) }
+}
+
+// 貪欲法で1ターン進める。ゲームが終わっている場合は何もせずfalseを返す
+#[wasm_bindgen]
+pub fn greedy_step(maze: &mut JsMaze) -> bool {
+    if maze.state.is_done() {
+        return false;
+    }
+    match greedy_action(&maze.state) {
+        Some(action) => maze.state.advance(action).is_ok(),
+        None => false,
+    }
+}
+
+// 現在のゲーム状況をDisplayと同じ形式の文字列にする
+#[wasm_bindgen]
+pub fn render(maze: &JsMaze) -> String {
+    maze.state.to_string()
+}
@@ -0,0 +1,27 @@
+// set_global_seedはプロセス全体の状態を変えるので、他のテストと別のバイナリで確かめる
+
+use thunder_rust::{set_global_seed, AutoMoveMazeState, MazeConfig, MazeState};
+
+const CONFIG: MazeConfig = MazeConfig::new(5, 5, 10);
+
+// シードを指定せずに盤面を3つつくり、それぞれのシードと表示を返す
+fn unseeded_boards() -> Vec<(u64, String)> {
+    let a = MazeState::new(CONFIG, None);
+    let b = MazeState::new(CONFIG, None);
+    let c = AutoMoveMazeState::new(None, 3);
+    vec![(a.seed(), a.to_string()), (b.seed(), b.to_string()), (c.seed(), c.to_string())]
+}
+
+#[test]
+fn global_seed_reproduces_unseeded_boards() {
+    set_global_seed(7);
+    let first = unseeded_boards();
+    set_global_seed(7);
+    let second = unseeded_boards();
+    assert_eq!(first, second);
+    // 同じ乱数生成器から続けて選ぶので、1つ目と2つ目の盤面は異なる
+    assert_ne!(first[0], first[1]);
+
+    set_global_seed(8);
+    assert_ne!(unseeded_boards(), first);
+}
@@ -0,0 +1,44 @@
+// クレートの外から、クレート直下で公開している型と関数だけを使ってゲームをプレイする
+
+use thunder_rust::{
+    beam_search_action_with_time_threshold, greedy_action, hill_climb, AutoMoveMazeState, MazeConfig, MazeState, TimeKeeper,
+};
+use thunder_rust::chapter4::SimulatedAnnealing02::NeighborMode;
+use rand::SeedableRng;
+
+const CONFIG: MazeConfig = MazeConfig::new(3, 4, 4);
+
+#[test]
+fn greedy_game_through_the_public_api() {
+    let mut state = MazeState::new(CONFIG, Some(0));
+    let mut turns = 0;
+    while !state.is_done() {
+        let before = state.game_score;
+        let action = greedy_action(&state).expect("an unfinished game has a legal action");
+        state.advance(action).unwrap();
+        // 貪欲法は取れるポイントが最も高いマスに進むので、罠のない盤面ではスコアが減らない
+        assert!(state.game_score >= before);
+        turns += 1;
+    }
+    assert_eq!(turns, CONFIG.end_turn);
+    assert!(state.game_score > 0);
+}
+
+#[test]
+fn beam_search_game_through_the_public_api() {
+    let time_keeper = TimeKeeper::new(10000);
+    let mut state = MazeState::new(CONFIG, Some(0));
+    while !state.is_done() {
+        state.advance(beam_search_action_with_time_threshold(&state, 3, 10000)).unwrap();
+    }
+    assert_eq!(state.turn, CONFIG.end_turn);
+    assert!(!time_keeper.is_time_over());
+}
+
+#[test]
+fn hill_climb_placement_through_the_public_api() {
+    let state = AutoMoveMazeState::new(Some(0), 3);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let placed = hill_climb(&state, 100, NeighborMode::RandomTeleport, &mut rng);
+    assert!(placed.get_score(false) > 0);
+}
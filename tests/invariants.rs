@@ -0,0 +1,42 @@
+// ランダムな盤面でランダムな合法手を選んでプレイし、スコアが取り得る範囲に収まっているか確かめる
+// 最終スコアは、最初に盤面にあったポイントの合計を超えない。罠がなければ0を下回らない
+// 盤面の大きさ、ターン数、壁、罠、移動方向も生成する。ポイントが復活する盤面は上限が変わるので含めない
+
+use proptest::prelude::*;
+use thunder_rust::{MazeConfig, MazeState, Movement};
+
+fn config_strategy() -> impl Strategy<Value = MazeConfig> {
+    (1..=8usize, 1..=8usize, 1..=40usize, any::<bool>(), any::<bool>(), any::<bool>()).prop_map(
+        |(h, w, end_turn, walls, traps, eight_way)| MazeConfig {
+            wall_ratio: if walls { 0.2 } else { 0.0 },
+            trap_ratio: if traps { 0.2 } else { 0.0 },
+            movement: if eight_way { Movement::EightWay } else { Movement::FourWay },
+            ..MazeConfig::new(h, w, end_turn)
+        },
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1000))]
+
+    // choicesのi番目の値で、iターン目に選ぶ合法手を決める
+    #[test]
+    fn game_score_stays_within_the_initial_points(
+        config in config_strategy(),
+        board_seed in any::<u64>(),
+        choices in prop::collection::vec(any::<usize>(), 40),
+    ) {
+        let mut state = MazeState::new(config, Some(board_seed));
+        let initial_points = state.remaining_points();
+
+        let mut turn = 0;
+        while !state.is_done() {
+            let legal_actions = state.legal_actions();
+            state.advance(legal_actions[choices[turn] % legal_actions.len()]).expect("action must be legal");
+            turn += 1;
+        }
+
+        prop_assert!(state.game_score <= initial_points, "score {} exceeds initial points {}", state.game_score, initial_points);
+        prop_assert!(config.trap_ratio > 0.0 || state.game_score >= 0, "score {} is negative without traps", state.game_score);
+    }
+}
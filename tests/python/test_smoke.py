@@ -0,0 +1,30 @@
+# PyO3のモジュールをPythonから呼び出せることを確かめる
+#
+# 拡張モジュールをビルドしてから pytest tests/python で実行する:
+# cargo rustc --release --lib --features pyo3,pyo3/extension-module --crate-type cdylib
+# cp target/release/libthunder_rust.so tests/python/thunder_rust.so
+import pytest
+
+thunder_rust = pytest.importorskip("thunder_rust")
+
+# src/python.rs の迷路の高さと幅
+H = 30
+W = 30
+
+
+def test_play_greedy_is_reproducible():
+    score = thunder_rust.play_greedy(0)
+    assert score > 0
+    assert thunder_rust.play_greedy(0) == score
+
+
+def test_play_beam_scores_at_least_zero():
+    assert thunder_rust.play_beam(0, 2, 1) >= 0
+
+
+def test_maze_to_numpy_returns_the_points_grid():
+    grid = thunder_rust.maze_to_numpy(0)
+    assert len(grid) == H
+    assert all(len(row) == W for row in grid)
+    assert all(0 <= point <= 9 for row in grid for point in row)
+    assert thunder_rust.maze_to_numpy(0) == grid
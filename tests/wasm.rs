@@ -0,0 +1,26 @@
+// wasmのラッパーから迷路を1ターン進められることを確かめる
+// wasm32ではwasm-pack test --headlessでブラウザ上で実行する。それ以外のターゲットでは通常のテストとして実行する
+#![cfg(feature = "wasm")]
+
+use thunder_rust::wasm::{greedy_step, new_maze, render};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test(unsupported = test)]
+fn greedy_step_advances_one_turn() {
+    let mut maze = new_maze(0, 3, 4, 4);
+    let before = render(&maze);
+    assert_eq!(maze.turn(), 0);
+
+    assert!(greedy_step(&mut maze));
+    assert_eq!(maze.turn(), 1);
+    assert!(maze.score() >= 0);
+    assert_ne!(render(&maze), before);
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn greedy_step_stops_at_the_end() {
+    let mut maze = new_maze(0, 3, 4, 4);
+    while greedy_step(&mut maze) {}
+    assert!(maze.is_done());
+    assert_eq!(maze.turn(), 4);
+}